@@ -0,0 +1 @@
+// the EVM round trip of the Solidity verifier lives in tests/
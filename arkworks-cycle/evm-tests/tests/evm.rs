@@ -0,0 +1,72 @@
+use ark_bn254::{Bn254, Fr};
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::Proof;
+use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+use ark_std::test_rng;
+use revm::context::result::{ExecutionResult, Output};
+use revm::context::{Context, TxEnv};
+use revm::database::{CacheDB, EmptyDB};
+use revm::primitives::{keccak256, Bytes, TxKind};
+use revm::{ExecuteCommitEvm, MainBuilder, MainContext};
+use zycle::prover::commitment;
+use zycle::solidity::{solidity_verifier, verifier_calldata, CONTRACT_NAME, VERIFY_PROOF_SELECTOR, VERIFY_PROOF_SIGNATURE};
+use zycle::AcyclicityProverOn;
+
+#[test]
+fn solidity_verifier_runs_on_the_evm() {
+    assert_eq!(keccak256(VERIFY_PROOF_SIGNATURE.as_bytes())[..4], VERIFY_PROOF_SELECTOR);
+
+    let adj_matrix = [[false, true, false], [false, false, true], [false, false, false]];
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = AcyclicityProverOn::<Bn254>::setup(3, &mut rng).unwrap();
+    let (proof, _) = prover.prove(&adj_matrix, &mut rng).unwrap();
+    let hash: Fr = commitment(&adj_matrix).unwrap();
+    let contract = solidity_verifier(verifier.verifying_key()).unwrap();
+
+    // compiled with solar, a Solidity compiler written in Rust, so no solc binary is needed
+    let input = serde_json::json!({
+        "language": "Solidity",
+        "sources": {"ZycleVerifier.sol": {"content": contract}},
+        "settings": {"outputSelection": {"*": {"*": ["evm.bytecode.object"]}}},
+    });
+    let mut opts = solar_cli::CompileOpts::default();
+    opts.unstable.codegen = true;
+    let mut output = Vec::new();
+    solar_cli::standard_json::compile_standard_json(&input.to_string(), opts, None, &mut output);
+    let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+    let bytecode = output["contracts"]["ZycleVerifier.sol"][CONTRACT_NAME]["evm"]["bytecode"]["object"]
+        .as_str()
+        .unwrap_or_else(|| panic!("contract does not compile: {}", output["errors"]));
+    let bytecode: Vec<u8> =
+        (0..bytecode.len()).step_by(2).map(|i| u8::from_str_radix(&bytecode[i..i + 2], 16).unwrap()).collect();
+
+    let mut evm = Context::mainnet().with_db(CacheDB::<EmptyDB>::default()).build_mainnet();
+    let mut nonce = 0;
+    let mut transact = |kind: TxKind, data: Vec<u8>| {
+        let tx = TxEnv::builder().kind(kind).data(Bytes::from(data)).gas_limit(10_000_000).nonce(nonce).build();
+        nonce += 1;
+        evm.transact_commit(tx.unwrap()).unwrap()
+    };
+    let address = match transact(TxKind::Create, bytecode) {
+        ExecutionResult::Success { output: Output::Create(_, Some(address)), .. } => address,
+        result => panic!("deployment failed: {:?}", result),
+    };
+    let mut verify_on_chain = |calldata: Vec<u8>| match transact(TxKind::Call(address), calldata) {
+        ExecutionResult::Success { output: Output::Call(output), .. } => {
+            output[..] == Fr::from(1u64).into_bigint().to_bytes_be()[..]
+        }
+        result => panic!("call failed: {:?}", result),
+    };
+
+    assert!(verify_on_chain(verifier_calldata(&proof, hash)));
+    assert!(!verify_on_chain(verifier_calldata(&proof, hash + Fr::from(1u64))));
+    let tampered = Proof { c: proof.a, ..proof.clone() };
+    assert!(!verify_on_chain(verifier_calldata(&tampered, hash)));
+    // the commitment has to be reduced, hash + r would otherwise pass as well
+    let mut unreduced = hash.into_bigint();
+    unreduced.add_with_carry(&Fr::MODULUS);
+    let mut calldata = verifier_calldata(&proof, hash);
+    let len = calldata.len();
+    calldata[len - 32..].copy_from_slice(&unreduced.to_bytes_be());
+    assert!(!verify_on_chain(calldata));
+}
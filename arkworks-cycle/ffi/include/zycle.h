@@ -0,0 +1,98 @@
+/* Generated by cbindgen from zycle-ffi, do not edit. Regenerate with `cargo test -p zycle-ffi`. */
+
+#ifndef ZYCLE_H
+#define ZYCLE_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ Size of a compressed proof written by zycle_prove.
+ */
+#define ZYCLE_PROOF_BYTES 192
+
+/*
+ Size of a commitment, a scalar field element serialized little-endian.
+ */
+#define ZYCLE_COMMITMENT_BYTES 32
+
+/*
+ Result of every zycle function, the values match the exit codes of the zycle CLI.
+ */
+typedef enum ZycleStatus {
+  ZYCLE_STATUS_OK = 0,
+  /*
+   zycle_verify rejected the proof.
+   */
+  ZYCLE_STATUS_INVALID_PROOF = 1,
+  /*
+   A null pointer, a malformed key, proof or commitment, or a matrix of the wrong size.
+   */
+  ZYCLE_STATUS_BAD_INPUT = 2,
+  ZYCLE_STATUS_INTERNAL_ERROR = 3,
+  /*
+   zycle_prove was given a graph with a cycle, the message names it.
+   */
+  ZYCLE_STATUS_CYCLIC_GRAPH = 4,
+} ZycleStatus;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Writes the commitment of the nodes x nodes adjacency matrix (row-major, non-zero bytes are
+ edges) to the ZYCLE_COMMITMENT_BYTES bytes at commitment_out.
+
+ # Safety
+ adj_matrix must point to nodes * nodes readable bytes and commitment_out to
+ ZYCLE_COMMITMENT_BYTES writable ones.
+ */
+enum ZycleStatus zycle_hash(const uint8_t *adj_matrix, size_t nodes, uint8_t *commitment_out);
+
+/*
+ Proves that the adjacency matrix has no cycles with the proving key file contents in pk,
+ writing the compressed proof to the ZYCLE_PROOF_BYTES bytes at proof_out and the commitment
+ it is checked against to the ZYCLE_COMMITMENT_BYTES bytes at commitment_out. The matrix must
+ have as many nodes as the key was made for.
+
+ # Safety
+ pk must point to pk_len readable bytes, adj_matrix to nodes * nodes, and proof_out and
+ commitment_out to ZYCLE_PROOF_BYTES and ZYCLE_COMMITMENT_BYTES writable bytes.
+ */
+enum ZycleStatus zycle_prove(const uint8_t *pk,
+                             size_t pk_len,
+                             const uint8_t *adj_matrix,
+                             size_t nodes,
+                             uint8_t *proof_out,
+                             uint8_t *commitment_out);
+
+/*
+ Checks a compressed proof against the verifying key (or prepared verifying key) file contents
+ in vk and the ZYCLE_COMMITMENT_BYTES bytes of the commitment, returning ZYCLE_STATUS_OK if
+ the proof is valid and ZYCLE_STATUS_INVALID_PROOF if it is not.
+
+ # Safety
+ vk and proof must point to vk_len and proof_len readable bytes, and commitment to
+ ZYCLE_COMMITMENT_BYTES.
+ */
+enum ZycleStatus zycle_verify(const uint8_t *vk,
+                              size_t vk_len,
+                              const uint8_t *proof,
+                              size_t proof_len,
+                              const uint8_t *commitment);
+
+/*
+ The message of the last failed call on this thread, empty if there was none. The string is
+ owned by zycle and stays valid until the next failing call on the same thread.
+ */
+const char *zycle_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* ZYCLE_H */
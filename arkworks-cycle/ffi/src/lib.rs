@@ -0,0 +1,263 @@
+use std::cell::RefCell;
+use std::ffi::{c_char, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+
+use ark_bls12_381::{fr::Fr, Bls12_381};
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Validate};
+use rand::{rngs::StdRng, SeedableRng};
+
+use zycle::prover::commitment;
+use zycle::{AcyclicityProver, AcyclicityVerifier, ZycleError};
+
+// C ABI over the dense Groth16 circuit on BLS12-381, for calling zycle in-process from C and C++.
+// Every function returns a ZycleStatus, with the message of the last failure on the calling thread
+// kept for zycle_last_error. Panics are caught at the boundary and reported as internal errors.
+// The header is generated with cbindgen into include/zycle.h.
+
+/// Size of a compressed proof written by zycle_prove.
+pub const ZYCLE_PROOF_BYTES: usize = 192;
+/// Size of a commitment, a scalar field element serialized little-endian.
+pub const ZYCLE_COMMITMENT_BYTES: usize = 32;
+
+/// Result of every zycle function, the values match the exit codes of the zycle CLI.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ZycleStatus {
+    Ok = 0,
+    /// zycle_verify rejected the proof.
+    InvalidProof = 1,
+    /// A null pointer, a malformed key, proof or commitment, or a matrix of the wrong size.
+    BadInput = 2,
+    InternalError = 3,
+    /// zycle_prove was given a graph with a cycle, the message names it.
+    CyclicGraph = 4,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+struct Failure {
+    status: ZycleStatus,
+    message: String,
+}
+
+fn bad_input(e: impl ToString) -> Failure {
+    Failure { status: ZycleStatus::BadInput, message: e.to_string() }
+}
+
+fn internal(e: impl ToString) -> Failure {
+    Failure { status: ZycleStatus::InternalError, message: e.to_string() }
+}
+
+fn failure(e: ZycleError) -> Failure {
+    match e {
+        ZycleError::CyclicGraph(cycle) => Failure { status: ZycleStatus::CyclicGraph, message: cycle.to_string() },
+        e if e.is_bad_input() => bad_input(e),
+        e => internal(e),
+    }
+}
+
+// runs the body of an exported function, recording why it failed
+fn run(body: impl FnOnce() -> Result<(), Failure>) -> ZycleStatus {
+    let failure = match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => return ZycleStatus::Ok,
+        Ok(Err(failure)) => failure,
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|msg| msg.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_default();
+            internal(format!("panicked: {}", message))
+        }
+    };
+    // a message with an interior nul is cut there
+    let message = failure.message.split('\0').next().unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = CString::new(message).unwrap_or_default());
+    failure.status
+}
+
+// a buffer passed in from C, refusing null pointers unless it is empty
+unsafe fn input<'a>(ptr: *const u8, len: usize, name: &str) -> Result<&'a [u8], Failure> {
+    match (ptr.is_null(), len) {
+        (_, 0) => Ok(&[]),
+        (true, _) => Err(bad_input(format!("{} is null", name))),
+        (false, _) => Ok(slice::from_raw_parts(ptr, len)),
+    }
+}
+
+unsafe fn output<'a>(ptr: *mut u8, len: usize, name: &str) -> Result<&'a mut [u8], Failure> {
+    if ptr.is_null() {
+        return Err(bad_input(format!("{} is null", name)));
+    }
+    Ok(slice::from_raw_parts_mut(ptr, len))
+}
+
+// nodes x nodes bytes in row-major order, any non-zero byte is an edge
+unsafe fn adjacency_matrix(ptr: *const u8, nodes: usize) -> Result<Vec<Vec<bool>>, Failure> {
+    let len = nodes.checked_mul(nodes).ok_or_else(|| bad_input(format!("{} nodes are too many", nodes)))?;
+    let bytes = input(ptr, len, "adj_matrix")?;
+    Ok(bytes.chunks(nodes.max(1)).map(|row| row.iter().map(|&entry| entry != 0).collect()).collect())
+}
+
+fn write_commitment(hash: Fr, out: &mut [u8]) -> Result<(), Failure> {
+    hash.serialize_compressed(out).map_err(internal)
+}
+
+/// Writes the commitment of the nodes x nodes adjacency matrix (row-major, non-zero bytes are
+/// edges) to the ZYCLE_COMMITMENT_BYTES bytes at commitment_out.
+///
+/// # Safety
+/// adj_matrix must point to nodes * nodes readable bytes and commitment_out to
+/// ZYCLE_COMMITMENT_BYTES writable ones.
+#[no_mangle]
+pub unsafe extern "C" fn zycle_hash(adj_matrix: *const u8, nodes: usize, commitment_out: *mut u8) -> ZycleStatus {
+    run(|| {
+        let adj_matrix = adjacency_matrix(adj_matrix, nodes)?;
+        let out = output(commitment_out, ZYCLE_COMMITMENT_BYTES, "commitment_out")?;
+        write_commitment(commitment(&adj_matrix).map_err(internal)?, out)
+    })
+}
+
+/// Proves that the adjacency matrix has no cycles with the proving key file contents in pk,
+/// writing the compressed proof to the ZYCLE_PROOF_BYTES bytes at proof_out and the commitment
+/// it is checked against to the ZYCLE_COMMITMENT_BYTES bytes at commitment_out. The matrix must
+/// have as many nodes as the key was made for.
+///
+/// # Safety
+/// pk must point to pk_len readable bytes, adj_matrix to nodes * nodes, and proof_out and
+/// commitment_out to ZYCLE_PROOF_BYTES and ZYCLE_COMMITMENT_BYTES writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn zycle_prove(
+    pk: *const u8,
+    pk_len: usize,
+    adj_matrix: *const u8,
+    nodes: usize,
+    proof_out: *mut u8,
+    commitment_out: *mut u8,
+) -> ZycleStatus {
+    run(|| {
+        let prover = AcyclicityProver::from_bytes(input(pk, pk_len, "pk")?, Validate::Yes).map_err(failure)?;
+        let adj_matrix = adjacency_matrix(adj_matrix, nodes)?;
+        let proof_out = output(proof_out, ZYCLE_PROOF_BYTES, "proof_out")?;
+        let commitment_out = output(commitment_out, ZYCLE_COMMITMENT_BYTES, "commitment_out")?;
+        let (proof, public_inputs) = prover.prove(&adj_matrix, &mut StdRng::from_entropy()).map_err(failure)?;
+        proof.serialize_compressed(proof_out).map_err(internal)?;
+        write_commitment(public_inputs[0], commitment_out)
+    })
+}
+
+/// Checks a compressed proof against the verifying key (or prepared verifying key) file contents
+/// in vk and the ZYCLE_COMMITMENT_BYTES bytes of the commitment, returning ZYCLE_STATUS_OK if
+/// the proof is valid and ZYCLE_STATUS_INVALID_PROOF if it is not.
+///
+/// # Safety
+/// vk and proof must point to vk_len and proof_len readable bytes, and commitment to
+/// ZYCLE_COMMITMENT_BYTES.
+#[no_mangle]
+pub unsafe extern "C" fn zycle_verify(
+    vk: *const u8,
+    vk_len: usize,
+    proof: *const u8,
+    proof_len: usize,
+    commitment: *const u8,
+) -> ZycleStatus {
+    run(|| {
+        let verifier = AcyclicityVerifier::from_bytes(input(vk, vk_len, "vk")?, Validate::Yes).map_err(failure)?;
+        let proof = Proof::<Bls12_381>::deserialize_compressed(input(proof, proof_len, "proof")?).map_err(bad_input)?;
+        let commitment = input(commitment, ZYCLE_COMMITMENT_BYTES, "commitment")?;
+        let commitment = Fr::deserialize_compressed(commitment).map_err(bad_input)?;
+        match verifier.verify(&proof, &[commitment]).map_err(failure)? {
+            true => Ok(()),
+            false => Err(Failure { status: ZycleStatus::InvalidProof, message: "proof is invalid".to_string() }),
+        }
+    })
+}
+
+/// The message of the last failed call on this thread, empty if there was none. The string is
+/// owned by zycle and stays valid until the next failing call on the same thread.
+#[no_mangle]
+pub extern "C" fn zycle_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+#[test]
+fn prove_and_verify_through_the_c_abi() {
+    use ark_serialize::Compress;
+    use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+    use std::ffi::CStr;
+    use std::ptr;
+    use zycle::circuit::PositionWidth;
+    use zycle::keys::{write_key, CircuitShape};
+
+    let last_error = || unsafe { CStr::from_ptr(zycle_last_error()) }.to_str().unwrap().to_string();
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = AcyclicityProver::setup(3, &mut rng).unwrap();
+    let shape = CircuitShape::dense(3, PositionWidth::U8);
+    let (mut pk, mut vk) = (Vec::new(), Vec::new());
+    write_key(prover.proving_key(), shape, Compress::Yes, &mut pk).unwrap();
+    write_key(verifier.verifying_key(), shape, Compress::Yes, &mut vk).unwrap();
+    let adj_matrix: [u8; 9] = [0, 1, 0, 0, 0, 1, 0, 0, 0];
+
+    let mut hash = [0u8; ZYCLE_COMMITMENT_BYTES];
+    assert_eq!(unsafe { zycle_hash(adj_matrix.as_ptr(), 3, hash.as_mut_ptr()) }, ZycleStatus::Ok);
+    let rows = [[false, true, false], [false, false, true], [false, false, false]];
+    assert_eq!(Fr::deserialize_compressed(&hash[..]).unwrap(), commitment(&rows).unwrap());
+
+    let mut proof = [0u8; ZYCLE_PROOF_BYTES];
+    let mut proved_hash = [0u8; ZYCLE_COMMITMENT_BYTES];
+    let status = unsafe {
+        zycle_prove(pk.as_ptr(), pk.len(), adj_matrix.as_ptr(), 3, proof.as_mut_ptr(), proved_hash.as_mut_ptr())
+    };
+    assert_eq!(status, ZycleStatus::Ok);
+    assert_eq!(proved_hash, hash);
+    let verify = |proof: &[u8], hash: &[u8]| unsafe {
+        zycle_verify(vk.as_ptr(), vk.len(), proof.as_ptr(), proof.len(), hash.as_ptr())
+    };
+    assert_eq!(verify(&proof, &hash), ZycleStatus::Ok);
+    let mut other_hash = [0u8; ZYCLE_COMMITMENT_BYTES];
+    Fr::from(1u64).serialize_compressed(&mut other_hash[..]).unwrap();
+    assert_eq!(verify(&proof, &other_hash), ZycleStatus::InvalidProof);
+    assert_eq!(last_error(), "proof is invalid");
+
+    // failures come back as codes and messages instead of panics
+    assert_eq!(verify(&proof[1..], &hash), ZycleStatus::BadInput);
+    let status = unsafe { zycle_verify(vk.as_ptr(), 10, proof.as_ptr(), proof.len(), hash.as_ptr()) };
+    assert_eq!(status, ZycleStatus::BadInput);
+    assert_eq!(unsafe { zycle_hash(ptr::null(), 3, hash.as_mut_ptr()) }, ZycleStatus::BadInput);
+    assert_eq!(last_error(), "adj_matrix is null");
+    let cyclic: [u8; 9] = [0, 1, 0, 0, 0, 1, 1, 0, 0];
+    let status = unsafe {
+        zycle_prove(pk.as_ptr(), pk.len(), cyclic.as_ptr(), 3, proof.as_mut_ptr(), proved_hash.as_mut_ptr())
+    };
+    assert_eq!(status, ZycleStatus::CyclicGraph);
+    assert!(last_error().contains("cycle"), "{}", last_error());
+    let status = unsafe {
+        zycle_prove(pk.as_ptr(), pk.len(), adj_matrix.as_ptr(), 2, proof.as_mut_ptr(), proved_hash.as_mut_ptr())
+    };
+    assert_eq!(status, ZycleStatus::BadInput);
+    assert_eq!(last_error(), "expected a 3x3 adjacency matrix");
+    let status = unsafe {
+        zycle_prove(vk.as_ptr(), vk.len(), adj_matrix.as_ptr(), 3, proof.as_mut_ptr(), proved_hash.as_mut_ptr())
+    };
+    assert_eq!(status, ZycleStatus::BadInput);
+}
+
+// the header in include/ is generated, this fails and rewrites it when the ABI changed
+#[test]
+fn header_is_up_to_date() {
+    let crate_dir = env!("CARGO_MANIFEST_DIR");
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).unwrap();
+    let mut header = Vec::new();
+    cbindgen::Builder::new().with_crate(crate_dir).with_config(config).generate().unwrap().write(&mut header);
+    let path = format!("{}/include/zycle.h", crate_dir);
+    if std::fs::read(&path).ok().as_ref() != Some(&header) {
+        std::fs::create_dir_all(format!("{}/include", crate_dir)).unwrap();
+        std::fs::write(&path, header).unwrap();
+        panic!("include/zycle.h was out of date and has been regenerated");
+    }
+}
@@ -0,0 +1,131 @@
+use crate::ahp::indexer::*;
+use crate::ahp::prover::ProverMsg;
+use ark_crypto_primitives::sponge::CryptographicSponge;
+use ark_ff::PrimeField;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{BatchLCProof, PolynomialCommitment};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+/* ************************************************************************* */
+/* ************************************************************************* */
+/* ************************************************************************* */
+
+/// The universal public parameters for the argument system.
+pub type UniversalSRS<F, PC, S> = <PC as PolynomialCommitment<F, DensePolynomial<F>, S>>::UniversalParams;
+
+/* ************************************************************************* */
+/* ************************************************************************* */
+/* ************************************************************************* */
+
+/// Verification key for a specific index (i.e., R1CS matrices).
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct IndexVerifierKey<
+    F: PrimeField,
+    S: CryptographicSponge,
+    PC: PolynomialCommitment<F, DensePolynomial<F>, S>,
+> {
+    /// Stores information about the size of the index, as well as its field of
+    /// definition.
+    pub index_info: IndexInfo<F>,
+    /// Commitments to the indexed polynomials.
+    pub index_comms: Vec<PC::Commitment>,
+    /// The verifier key for this index, trimmed from the universal SRS.
+    pub verifier_key: PC::VerifierKey,
+}
+
+impl<F: PrimeField, S: CryptographicSponge, PC: PolynomialCommitment<F, DensePolynomial<F>, S>> Clone
+    for IndexVerifierKey<F, S, PC>
+{
+    fn clone(&self) -> Self {
+        Self {
+            index_comms: self.index_comms.clone(),
+            index_info: self.index_info,
+            verifier_key: self.verifier_key.clone(),
+        }
+    }
+}
+
+impl<F: PrimeField, S: CryptographicSponge, PC: PolynomialCommitment<F, DensePolynomial<F>, S>>
+    IndexVerifierKey<F, S, PC>
+{
+    /// Iterate over the commitments to indexed polynomials in `self`.
+    pub fn iter(&self) -> impl Iterator<Item = &PC::Commitment> {
+        self.index_comms.iter()
+    }
+}
+
+/* ************************************************************************* */
+/* ************************************************************************* */
+/* ************************************************************************* */
+
+/// Proving key for a specific index (i.e., R1CS matrices).
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct IndexProverKey<
+    F: PrimeField,
+    S: CryptographicSponge,
+    PC: PolynomialCommitment<F, DensePolynomial<F>, S>,
+> {
+    /// The index verifier key.
+    pub index_vk: IndexVerifierKey<F, S, PC>,
+    /// The randomness for the index polynomial commitments.
+    pub index_comm_rands: Vec<PC::Randomness>,
+    /// The index itself.
+    pub index: Index<F>,
+    /// The committer key for this index, trimmed from the universal SRS.
+    pub committer_key: PC::CommitterKey,
+}
+
+impl<F: PrimeField, S: CryptographicSponge, PC: PolynomialCommitment<F, DensePolynomial<F>, S>> Clone
+    for IndexProverKey<F, S, PC>
+{
+    fn clone(&self) -> Self {
+        Self {
+            index_vk: self.index_vk.clone(),
+            index_comm_rands: self.index_comm_rands.clone(),
+            index: self.index.clone(),
+            committer_key: self.committer_key.clone(),
+        }
+    }
+}
+
+/* ************************************************************************* */
+/* ************************************************************************* */
+/* ************************************************************************* */
+
+/// A zkSNARK proof.
+#[derive(CanonicalSerialize, CanonicalDeserialize)]
+pub struct Proof<F: PrimeField, S: CryptographicSponge, PC: PolynomialCommitment<F, DensePolynomial<F>, S>> {
+    /// Commitments to the polynomials produced by the AHP prover.
+    pub commitments: Vec<Vec<PC::Commitment>>,
+    /// Evaluations of these polynomials.
+    pub evaluations: Vec<F>,
+    /// The field elements sent by the prover.
+    pub prover_messages: Vec<ProverMsg<F>>,
+    /// An evaluation proof from the polynomial commitment.
+    pub pc_proof: BatchLCProof<F, PC::BatchProof>,
+}
+
+impl<F: PrimeField, S: CryptographicSponge, PC: PolynomialCommitment<F, DensePolynomial<F>, S>> Proof<F, S, PC> {
+    /// Construct a new proof.
+    pub fn new(
+        commitments: Vec<Vec<PC::Commitment>>,
+        evaluations: Vec<F>,
+        prover_messages: Vec<ProverMsg<F>>,
+        pc_proof: BatchLCProof<F, PC::BatchProof>,
+    ) -> Self {
+        Self { commitments, evaluations, prover_messages, pc_proof }
+    }
+}
+
+impl<F: PrimeField, S: CryptographicSponge, PC: PolynomialCommitment<F, DensePolynomial<F>, S>> Clone
+    for Proof<F, S, PC>
+{
+    fn clone(&self) -> Self {
+        Self {
+            commitments: self.commitments.clone(),
+            evaluations: self.evaluations.clone(),
+            prover_messages: self.prover_messages.clone(),
+            pc_proof: self.pc_proof.clone(),
+        }
+    }
+}
@@ -0,0 +1,336 @@
+//! The Marlin preprocessing zkSNARK for R1CS, ported from ark-marlin 0.3
+//! (<https://github.com/arkworks-rs/marlin>) to arkworks 0.4, which has no release of it.
+//! Timers and the `parallel` feature are dropped. Like upstream it is dual licensed under
+//! MIT and Apache-2.0, see LICENSE-MIT and LICENSE-APACHE, and like upstream it has not been audited.
+//!
+//! Marlin only supports square constraint matrices and public inputs of size 2^n - 1;
+//! the indexer and prover pad the constraint system to get there.
+
+use ark_crypto_primitives::sponge::CryptographicSponge;
+use ark_ff::{PrimeField, UniformRand};
+use ark_poly::{univariate::DensePolynomial, EvaluationDomain, GeneralEvaluationDomain};
+use ark_poly_commit::challenge::ChallengeGenerator;
+use ark_poly_commit::{Evaluations, LabeledCommitment, PCCommitment, PCUniversalParams, PolynomialCommitment};
+use ark_relations::r1cs::ConstraintSynthesizer;
+use ark_std::marker::PhantomData;
+use ark_std::rand::RngCore;
+use digest::Digest;
+
+/// Fiat-Shamir rng that folds each new transcript message into its seed.
+#[macro_use]
+pub mod rng;
+use rng::FiatShamirRng;
+
+mod error;
+pub use error::*;
+
+mod data_structures;
+pub use data_structures::*;
+
+/// Algebraic holographic proof for the R1CS indexed relation.
+pub mod ahp;
+use ahp::EvaluationsProvider;
+pub use ahp::AHPForR1CS;
+
+#[cfg(test)]
+mod test;
+
+/// The compiled argument system.
+pub struct Marlin<F: PrimeField, S: CryptographicSponge, PC: PolynomialCommitment<F, DensePolynomial<F>, S>, D: Digest>(
+    #[doc(hidden)] PhantomData<F>,
+    #[doc(hidden)] PhantomData<S>,
+    #[doc(hidden)] PhantomData<PC>,
+    #[doc(hidden)] PhantomData<D>,
+);
+
+impl<F, S, PC, D> Marlin<F, S, PC, D>
+where
+    F: PrimeField,
+    S: CryptographicSponge,
+    PC: PolynomialCommitment<F, DensePolynomial<F>, S>,
+    D: Digest,
+{
+    /// The personalization string for this protocol. Used to personalize the
+    /// Fiat-Shamir rng.
+    pub const PROTOCOL_NAME: &'static [u8] = b"MARLIN-2019";
+
+    /// Generate the universal prover and verifier keys for the argument system.
+    pub fn universal_setup<R: RngCore>(
+        num_constraints: usize,
+        num_variables: usize,
+        num_non_zero: usize,
+        rng: &mut R,
+    ) -> Result<UniversalSRS<F, PC, S>, Error<PC::Error>> {
+        let max_degree = AHPForR1CS::<F>::max_degree(num_constraints, num_variables, num_non_zero)?;
+        PC::setup(max_degree, None, rng).map_err(Error::from_pc_err)
+    }
+
+    /// Generate the index-specific (i.e., circuit-specific) prover and verifier
+    /// keys. This is a deterministic algorithm that anyone can rerun.
+    #[allow(clippy::type_complexity)]
+    pub fn index<C: ConstraintSynthesizer<F>>(
+        srs: &UniversalSRS<F, PC, S>,
+        c: C,
+    ) -> Result<(IndexProverKey<F, S, PC>, IndexVerifierKey<F, S, PC>), Error<PC::Error>> {
+        let index = AHPForR1CS::index(c)?;
+        if srs.max_degree() < index.max_degree() {
+            return Err(Error::IndexTooLarge);
+        }
+
+        let coeff_support = AHPForR1CS::get_degree_bounds(&index.index_info);
+        // Marlin only needs degree 2 random polynomials
+        let supported_hiding_bound = 1;
+        let (committer_key, verifier_key) =
+            PC::trim(srs, index.max_degree(), supported_hiding_bound, Some(&coeff_support))
+                .map_err(Error::from_pc_err)?;
+
+        let (index_comms, index_comm_rands) =
+            PC::commit(&committer_key, index.iter(), None).map_err(Error::from_pc_err)?;
+
+        let index_comms = index_comms.into_iter().map(|c| c.commitment().clone()).collect();
+        let index_vk = IndexVerifierKey { index_info: index.index_info, index_comms, verifier_key };
+
+        let index_pk = IndexProverKey { index, index_comm_rands, index_vk: index_vk.clone(), committer_key };
+
+        Ok((index_pk, index_vk))
+    }
+
+    /// Create a zkSNARK asserting that the constraint system is satisfied.
+    pub fn prove<C: ConstraintSynthesizer<F>, R: RngCore>(
+        index_pk: &IndexProverKey<F, S, PC>,
+        c: C,
+        zk_rng: &mut R,
+    ) -> Result<Proof<F, S, PC>, Error<PC::Error>> {
+        let prover_init_state = AHPForR1CS::prover_init(&index_pk.index, c)?;
+        let public_input = prover_init_state.public_input();
+        let mut fs_rng =
+            FiatShamirRng::<D>::from_seed(&to_bytes![Self::PROTOCOL_NAME.to_vec(), index_pk.index_vk, public_input]);
+
+        // first round
+
+        let (prover_first_msg, prover_first_oracles, prover_state) =
+            AHPForR1CS::prover_first_round(prover_init_state, zk_rng)?;
+
+        let (first_comms, first_comm_rands) =
+            PC::commit(&index_pk.committer_key, prover_first_oracles.iter(), Some(zk_rng))
+                .map_err(Error::from_pc_err)?;
+
+        fs_rng.absorb(&to_bytes![commitments_of(&first_comms), prover_first_msg]);
+
+        let (verifier_first_msg, verifier_state) =
+            AHPForR1CS::verifier_first_round(index_pk.index_vk.index_info, &mut fs_rng)?;
+
+        // second round
+
+        let (prover_second_msg, prover_second_oracles, prover_state) =
+            AHPForR1CS::prover_second_round(&verifier_first_msg, prover_state, zk_rng);
+
+        let (second_comms, second_comm_rands) =
+            PC::commit(&index_pk.committer_key, prover_second_oracles.iter(), Some(zk_rng))
+                .map_err(Error::from_pc_err)?;
+
+        fs_rng.absorb(&to_bytes![commitments_of(&second_comms), prover_second_msg]);
+
+        let (verifier_second_msg, verifier_state) = AHPForR1CS::verifier_second_round(verifier_state, &mut fs_rng);
+
+        // third round
+
+        let (prover_third_msg, prover_third_oracles) =
+            AHPForR1CS::prover_third_round(&verifier_second_msg, prover_state, zk_rng)?;
+
+        let (third_comms, third_comm_rands) =
+            PC::commit(&index_pk.committer_key, prover_third_oracles.iter(), Some(zk_rng))
+                .map_err(Error::from_pc_err)?;
+
+        fs_rng.absorb(&to_bytes![commitments_of(&third_comms), prover_third_msg]);
+
+        let verifier_state = AHPForR1CS::verifier_third_round(verifier_state, &mut fs_rng);
+
+        // Gather prover polynomials in one vector.
+        let polynomials: Vec<_> = index_pk
+            .index
+            .iter()
+            .chain(prover_first_oracles.iter())
+            .chain(prover_second_oracles.iter())
+            .chain(prover_third_oracles.iter())
+            .collect();
+
+        // Gather commitments in one vector.
+        let commitments =
+            vec![commitments_of(&first_comms), commitments_of(&second_comms), commitments_of(&third_comms)];
+        let labeled_comms: Vec<_> = index_pk
+            .index_vk
+            .iter()
+            .cloned()
+            .zip(&AHPForR1CS::<F>::INDEXER_POLYNOMIALS)
+            .map(|(c, l)| LabeledCommitment::new(l.to_string(), c, None))
+            .chain(first_comms.iter().cloned())
+            .chain(second_comms.iter().cloned())
+            .chain(third_comms.iter().cloned())
+            .collect();
+
+        // Gather commitment randomness together.
+        let comm_rands: Vec<PC::Randomness> = index_pk
+            .index_comm_rands
+            .clone()
+            .into_iter()
+            .chain(first_comm_rands)
+            .chain(second_comm_rands)
+            .chain(third_comm_rands)
+            .collect();
+
+        // Compute the AHP verifier's query set.
+        let (query_set, verifier_state) = AHPForR1CS::verifier_query_set(verifier_state, &mut fs_rng);
+        let lc_s = AHPForR1CS::construct_linear_combinations(&public_input, &polynomials, &verifier_state)?;
+
+        let mut evaluations = Vec::new();
+        for (label, (_, point)) in &query_set {
+            let lc = lc_s
+                .iter()
+                .find(|lc| &lc.label == label)
+                .ok_or_else(|| ahp::Error::MissingEval(label.to_string()))?;
+            let eval = polynomials.get_lc_eval(lc, *point)?;
+            if !AHPForR1CS::<F>::LC_WITH_ZERO_EVAL.contains(&lc.label.as_ref()) {
+                evaluations.push((label.to_string(), eval));
+            }
+        }
+
+        evaluations.sort_by(|a, b| a.0.cmp(&b.0));
+        let evaluations = evaluations.into_iter().map(|x| x.1).collect::<Vec<F>>();
+
+        fs_rng.absorb(&evaluations);
+        let mut opening_challenge = opening_challenge::<F, S, D>(&mut fs_rng);
+
+        let pc_proof = PC::open_combinations(
+            &index_pk.committer_key,
+            &lc_s,
+            polynomials,
+            &labeled_comms,
+            &query_set,
+            &mut opening_challenge,
+            &comm_rands,
+            Some(zk_rng),
+        )
+        .map_err(Error::from_pc_err)?;
+
+        // Gather prover messages together.
+        let prover_messages = vec![prover_first_msg, prover_second_msg, prover_third_msg];
+
+        Ok(Proof::new(commitments, evaluations, prover_messages, pc_proof))
+    }
+
+    /// Verify that a proof for the constraint system defined by `C` asserts that
+    /// all constraints are satisfied.
+    pub fn verify<R: RngCore>(
+        index_vk: &IndexVerifierKey<F, S, PC>,
+        public_input: &[F],
+        proof: &Proof<F, S, PC>,
+        rng: &mut R,
+    ) -> Result<bool, Error<PC::Error>> {
+        // a malformed proof is rejected rather than indexed out of bounds
+        if proof.commitments.len() != 3 || proof.prover_messages.len() != 3 {
+            return Ok(false);
+        }
+
+        let public_input = {
+            let domain_x = GeneralEvaluationDomain::<F>::new(public_input.len() + 1).unwrap();
+
+            let mut unpadded_input = public_input.to_vec();
+            unpadded_input.resize(core::cmp::max(public_input.len(), domain_x.size() - 1), F::zero());
+
+            unpadded_input
+        };
+
+        let mut fs_rng =
+            FiatShamirRng::<D>::from_seed(&to_bytes![Self::PROTOCOL_NAME.to_vec(), index_vk, public_input]);
+
+        // first round
+        let first_comms = &proof.commitments[0];
+        fs_rng.absorb(&to_bytes![first_comms, proof.prover_messages[0]]);
+
+        let (_, verifier_state) = AHPForR1CS::verifier_first_round(index_vk.index_info, &mut fs_rng)?;
+
+        // second round
+        let second_comms = &proof.commitments[1];
+        fs_rng.absorb(&to_bytes![second_comms, proof.prover_messages[1]]);
+
+        let (_, verifier_state) = AHPForR1CS::verifier_second_round(verifier_state, &mut fs_rng);
+
+        // third round
+        let third_comms = &proof.commitments[2];
+        fs_rng.absorb(&to_bytes![third_comms, proof.prover_messages[2]]);
+
+        let verifier_state = AHPForR1CS::verifier_third_round(verifier_state, &mut fs_rng);
+
+        // Collect degree bounds for commitments. Indexed polynomials have *no*
+        // degree bounds because we know the committed index polynomial has the
+        // correct degree.
+        let index_info = index_vk.index_info;
+        let degree_bounds = vec![None; index_vk.index_comms.len()]
+            .into_iter()
+            .chain(AHPForR1CS::prover_first_round_degree_bounds(&index_info))
+            .chain(AHPForR1CS::prover_second_round_degree_bounds(&index_info))
+            .chain(AHPForR1CS::prover_third_round_degree_bounds(&index_info))
+            .collect::<Vec<_>>();
+
+        // Gather commitments in one vector.
+        let commitments: Vec<_> = index_vk
+            .iter()
+            .chain(first_comms)
+            .chain(second_comms)
+            .chain(third_comms)
+            .cloned()
+            .zip(AHPForR1CS::<F>::polynomial_labels())
+            .zip(degree_bounds)
+            .map(|((c, l), d)| LabeledCommitment::new(l, c, d))
+            .collect();
+
+        let (query_set, verifier_state) = AHPForR1CS::verifier_query_set(verifier_state, &mut fs_rng);
+
+        fs_rng.absorb(&proof.evaluations);
+        let mut opening_challenge = opening_challenge::<F, S, D>(&mut fs_rng);
+
+        let mut evaluations = Evaluations::new();
+        let mut evaluation_labels = Vec::new();
+        for (poly_label, (_, point)) in query_set.iter().cloned() {
+            if AHPForR1CS::<F>::LC_WITH_ZERO_EVAL.contains(&poly_label.as_ref()) {
+                evaluations.insert((poly_label, point), F::zero());
+            } else {
+                evaluation_labels.push((poly_label, point));
+            }
+        }
+        if evaluation_labels.len() != proof.evaluations.len() {
+            return Ok(false);
+        }
+        evaluation_labels.sort_by(|a, b| a.0.cmp(&b.0));
+        for (q, eval) in evaluation_labels.into_iter().zip(&proof.evaluations) {
+            evaluations.insert(q, *eval);
+        }
+
+        let lc_s = AHPForR1CS::construct_linear_combinations(&public_input, &evaluations, &verifier_state)?;
+
+        PC::check_combinations(
+            &index_vk.verifier_key,
+            &lc_s,
+            &commitments,
+            &query_set,
+            &evaluations,
+            &proof.pc_proof,
+            &mut opening_challenge,
+            rng,
+        )
+        .map_err(Error::from_pc_err)
+    }
+}
+
+fn commitments_of<C: PCCommitment>(labeled: &[LabeledCommitment<C>]) -> Vec<C> {
+    labeled.iter().map(|c| c.commitment().clone()).collect()
+}
+
+// marlin 0.3 batched openings with powers of a single 128-bit challenge; keep that
+fn opening_challenge<F: PrimeField, S: CryptographicSponge, D: Digest>(
+    fs_rng: &mut FiatShamirRng<D>,
+) -> ChallengeGenerator<F, S> {
+    let c = F::from(u128::rand(fs_rng));
+    ChallengeGenerator::Univariate(c, c)
+}
@@ -0,0 +1,76 @@
+use ark_serialize::CanonicalSerialize;
+
+/// Serializes each argument in turn into one byte vector, for feeding the transcript.
+macro_rules! to_bytes {
+    ($($x:expr),* $(,)?) => {{
+        use ark_serialize::CanonicalSerialize;
+        let mut bytes = Vec::new();
+        $($x.serialize_compressed(&mut bytes).expect("failed to convert to bytes");)*
+        bytes
+    }};
+}
+use ark_std::marker::PhantomData;
+use ark_std::rand::{RngCore, SeedableRng};
+use digest::{generic_array::GenericArray, Digest};
+use rand_chacha::ChaChaRng;
+
+/// A `SeedableRng` that refreshes its seed by hashing together the previous seed
+/// and the new seed material.
+pub struct FiatShamirRng<D: Digest> {
+    r: ChaChaRng,
+    seed: GenericArray<u8, D::OutputSize>,
+    #[doc(hidden)]
+    digest: PhantomData<D>,
+}
+
+impl<D: Digest> RngCore for FiatShamirRng<D> {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        self.r.next_u32()
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        self.r.next_u64()
+    }
+
+    #[inline]
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.r.fill_bytes(dest);
+    }
+
+    #[inline]
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), ark_std::rand::Error> {
+        self.r.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl<D: Digest> FiatShamirRng<D> {
+    /// Create a new `Self` by initializing with a fresh seed.
+    /// `self.seed = H(self.seed || new_seed)`.
+    #[inline]
+    pub fn from_seed<T: CanonicalSerialize>(seed: &T) -> Self {
+        let mut bytes = Vec::new();
+        seed.serialize_compressed(&mut bytes).expect("failed to convert to bytes");
+        let seed = D::digest(&bytes);
+        let r = ChaChaRng::from_seed(chacha_seed(&seed));
+        Self { r, seed, digest: PhantomData }
+    }
+
+    /// Refresh `self.seed` with new material. Achieved by setting
+    /// `self.seed = H(self.seed || new_seed)`.
+    #[inline]
+    pub fn absorb<T: CanonicalSerialize>(&mut self, seed: &T) {
+        let mut bytes = Vec::new();
+        seed.serialize_compressed(&mut bytes).expect("failed to convert to bytes");
+        bytes.extend_from_slice(&self.seed);
+        self.seed = D::digest(&bytes);
+        self.r = ChaChaRng::from_seed(chacha_seed(&self.seed));
+    }
+}
+
+// the first 32 bytes of the digest, which has to be at least that long
+fn chacha_seed(digest: &[u8]) -> [u8; 32] {
+    digest[..32].try_into().expect("digest is shorter than 32 bytes")
+}
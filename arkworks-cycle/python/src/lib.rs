@@ -0,0 +1,205 @@
+use ark_bls12_381::{fr::Fr, Bls12_381};
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Validate};
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+use rand::{rngs::StdRng, SeedableRng};
+
+use zycle::edge_list::EdgeListGraph;
+use zycle::keys::{write_key, CircuitShape};
+use zycle::prover::commitment as matrix_commitment;
+use zycle::witness::{toposort_witness_vec, CycleError as GraphCycleError};
+use zycle::{AcyclicityProver, AcyclicityVerifier, ZycleError};
+
+// Python bindings over the dense Groth16 circuit on BLS12-381, built with maturin into the pyzycle
+// module. Graphs are passed either as `matrix`, a square numpy boolean array or list of lists
+// (any truthy entry is an edge), or networkx style as `edges`, an iterable of (sender, receiver)
+// pairs such as G.edges(), with `nodes` (e.g. G.nodes()) to include isolated nodes and fix their
+// order. Node ids are compared by str(), and node i of the matrix is the i-th of `nodes` followed
+// by the others in order of first appearance in `edges`. Keys and proofs are bytes, commitments
+// and other public inputs decimal strings like in proof bundles.
+
+create_exception!(pyzycle, CycleError, PyValueError, "The graph has a cycle, the message names it.");
+
+fn value_error(e: impl ToString) -> PyErr {
+    PyValueError::new_err(e.to_string())
+}
+
+fn cycle_error(e: GraphCycleError) -> PyErr {
+    CycleError::new_err(e.to_string())
+}
+
+fn zycle_error(e: ZycleError) -> PyErr {
+    match e {
+        ZycleError::CyclicGraph(cycle) => cycle_error(cycle),
+        e => value_error(e),
+    }
+}
+
+fn adjacency_matrix(
+    matrix: Option<&Bound<'_, PyAny>>,
+    edges: Option<&Bound<'_, PyAny>>,
+    nodes: Option<&Bound<'_, PyAny>>,
+) -> PyResult<Vec<Vec<bool>>> {
+    match (matrix, edges) {
+        (Some(matrix), None) if nodes.is_none() => {
+            let rows = matrix
+                .try_iter()?
+                .map(|row| row?.try_iter()?.map(|entry| entry?.is_truthy()).collect::<PyResult<Vec<bool>>>())
+                .collect::<PyResult<Vec<_>>>()?;
+            if rows.iter().any(|row| row.len() != rows.len()) {
+                return Err(value_error("matrix is not square"));
+            }
+            Ok(rows)
+        }
+        (None, Some(edges)) => {
+            let nodes = match nodes {
+                Some(nodes) => nodes.try_iter()?.map(|node| Ok(node?.str()?.to_string())).collect::<PyResult<_>>()?,
+                None => Vec::new(),
+            };
+            let edges = edges
+                .try_iter()?
+                .map(|edge| {
+                    let (sender, receiver): (Bound<'_, PyAny>, Bound<'_, PyAny>) = edge?.extract()?;
+                    Ok((sender.str()?.to_string(), receiver.str()?.to_string()))
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(EdgeListGraph::from_edges(&nodes, &edges).adj_matrix)
+        }
+        _ => Err(value_error("pass the graph as either matrix or edges (with optional nodes)")),
+    }
+}
+
+fn parse_field(input: &str) -> PyResult<Fr> {
+    input.parse().map_err(|_| value_error(format!("invalid field element {:?}", input)))
+}
+
+/// The commitment the graph is proven against.
+#[pyfunction]
+#[pyo3(signature = (matrix=None, edges=None, nodes=None))]
+fn commitment(
+    matrix: Option<&Bound<'_, PyAny>>,
+    edges: Option<&Bound<'_, PyAny>>,
+    nodes: Option<&Bound<'_, PyAny>>,
+) -> PyResult<String> {
+    let adj_matrix = adjacency_matrix(matrix, edges, nodes)?;
+    let hash: Fr = matrix_commitment(&adj_matrix).map_err(value_error)?;
+    Ok(hash.to_string())
+}
+
+/// The position of every node in a topological sort, the witness prove uses.
+#[pyfunction]
+#[pyo3(signature = (matrix=None, edges=None, nodes=None))]
+fn toposort(
+    matrix: Option<&Bound<'_, PyAny>>,
+    edges: Option<&Bound<'_, PyAny>>,
+    nodes: Option<&Bound<'_, PyAny>>,
+) -> PyResult<Vec<u32>> {
+    toposort_witness_vec(&adjacency_matrix(matrix, edges, nodes)?).map_err(zycle_error)
+}
+
+/// A proving and verifying key for graphs with this many nodes, as written by `zycle setup`.
+#[pyfunction]
+fn setup<'py>(py: Python<'py>, nodes: usize) -> PyResult<(Bound<'py, PyBytes>, Bound<'py, PyBytes>)> {
+    let (pk, vk) = py.detach(|| {
+        let (prover, verifier) = AcyclicityProver::setup(nodes, &mut StdRng::from_entropy()).map_err(value_error)?;
+        let shape = CircuitShape::dense(nodes, prover.position_width());
+        let (mut pk, mut vk) = (Vec::new(), Vec::new());
+        write_key(prover.proving_key(), shape, Compress::Yes, &mut pk).map_err(value_error)?;
+        write_key(verifier.verifying_key(), shape, Compress::Yes, &mut vk).map_err(value_error)?;
+        Ok::<_, PyErr>((pk, vk))
+    })?;
+    Ok((PyBytes::new(py, &pk), PyBytes::new(py, &vk)))
+}
+
+/// Proves the graph has no cycles, returning the compressed proof and its public inputs.
+#[pyfunction]
+#[pyo3(signature = (pk, matrix=None, edges=None, nodes=None))]
+fn prove<'py>(
+    py: Python<'py>,
+    pk: &[u8],
+    matrix: Option<&Bound<'py, PyAny>>,
+    edges: Option<&Bound<'py, PyAny>>,
+    nodes: Option<&Bound<'py, PyAny>>,
+) -> PyResult<(Bound<'py, PyBytes>, Vec<String>)> {
+    let adj_matrix = adjacency_matrix(matrix, edges, nodes)?;
+    let (proof, public_inputs) = py.detach(|| {
+        let prover = AcyclicityProver::from_bytes(pk, Validate::Yes).map_err(value_error)?;
+        let (proof, public_inputs) = prover.prove(&adj_matrix, &mut StdRng::from_entropy()).map_err(zycle_error)?;
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes).map_err(value_error)?;
+        Ok::<_, PyErr>((proof_bytes, public_inputs))
+    })?;
+    Ok((PyBytes::new(py, &proof), public_inputs.iter().map(|input| input.to_string()).collect()))
+}
+
+/// Checks a proof from prove against the verifying key and public inputs.
+#[pyfunction]
+fn verify(py: Python<'_>, vk: &[u8], proof: &[u8], public_inputs: Vec<String>) -> PyResult<bool> {
+    let public_inputs = public_inputs.iter().map(|input| parse_field(input)).collect::<PyResult<Vec<_>>>()?;
+    py.detach(|| {
+        let verifier = AcyclicityVerifier::from_bytes(vk, Validate::Yes).map_err(value_error)?;
+        let proof = Proof::<Bls12_381>::deserialize_compressed(proof).map_err(value_error)?;
+        verifier.verify(&proof, &public_inputs).map_err(value_error)
+    })
+}
+
+#[pymodule]
+fn pyzycle(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add("CycleError", module.py().get_type::<CycleError>())?;
+    module.add_function(wrap_pyfunction!(commitment, module)?)?;
+    module.add_function(wrap_pyfunction!(toposort, module)?)?;
+    module.add_function(wrap_pyfunction!(setup, module)?)?;
+    module.add_function(wrap_pyfunction!(prove, module)?)?;
+    module.add_function(wrap_pyfunction!(verify, module)?)?;
+    Ok(())
+}
+
+#[test]
+fn prove_and_verify_from_python() {
+    Python::attach(|py| {
+        let module = pyo3::wrap_pymodule!(pyzycle)(py);
+        py.import("sys").unwrap().getattr("modules").unwrap().set_item("pyzycle", module).unwrap();
+        let script = c"
+import pyzycle
+try:
+    import networkx
+    graph = networkx.DiGraph([('alice', 'bob'), ('bob', 'carol')])
+    edges, nodes = graph.edges(), graph.nodes()
+except ImportError:
+    edges, nodes = [('alice', 'bob'), ('bob', 'carol')], ['alice', 'bob', 'carol']
+
+matrix = [[False, True, False], [False, False, True], [False, False, False]]
+commitment = pyzycle.commitment(matrix=matrix)
+assert pyzycle.commitment(edges=edges, nodes=nodes) == commitment
+assert pyzycle.commitment(matrix=[[0, 1, 0], [0, 0, 1], [0, 0, 0]]) == commitment
+assert pyzycle.toposort(edges=edges, nodes=nodes) == [0, 1, 2]
+
+pk, vk = pyzycle.setup(3)
+proof, public_inputs = pyzycle.prove(pk, edges=edges, nodes=nodes)
+assert public_inputs == [commitment]
+assert pyzycle.verify(vk, proof, public_inputs)
+assert not pyzycle.verify(vk, proof, ['1'])
+proof, public_inputs = pyzycle.prove(pk, matrix=matrix)
+assert pyzycle.verify(vk, proof, public_inputs)
+
+def raises(error, f, *args, **kwargs):
+    try:
+        f(*args, **kwargs)
+    except error as e:
+        return str(e)
+    raise AssertionError(f'{f.__name__} did not raise {error.__name__}')
+
+assert 'cycle' in raises(pyzycle.CycleError, pyzycle.prove, pk, edges=[(0, 1), (1, 2), (2, 0)])
+assert issubclass(pyzycle.CycleError, ValueError)
+raises(ValueError, pyzycle.prove, pk, matrix=[[False, True], [False, False]])
+raises(ValueError, pyzycle.commitment, matrix=[[False, True]])
+raises(ValueError, pyzycle.commitment)
+raises(ValueError, pyzycle.verify, vk, proof[1:], public_inputs)
+raises(ValueError, pyzycle.verify, pk, proof, public_inputs)
+";
+        py.run(script, None, None).unwrap();
+    });
+}
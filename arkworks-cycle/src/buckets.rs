@@ -0,0 +1,329 @@
+use std::path::Path;
+
+use ark_bls12_381::Bls12_381;
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_groth16::Proof;
+use ark_serialize::{Compress, Validate};
+use ark_std::{
+    io,
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::circuit::PositionWidth;
+use crate::curves::ZycleCurve;
+use crate::error::ZycleError;
+use crate::prover::{check_matrix, commitment, AcyclicityProverOn, AcyclicityVerifierOn};
+use crate::witness::toposort_witness_vec;
+
+// The circuit shape gives away the number of nodes, so graphs are padded with isolated dummy nodes
+// up to the next of these sizes and only the bucket a graph falls in is revealed
+pub const BUCKET_SIZES: [usize; 9] = [16, 32, 64, 128, 256, 512, 1024, 2048, 4096];
+
+// smallest bucket that fits a graph with `nodes` nodes
+pub fn bucket_for(nodes: usize) -> Option<usize> {
+    BUCKET_SIZES.iter().copied().find(|&size| size >= nodes)
+}
+
+// every bucket up to and including the one that fits `max_nodes`
+pub fn buckets_up_to(max_nodes: usize) -> Option<Vec<usize>> {
+    let largest = bucket_for(max_nodes)?;
+    Some(BUCKET_SIZES.iter().copied().filter(|&size| size <= largest).collect())
+}
+
+// Embeds the graph in a size x size matrix, the added nodes have no edges at all
+// the graph has to be square and no larger than size
+pub fn pad_adjacency_matrix<Row: AsRef<[bool]>>(adj_matrix: &[Row], size: usize) -> Result<Vec<Vec<bool>>, ZycleError> {
+    check_matrix(adj_matrix, adj_matrix.len())?;
+    if adj_matrix.len() > size {
+        return Err(ZycleError::InvalidGraph(format!(
+            "a graph with {} nodes does not fit in {} nodes",
+            adj_matrix.len(),
+            size
+        )));
+    }
+    let mut padded = vec![vec![false; size]; size];
+    for (padded_row, row) in padded.iter_mut().zip(adj_matrix) {
+        padded_row[..row.as_ref().len()].copy_from_slice(row.as_ref());
+    }
+    Ok(padded)
+}
+
+// Puts the dummy nodes after every real node, which keeps the ordering valid since they have no edges
+pub fn pad_toposort(toposort: &[u32], size: usize) -> Vec<u32> {
+    let mut padded = toposort.to_vec();
+    padded.extend((toposort.len()..size).map(|pos| pos as u32));
+    padded
+}
+
+// commitment to the graph once padded to its bucket, which is what a bucketed proof is checked against
+pub fn padded_commitment<F: PrimeField + Absorb, Row: AsRef<[bool]>>(adj_matrix: &[Row]) -> Result<F, ZycleError> {
+    let largest = BUCKET_SIZES[BUCKET_SIZES.len() - 1];
+    let size = bucket_for(adj_matrix.len()).ok_or_else(|| too_large(adj_matrix.len(), largest))?;
+    Ok(commitment(&pad_adjacency_matrix(adj_matrix, size)?)?)
+}
+
+// file names of the keys for one bucket inside a key directory
+pub fn proving_key_path(dir: &str, size: usize) -> String {
+    Path::new(dir).join(format!("pk_{}.bin", size)).to_string_lossy().into_owned()
+}
+
+pub fn verifying_key_path(dir: &str, size: usize) -> String {
+    Path::new(dir).join(format!("vk_{}.bin", size)).to_string_lossy().into_owned()
+}
+
+// a proof, its public inputs and the bucket size it was made for
+pub type BucketedProof<E> = (Proof<E>, Vec<<E as Pairing>::ScalarField>, usize);
+
+// One proving key per bucket, a graph is proven with the key of the smallest bucket it fits in
+pub struct BucketedProverOn<E: ZycleCurve> {
+    provers: Vec<AcyclicityProverOn<E>>,
+}
+
+// The verifying keys matching a BucketedProverOn
+pub struct BucketedVerifierOn<E: ZycleCurve> {
+    verifiers: Vec<AcyclicityVerifierOn<E>>,
+}
+
+// the prover and verifier on the default curve
+pub type BucketedProver = BucketedProverOn<Bls12_381>;
+pub type BucketedVerifier = BucketedVerifierOn<Bls12_381>;
+
+impl<E: ZycleCurve> BucketedProverOn<E> {
+    // runs the setup once for each of the given bucket sizes
+    pub fn setup<R: RngCore + CryptoRng>(
+        sizes: &[usize],
+        rng: &mut R,
+    ) -> Result<(Self, BucketedVerifierOn<E>), ZycleError> {
+        Self::setup_with_width(sizes, None, rng)
+    }
+
+    // same as setup, with every bucket using the given position width instead of the smallest that fits
+    pub fn setup_with_width<R: RngCore + CryptoRng>(
+        sizes: &[usize],
+        position_width: Option<PositionWidth>,
+        rng: &mut R,
+    ) -> Result<(Self, BucketedVerifierOn<E>), ZycleError> {
+        let mut provers = Vec::new();
+        let mut verifiers = Vec::new();
+        for &size in sizes {
+            let (prover, verifier) = match position_width {
+                Some(position_width) => AcyclicityProverOn::setup_with_width(size, position_width, rng)?,
+                None => AcyclicityProverOn::setup(size, rng)?,
+            };
+            provers.push(prover);
+            verifiers.push(verifier);
+        }
+        Ok((Self::new(provers), BucketedVerifierOn::new(verifiers)))
+    }
+
+    pub fn new(mut provers: Vec<AcyclicityProverOn<E>>) -> Self {
+        provers.sort_by_key(|prover| prover.nodes());
+        Self { provers }
+    }
+
+    pub fn buckets(&self) -> Vec<usize> {
+        self.provers.iter().map(|prover| prover.nodes()).collect()
+    }
+
+    // the prover for the smallest bucket that fits a graph with `nodes` nodes
+    pub fn prover_for(&self, nodes: usize) -> Option<&AcyclicityProverOn<E>> {
+        self.provers.iter().find(|prover| prover.nodes() >= nodes)
+    }
+
+    // loads pk_<size>.bin for every bucket size that has a key in the directory
+    pub fn load_dir(dir: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let mut provers = Vec::new();
+        for size in BUCKET_SIZES {
+            let path = proving_key_path(dir, size);
+            if Path::new(&path).exists() {
+                let prover = AcyclicityProverOn::load(&path, validate)?;
+                if prover.nodes() != size {
+                    return Err(wrong_bucket(&path, prover.nodes()));
+                }
+                provers.push(prover);
+            }
+        }
+        if provers.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no proving keys in {}", dir)).into());
+        }
+        Ok(Self::new(provers))
+    }
+
+    pub fn write_dir(&self, dir: &str, compress: Compress) -> Result<(), ZycleError> {
+        for prover in &self.provers {
+            prover.write_proving_key(&proving_key_path(dir, prover.nodes()), compress)?;
+        }
+        Ok(())
+    }
+
+    // proves that adj_matrix has no cycles after padding it to its bucket
+    // returns the proof, its public inputs and the bucket size the proof is for
+    pub fn prove<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        rng: &mut R,
+    ) -> Result<BucketedProof<E>, ZycleError> {
+        check_matrix(adj_matrix, adj_matrix.len())?;
+        let topological_sort = toposort_witness_vec(adj_matrix)?;
+        self.prove_with_toposort(adj_matrix, &topological_sort, rng)
+    }
+
+    pub fn prove_with_toposort<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        topological_sort: &[u32],
+        rng: &mut R,
+    ) -> Result<BucketedProof<E>, ZycleError> {
+        let nodes = adj_matrix.len();
+        check_matrix(adj_matrix, nodes)?;
+        let largest = self.provers.last().map_or(0, |prover| prover.nodes());
+        let prover = self.prover_for(nodes).ok_or_else(|| too_large(nodes, largest))?;
+        if topological_sort.len() != nodes {
+            return Err(ZycleError::InvalidGraph(format!(
+                "topological sort has {} entries, expected {}",
+                topological_sort.len(),
+                nodes
+            )));
+        }
+        let size = prover.nodes();
+        let (proof, public_inputs) = prover.prove_with_toposort(
+            &pad_adjacency_matrix(adj_matrix, size)?,
+            &pad_toposort(topological_sort, size),
+            rng,
+        )?;
+        Ok((proof, public_inputs, size))
+    }
+}
+
+impl<E: ZycleCurve> BucketedVerifierOn<E> {
+    pub fn new(mut verifiers: Vec<AcyclicityVerifierOn<E>>) -> Self {
+        verifiers.sort_by_key(|verifier| verifier.nodes());
+        Self { verifiers }
+    }
+
+    pub fn buckets(&self) -> Vec<usize> {
+        self.verifiers.iter().map(|verifier| verifier.nodes()).collect()
+    }
+
+    pub fn verifier(&self, size: usize) -> Option<&AcyclicityVerifierOn<E>> {
+        self.verifiers.iter().find(|verifier| verifier.nodes() == size)
+    }
+
+    // loads vk_<size>.bin (a verifying or prepared verifying key) for every bucket size in the directory
+    pub fn load_dir(dir: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let mut verifiers = Vec::new();
+        for size in BUCKET_SIZES {
+            let path = verifying_key_path(dir, size);
+            if Path::new(&path).exists() {
+                let verifier = AcyclicityVerifierOn::load(&path, validate)?;
+                if verifier.nodes() != size {
+                    return Err(wrong_bucket(&path, verifier.nodes()));
+                }
+                verifiers.push(verifier);
+            }
+        }
+        if verifiers.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no verifying keys in {}", dir)).into());
+        }
+        Ok(Self::new(verifiers))
+    }
+
+    pub fn write_dir(&self, dir: &str, compress: Compress) -> Result<(), ZycleError> {
+        for verifier in &self.verifiers {
+            verifier.write_verifying_key(&verifying_key_path(dir, verifier.nodes()), compress)?;
+        }
+        Ok(())
+    }
+
+    // checks a proof made for the given bucket size
+    pub fn verify(
+        &self,
+        size: usize,
+        proof: &Proof<E>,
+        public_inputs: &[E::ScalarField],
+    ) -> Result<bool, ZycleError> {
+        let verifier = self
+            .verifier(size)
+            .ok_or_else(|| ZycleError::KeyMismatch(format!("no verifying key for {} node graphs", size)))?;
+        verifier.verify(proof, public_inputs)
+    }
+}
+
+fn too_large(nodes: usize, largest: usize) -> ZycleError {
+    ZycleError::InvalidGraph(format!("graph has {} nodes, but the largest bucket is for {} nodes", nodes, largest))
+}
+
+fn wrong_bucket(path: &str, nodes: usize) -> ZycleError {
+    ZycleError::KeyMismatch(format!("{} holds a key for {} node graphs", path, nodes))
+}
+
+#[test]
+fn padding_keeps_the_sort_valid() {
+    use crate::circuit::MyGraphCircuitStruct;
+    use ark_bls12_381::Fr;
+    use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem};
+
+    assert_eq!(bucket_for(3), Some(16));
+    assert_eq!(bucket_for(16), Some(16));
+    assert_eq!(bucket_for(17), Some(32));
+    assert_eq!(bucket_for(257), Some(512));
+    assert_eq!(bucket_for(5000), None);
+    assert_eq!(buckets_up_to(40), Some(vec![16, 32, 64]));
+
+    let adj_matrix = [[false, true, false], [false, false, true], [false, false, false]];
+    let toposort = toposort_witness_vec(&adj_matrix).unwrap();
+    let padded = pad_adjacency_matrix(&adj_matrix, 16).unwrap();
+    assert_eq!(padded[1][..3], [false, false, true]);
+    assert!(padded[3..].iter().all(|row| row.iter().all(|&edge| !edge)));
+    assert_eq!(padded_commitment::<Fr, _>(&adj_matrix).unwrap(), commitment(&padded).unwrap());
+    // a graph larger than the padded size or with a row longer than the matrix is refused
+    assert!(matches!(pad_adjacency_matrix(&adj_matrix, 2), Err(ZycleError::InvalidGraph(_))));
+    let ragged = [vec![false, false, false, true], vec![false, false], vec![false]];
+    assert!(matches!(pad_adjacency_matrix(&ragged, 16), Err(ZycleError::InvalidGraph(_))));
+
+    let circuit = MyGraphCircuitStruct {
+        adj_hash: commitment(&padded).unwrap(),
+        adj_matrix: padded,
+        toposort: pad_toposort(&toposort, 16),
+        position_width: crate::circuit::PositionWidth::U8,
+        commitment: crate::circuit::CommitmentKind::Plain,
+        salt: None,
+    };
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    circuit.generate_constraints(cs.clone()).unwrap();
+    assert!(cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn bucketed_prove_and_verify() {
+    use ark_bls12_381::Fr;
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use ark_std::test_rng;
+
+    // small sizes keep the setup quick, the real buckets work the same way
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = BucketedProver::setup(&[4, 2], &mut rng).unwrap();
+    assert_eq!(prover.buckets(), vec![2, 4]);
+
+    let adj_matrix = [[false, true, true], [false, false, true], [false, false, false]];
+    let (proof, public_inputs, size) = prover.prove(&adj_matrix, &mut rng).unwrap();
+    assert_eq!(size, 4);
+    assert_eq!(public_inputs, vec![commitment(&pad_adjacency_matrix(&adj_matrix, 4).unwrap()).unwrap()]);
+    assert!(verifier.verify(size, &proof, &public_inputs).unwrap());
+    assert!(!verifier.verify(2, &proof, &public_inputs).unwrap());
+    assert!(matches!(verifier.verify(8, &proof, &public_inputs), Err(ZycleError::KeyMismatch(_))));
+
+    assert!(matches!(prover.prove(&[[false; 5]; 5], &mut rng), Err(ZycleError::InvalidGraph(_))));
+    // a row longer than the matrix is refused rather than indexed out of bounds
+    let ragged = [vec![false, false, false, true], vec![false, false], vec![false]];
+    assert!(matches!(prover.prove(&ragged, &mut rng), Err(ZycleError::InvalidGraph(_))));
+    assert!(matches!(padded_commitment::<Fr, _>(&ragged), Err(ZycleError::InvalidGraph(_))));
+
+    // and on another curve
+    let (prover, verifier) = BucketedProverOn::<ark_bn254::Bn254>::setup(&[4], &mut rng).unwrap();
+    let (proof, public_inputs, size) = prover.prove(&adj_matrix, &mut rng).unwrap();
+    assert_eq!(public_inputs, vec![commitment(&pad_adjacency_matrix(&adj_matrix, size).unwrap()).unwrap()]);
+    assert!(verifier.verify(size, &proof, &public_inputs).unwrap());
+}
@@ -0,0 +1,409 @@
+use std::collections::HashMap;
+
+use ark_bls12_381::{fr::Fr, Bls12_381};
+use ark_ff::PrimeField;
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use ark_std::{fs, io};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::circuit::{CircuitVariant, CommitmentKind};
+use crate::curves::ZycleCurve;
+use crate::edge_list::{EdgeList, EdgeListGraph};
+use crate::error::ZycleError;
+use crate::prover::AcyclicityVerifierOn;
+use crate::universal::{Backend, MarlinProof};
+
+// bumped whenever a field is added, removed or changes meaning
+pub const SCHEMA_VERSION: u32 = 1;
+
+// JSON description of a graph to prove acyclic
+//   {
+//     "version": 1,
+//     "nodes": ["alice", "bob", "carol"],
+//     "edges": [["alice", "bob"], ["bob", "carol"]],
+//     "subgraph": ["bob", "carol"]
+//   }
+// when subgraph is given only the graph induced by those nodes is committed to and proven, so no
+// edge may go from a subgraph node to a node outside it
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphInput {
+    pub version: u32,
+    pub nodes: Vec<String>,
+    pub edges: Vec<[String; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subgraph: Option<Vec<String>>,
+}
+
+impl GraphInput {
+    pub fn from_json(json: &str) -> Result<Self, ZycleError> {
+        let input: Self = serde_json::from_str(json)?;
+        check_version(input.version)?;
+        Ok(input)
+    }
+
+    pub fn read_from_file(file_path: &str) -> Result<Self, ZycleError> {
+        Self::from_json(&fs::read_to_string(file_path)?)
+    }
+
+    // builds the adjacency matrix, numbering nodes in the order they are listed
+    pub fn to_graph(&self) -> Result<EdgeListGraph, ZycleError> {
+        Ok(self.to_edge_list()?.to_graph())
+    }
+
+    // same as to_graph without building the adjacency matrix
+    pub fn to_edge_list(&self) -> Result<EdgeList, ZycleError> {
+        let mut index_of: HashMap<&String, usize> = HashMap::with_capacity(self.nodes.len());
+        for (i, id) in self.nodes.iter().enumerate() {
+            if index_of.insert(id, i).is_some() {
+                return Err(ZycleError::InvalidGraph(format!("node {:?} is listed more than once", id)));
+            }
+        }
+        let index = |id: &String, what: &str| {
+            let missing = || ZycleError::InvalidGraph(format!("{} {:?} is not in the graph", what, id));
+            index_of.get(id).copied().ok_or_else(missing)
+        };
+
+        // the index every node gets in the matrix, None for the nodes left out of the subgraph
+        let selected: Vec<Option<usize>> = match &self.subgraph {
+            Some(subgraph) => {
+                let mut in_subgraph = vec![false; self.nodes.len()];
+                for id in subgraph {
+                    in_subgraph[index(id, "subgraph node")?] = true;
+                }
+                let mut next = 0..;
+                in_subgraph.iter().map(|&keep| if keep { next.next() } else { None }).collect()
+            }
+            None => (0..self.nodes.len()).map(Some).collect(),
+        };
+
+        let mut edges = Vec::with_capacity(self.edges.len());
+        for [sender, receiver] in &self.edges {
+            let (i, j) = (index(sender, "edge node")?, index(receiver, "edge node")?);
+            match (selected[i], selected[j]) {
+                (Some(i), Some(j)) => edges.push((i, j)),
+                // a cycle through a node outside the subgraph would go unnoticed
+                (Some(_), None) => {
+                    return Err(ZycleError::InvalidGraph(format!(
+                        "edge from {:?} to {:?} leaves the subgraph",
+                        sender, receiver
+                    )))
+                }
+                // edges into the subgraph cannot be on a cycle inside it
+                (None, _) => {}
+            }
+        }
+        let ids = self.nodes.iter().zip(&selected).filter(|(_, index)| index.is_some()).map(|(id, _)| id.clone());
+        Ok(EdgeList { ids: ids.collect(), edges })
+    }
+}
+
+// A proof together with everything needed to know what it proves
+//   {
+//     "version": 1,
+//     "circuit": "toposort",
+//     "commitment": "plain",
+//     "curve": "bls12-381",
+//     "n": 4,
+//     "proof": "<base64 of the compressed Groth16 proof>",
+//     "public_inputs": ["<commitment as a decimal field element>"],
+//     "node_ids": ["alice", "bob", "carol", "dave"],
+//     "backend": "marlin"
+//   }
+// node_ids is only present when the graph came with account ids, backend only when the proof is
+// not a Groth16 one
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProofBundle {
+    pub version: u32,
+    pub circuit: String,
+    // what the public input commits to, see CommitmentKind
+    pub commitment: String,
+    pub curve: String,
+    pub n: usize,
+    pub proof: String,
+    pub public_inputs: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_ids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+}
+
+impl ProofBundle {
+    // a bundle for a Groth16 proof, recording the curve the proof is on
+    pub fn new<E: ZycleCurve>(
+        proof: &Proof<E>,
+        public_inputs: &[E::ScalarField],
+        n: usize,
+        variant: CircuitVariant,
+        commitment: CommitmentKind,
+        node_ids: Option<Vec<String>>,
+    ) -> Result<Self, ZycleError> {
+        let bundle = Self::encode(proof, public_inputs, n, variant, commitment, node_ids)?;
+        Ok(Self { curve: E::CURVE_ID.to_string(), ..bundle })
+    }
+
+    // a bundle for a Marlin proof, which are only made on bls12-381
+    pub fn new_marlin(
+        proof: &MarlinProof,
+        public_inputs: &[Fr],
+        n: usize,
+        variant: CircuitVariant,
+        commitment: CommitmentKind,
+        node_ids: Option<Vec<String>>,
+    ) -> Result<Self, ZycleError> {
+        let bundle = Self::encode(proof, public_inputs, n, variant, commitment, node_ids)?;
+        let backend = Some(Backend::Marlin.id().to_string());
+        Ok(Self { curve: Bls12_381::CURVE_ID.to_string(), backend, ..bundle })
+    }
+
+    // everything but the curve and backend, which the callers fill in
+    fn encode<P: CanonicalSerialize, F: PrimeField>(
+        proof: &P,
+        public_inputs: &[F],
+        n: usize,
+        variant: CircuitVariant,
+        commitment: CommitmentKind,
+        node_ids: Option<Vec<String>>,
+    ) -> Result<Self, ZycleError> {
+        let mut proof_bytes = Vec::new();
+        proof.serialize_compressed(&mut proof_bytes)?;
+        Ok(Self {
+            version: SCHEMA_VERSION,
+            circuit: variant.id().to_string(),
+            commitment: commitment.id().to_string(),
+            curve: String::new(),
+            n,
+            proof: BASE64.encode(proof_bytes),
+            public_inputs: public_inputs.iter().map(field_to_string).collect(),
+            node_ids,
+            backend: None,
+        })
+    }
+
+    // which proof system made the proof, bundles without one hold Groth16 proofs
+    pub fn backend(&self) -> Result<Backend, ZycleError> {
+        match &self.backend {
+            None => Ok(Backend::Groth16),
+            Some(id) => Backend::from_id(id).ok_or_else(|| invalid_data(format!("unknown backend {:?}", id))),
+        }
+    }
+
+    // what the public input commits to
+    pub fn commitment(&self) -> Result<CommitmentKind, ZycleError> {
+        CommitmentKind::from_id(&self.commitment)
+            .ok_or_else(|| invalid_data(format!("unknown commitment {:?}", self.commitment)))
+    }
+
+    pub fn proof<E: ZycleCurve>(&self) -> Result<Proof<E>, ZycleError> {
+        self.check_curve(E::CURVE_ID)?;
+        self.decode_proof(Backend::Groth16)
+    }
+
+    pub fn marlin_proof(&self) -> Result<MarlinProof, ZycleError> {
+        self.decode_proof(Backend::Marlin)
+    }
+
+    fn decode_proof<P: CanonicalDeserialize>(&self, backend: Backend) -> Result<P, ZycleError> {
+        if self.backend()? != backend {
+            return Err(ZycleError::KeyMismatch(format!(
+                "proof was made with {}, expected {}",
+                self.backend()?.id(),
+                backend.id()
+            )));
+        }
+        let proof_bytes = BASE64
+            .decode(&self.proof)
+            .map_err(|e| invalid_data(format!("proof is not valid base64: {}", e)))?;
+        Ok(P::deserialize_compressed(proof_bytes.as_slice())?)
+    }
+
+    pub fn public_inputs<F: PrimeField>(&self) -> Result<Vec<F>, ZycleError> {
+        self.public_inputs
+            .iter()
+            .map(|input| {
+                F::from_str(input).map_err(|_| invalid_data(format!("invalid field element {:?}", input)))
+            })
+            .collect()
+    }
+
+    // errors unless the bundle was made for the circuit, commitment, curve and graph size of the verifier
+    pub fn check_matches<E: ZycleCurve>(&self, verifier: &AcyclicityVerifierOn<E>) -> Result<(), ZycleError> {
+        self.check_matches_circuit::<E>(CircuitVariant::TopoSort, verifier.commitment(), verifier.nodes())
+    }
+
+    // same as check_matches for a verifying key of any circuit variant on the curve E
+    pub fn check_matches_circuit<E: ZycleCurve>(
+        &self,
+        variant: CircuitVariant,
+        commitment: CommitmentKind,
+        nodes: usize,
+    ) -> Result<(), ZycleError> {
+        self.check_curve(E::CURVE_ID)?;
+        self.check_circuit(variant, nodes)?;
+        self.check_commitment(commitment)
+    }
+
+    fn check_curve(&self, curve: &str) -> Result<(), ZycleError> {
+        if self.curve != curve {
+            return Err(ZycleError::KeyMismatch(format!("proof is for curve {}, expected {}", self.curve, curve)));
+        }
+        Ok(())
+    }
+
+    fn check_commitment(&self, commitment: CommitmentKind) -> Result<(), ZycleError> {
+        if self.commitment != commitment.id() {
+            return Err(ZycleError::KeyMismatch(format!(
+                "proof is against a {} commitment, but the verifying key takes a {} commitment",
+                self.commitment,
+                commitment.id()
+            )));
+        }
+        Ok(())
+    }
+
+    fn check_circuit(&self, variant: CircuitVariant, nodes: usize) -> Result<(), ZycleError> {
+        match CircuitVariant::from_id(&self.circuit) {
+            Some(circuit) if circuit == variant => {}
+            Some(_) => {
+                return Err(ZycleError::KeyMismatch(format!(
+                    "proof is for the {} circuit, but the verifying key is for the {} circuit",
+                    self.circuit,
+                    variant.id()
+                )))
+            }
+            None => return Err(ZycleError::KeyMismatch(format!("proof is for unknown circuit {:?}", self.circuit))),
+        }
+        if self.n != nodes {
+            return Err(ZycleError::KeyMismatch(format!(
+                "proof is for graphs with {} nodes, but the verifying key is for {} nodes",
+                self.n, nodes
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<String, ZycleError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ZycleError> {
+        let bundle: Self = serde_json::from_str(json)?;
+        check_version(bundle.version)?;
+        Ok(bundle)
+    }
+
+    pub fn write_to_file(&self, file_path: &str) -> Result<(), ZycleError> {
+        Ok(fs::write(file_path, self.to_json()?)?)
+    }
+
+    pub fn read_from_file(file_path: &str) -> Result<Self, ZycleError> {
+        Self::from_json(&fs::read_to_string(file_path)?)
+    }
+}
+
+fn check_version(version: u32) -> Result<(), ZycleError> {
+    if version != SCHEMA_VERSION {
+        return Err(invalid_data(format!(
+            "unsupported schema version {}, expected {}",
+            version, SCHEMA_VERSION
+        )));
+    }
+    Ok(())
+}
+
+// the Display impl of a field element writes zero as an empty string, which does not parse back
+pub fn field_to_string<F: PrimeField>(element: &F) -> String {
+    if element.is_zero() {
+        "0".to_string()
+    } else {
+        element.to_string()
+    }
+}
+
+fn invalid_data(msg: String) -> ZycleError {
+    io::Error::new(io::ErrorKind::InvalidData, msg).into()
+}
+
+#[test]
+fn graph_input_subgraph_selection() {
+    let json = r#"{
+        "version": 1,
+        "nodes": ["alice", "bob", "carol", "dave"],
+        "edges": [["alice", "bob"], ["bob", "carol"], ["alice", "dave"], ["carol", "dave"]],
+        "subgraph": ["bob", "carol", "dave"]
+    }"#;
+    let input = GraphInput::from_json(json).unwrap();
+    let graph = input.to_graph().unwrap();
+    assert_eq!(graph.ids, vec!["bob", "carol", "dave"]);
+    assert_eq!(
+        graph.adj_matrix,
+        vec![
+            vec![false, true, false],
+            vec![false, false, true],
+            vec![false, false, false],
+        ]
+    );
+
+    // a cycle through a node outside the subgraph is refused rather than dropped with its edges
+    let mut cyclic = input.clone();
+    cyclic.edges.push(["carol".to_string(), "alice".to_string()]);
+    assert!(matches!(cyclic.to_graph(), Err(ZycleError::InvalidGraph(_))));
+
+    let unknown = r#"{"version": 1, "nodes": ["a"], "edges": [["a", "b"]]}"#;
+    assert!(matches!(GraphInput::from_json(unknown).unwrap().to_graph(), Err(ZycleError::InvalidGraph(_))));
+    // node ids are unique whether or not they are in the subgraph
+    let repeated = r#"{"version": 1, "nodes": ["a", "b", "a"], "edges": [], "subgraph": ["b"]}"#;
+    assert!(matches!(GraphInput::from_json(repeated).unwrap().to_graph(), Err(ZycleError::InvalidGraph(_))));
+    let future = r#"{"version": 2, "nodes": [], "edges": []}"#;
+    assert!(GraphInput::from_json(future).is_err());
+}
+
+#[test]
+fn proof_bundle_roundtrip() {
+    use crate::AcyclicityProver;
+    use ark_ff::Zero;
+    use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+
+    let adj_matrix = [[false, true, false], [false, false, true], [false, false, false]];
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = AcyclicityProver::setup(3, &mut rng).unwrap();
+    let (proof, public_inputs) = prover.prove(&adj_matrix, &mut rng).unwrap();
+
+    let ids = Some(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+    let plain = CommitmentKind::Plain;
+    let bundle = ProofBundle::new(&proof, &public_inputs, 3, CircuitVariant::TopoSort, plain, ids).unwrap();
+    let read_back = ProofBundle::from_json(&bundle.to_json().unwrap()).unwrap();
+    assert_eq!(read_back, bundle);
+    read_back.check_matches(&verifier).unwrap();
+    assert_eq!(read_back.public_inputs::<Fr>().unwrap(), public_inputs);
+    assert_eq!(read_back.backend().unwrap(), Backend::Groth16);
+    assert!(verifier.verify(&read_back.proof().unwrap(), &public_inputs).unwrap());
+    // a bundle claiming another backend is not decoded as a Groth16 proof
+    let marlin = ProofBundle { backend: Some(Backend::Marlin.id().to_string()), ..read_back.clone() };
+    assert!(marlin.proof::<Bls12_381>().is_err());
+
+    let mut wrong_size = read_back.clone();
+    wrong_size.n = 4;
+    assert!(matches!(wrong_size.check_matches(&verifier), Err(ZycleError::KeyMismatch(_))));
+    let err = read_back.check_matches_circuit::<Bls12_381>(CircuitVariant::Sparse, plain, 3);
+    assert!(matches!(err, Err(ZycleError::KeyMismatch(_))));
+    let err = read_back.check_matches_circuit::<ark_bn254::Bn254>(CircuitVariant::TopoSort, plain, 3);
+    assert!(matches!(err, Err(ZycleError::KeyMismatch(_))));
+
+    // the commitment kind is recorded, so a bundle for salted or row tree keys is refused by plain ones
+    assert_eq!(read_back.commitment().unwrap(), CommitmentKind::Plain);
+    for commitment in [CommitmentKind::Salted, CommitmentKind::RowTree] {
+        let other = ProofBundle { commitment: commitment.id().to_string(), ..read_back.clone() };
+        assert_eq!(other.commitment().unwrap(), commitment);
+        assert!(matches!(other.check_matches(&verifier), Err(ZycleError::KeyMismatch(_))));
+    }
+    let unknown = ProofBundle { commitment: "sha256".to_string(), ..read_back.clone() };
+    assert!(unknown.commitment().is_err() && unknown.check_matches(&verifier).is_err());
+
+    // a zero input, like start node 0 of a reachability proof, reads back too
+    let inputs = [public_inputs[0], Fr::zero()];
+    let bundle = ProofBundle::new(&proof, &inputs, 3, CircuitVariant::Reachability, plain, None).unwrap();
+    assert_eq!(bundle.public_inputs::<Fr>().unwrap(), inputs);
+}
@@ -0,0 +1,282 @@
+use ark_ff::PrimeField;
+use ark_r1cs_std::{
+    alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar, uint16::UInt16, uint32::UInt32,
+    uint8::UInt8,
+};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+
+use crate::graph_checks::check_subgraph_topo_sort_vec;
+use crate::hashing::{hasher_var_vec, salted_hasher_var_vec};
+use crate::merkle::row_tree_root_var;
+use crate::utils::{position_witnesses, Boolean2DVec, BooleanVec, BoundedFpVar};
+
+// identifies which statement a circuit proves, recorded alongside keys and proofs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitVariant {
+    // every node of the graph is in the topological sort
+    TopoSort,
+    // the graph is a committed edge list, positions are looked up per edge (see sparse.rs)
+    Sparse,
+    // only the nodes reachable from a public start node are sorted (see reachability.rs)
+    Reachability,
+}
+
+impl CircuitVariant {
+    pub fn id(&self) -> &'static str {
+        match self {
+            CircuitVariant::TopoSort => "toposort",
+            CircuitVariant::Sparse => "sparse",
+            CircuitVariant::Reachability => "reachability",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "toposort" => Some(CircuitVariant::TopoSort),
+            "sparse" => Some(CircuitVariant::Sparse),
+            "reachability" => Some(CircuitVariant::Reachability),
+            _ => None,
+        }
+    }
+}
+
+// what the public input of the dense circuit commits to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitmentKind {
+    // Poseidon hash of the whole matrix
+    Plain,
+    // the same with a secret salt absorbed first (see salt.rs)
+    Salted,
+    // root of a Merkle tree over the rows with salted leaves, so single rows can be opened later (see merkle.rs)
+    RowTree,
+}
+
+impl CommitmentKind {
+    // whether proofs against this kind of commitment take a secret salt
+    pub fn is_salted(self) -> bool {
+        matches!(self, CommitmentKind::Salted | CommitmentKind::RowTree)
+    }
+
+    pub fn id(&self) -> &'static str {
+        match self {
+            CommitmentKind::Plain => "plain",
+            CommitmentKind::Salted => "salted",
+            CommitmentKind::RowTree => "row-tree",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "plain" => Some(CommitmentKind::Plain),
+            "salted" => Some(CommitmentKind::Salted),
+            "row-tree" => Some(CommitmentKind::RowTree),
+            _ => None,
+        }
+    }
+
+    // the byte key headers record it as
+    pub fn to_byte(self) -> u8 {
+        match self {
+            CommitmentKind::Plain => 0,
+            CommitmentKind::Salted => 1,
+            CommitmentKind::RowTree => 2,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(CommitmentKind::Plain),
+            1 => Some(CommitmentKind::Salted),
+            2 => Some(CommitmentKind::RowTree),
+            _ => None,
+        }
+    }
+}
+
+// how the positions of the topological sort are represented in the circuit, which bounds the graph size
+// Field(bits) holds positions as field elements below 2^bits, the cheapest to compare
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionWidth {
+    U8,
+    U16,
+    U32,
+    Field(usize),
+}
+
+impl PositionWidth {
+    // smallest unsigned width that can give every one of `nodes` nodes its own position
+    pub fn for_nodes(nodes: usize) -> Option<Self> {
+        [PositionWidth::U8, PositionWidth::U16, PositionWidth::U32]
+            .into_iter()
+            .find(|width| width.max_nodes() >= nodes)
+    }
+
+    // positions run from 0 to max_nodes - 1, on 32-bit targets a usize cannot hold 1 << 32, but no
+    // graph there can have that many nodes anyway
+    pub fn max_nodes(&self) -> usize {
+        1usize.checked_shl(self.bits() as u32).unwrap_or(usize::MAX)
+    }
+
+    pub fn bits(&self) -> usize {
+        match self {
+            PositionWidth::U8 => 8,
+            PositionWidth::U16 => 16,
+            PositionWidth::U32 => 32,
+            PositionWidth::Field(bits) => *bits,
+        }
+    }
+
+    pub fn id(&self) -> String {
+        match self {
+            PositionWidth::U8 => "u8".to_string(),
+            PositionWidth::U16 => "u16".to_string(),
+            PositionWidth::U32 => "u32".to_string(),
+            PositionWidth::Field(bits) => format!("field{}", bits),
+        }
+    }
+
+    // native positions are u32s, so a field width can be at most 32 bits
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "u8" => Some(PositionWidth::U8),
+            "u16" => Some(PositionWidth::U16),
+            "u32" => Some(PositionWidth::U32),
+            _ => {
+                let bits: usize = id.strip_prefix("field")?.parse().ok()?;
+                (1..=32).contains(&bits).then_some(PositionWidth::Field(bits))
+            }
+        }
+    }
+}
+
+// struct for generating the circuit trace
+//the fields are the inputs to the circuit
+// the number of nodes is adj_matrix.len(), which together with position_width fixes the shape of the circuit
+#[derive(Clone)]
+pub struct MyGraphCircuitStruct<ConstraintF: PrimeField> {
+    pub adj_matrix: Vec<Vec<bool>>,
+    pub toposort: Vec<u32>,
+    pub position_width: PositionWidth,
+    pub adj_hash: ConstraintF,
+    pub commitment: CommitmentKind,
+    // secret salt hashed along with the matrix, only used by salted and row tree commitments
+    pub salt: Option<ConstraintF>,
+}
+
+impl<ConstraintF: PrimeField> MyGraphCircuitStruct<ConstraintF> {
+    // placeholder inputs used when only the circuit shape matters (e.g. during setup)
+    pub fn blank(nodes: usize, position_width: PositionWidth) -> Self {
+        Self {
+            adj_matrix: vec![vec![false; nodes]; nodes],
+            toposort: (0..nodes as u32).collect(),
+            position_width,
+            adj_hash: ConstraintF::zero(),
+            commitment: CommitmentKind::Plain,
+            salt: None,
+        }
+    }
+
+    // same shape as blank, for keys that take a salted commitment
+    pub fn blank_salted(nodes: usize, position_width: PositionWidth) -> Self {
+        Self {
+            commitment: CommitmentKind::Salted,
+            salt: Some(ConstraintF::zero()),
+            ..Self::blank(nodes, position_width)
+        }
+    }
+
+    // same shape as blank, for keys committing to the graph by a Merkle tree over its rows
+    pub fn blank_row_tree(nodes: usize, position_width: PositionWidth) -> Self {
+        Self {
+            commitment: CommitmentKind::RowTree,
+            salt: Some(ConstraintF::zero()),
+            ..Self::blank(nodes, position_width)
+        }
+    }
+
+    // the blank circuit for keys taking the given kind of commitment
+    pub fn blank_with_commitment(nodes: usize, position_width: PositionWidth, commitment: CommitmentKind) -> Self {
+        match commitment {
+            CommitmentKind::Plain => Self::blank(nodes, position_width),
+            CommitmentKind::Salted => Self::blank_salted(nodes, position_width),
+            CommitmentKind::RowTree => Self::blank_row_tree(nodes, position_width),
+        }
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.adj_matrix.len()
+    }
+}
+
+// Takes the struct that holds inputs and generates the entire circuit
+impl<ConstraintF: PrimeField> ConstraintSynthesizer<ConstraintF> for MyGraphCircuitStruct<ConstraintF> {
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let nodes = self.nodes();
+        // create input vars
+        let adj_matrix_var = Boolean2DVec::new_witness(cs.clone(), || Ok(self.adj_matrix))?;
+        let hash_claim_var: FpVar<ConstraintF> = FpVar::new_input(cs.clone(), || Ok(self.adj_hash))?;
+
+        // check the claimed hash is correct
+        let hash_real: FpVar<ConstraintF> = match self.commitment {
+            CommitmentKind::Plain => hasher_var_vec(cs.clone(), &adj_matrix_var)?[0].clone(),
+            CommitmentKind::Salted => {
+                let salt_var = FpVar::new_witness(cs.clone(), || self.salt.ok_or(SynthesisError::AssignmentMissing))?;
+                salted_hasher_var_vec(cs.clone(), &adj_matrix_var, &salt_var)?[0].clone()
+            }
+            CommitmentKind::RowTree => {
+                let salt_var = FpVar::new_witness(cs.clone(), || self.salt.ok_or(SynthesisError::AssignmentMissing))?;
+                row_tree_root_var(cs.clone(), &adj_matrix_var, &salt_var)?
+            }
+        };
+        hash_real.enforce_equal(&hash_claim_var)?;
+
+        // check the graph properties, every node is part of the sort
+        let subgraph_nodes = BooleanVec(vec![Boolean::TRUE; nodes]);
+        check_subgraph_with_width(cs, &adj_matrix_var, &subgraph_nodes, &self.toposort, self.position_width)
+    }
+}
+
+// Allocates the positions as wide as the keys were made for and checks they sort the subgraph
+pub fn check_subgraph_with_width<ConstraintF: PrimeField>(
+    cs: ConstraintSystemRef<ConstraintF>,
+    adj_matrix_var: &Boolean2DVec<ConstraintF>,
+    subgraph_nodes: &BooleanVec<ConstraintF>,
+    toposort: &[u32],
+    position_width: PositionWidth,
+) -> Result<(), SynthesisError> {
+    match position_width {
+        PositionWidth::U8 => {
+            let topo_var: Vec<UInt8<ConstraintF>> = position_witnesses::<_, _, u8>(cs, toposort)?;
+            check_subgraph_topo_sort_vec(adj_matrix_var, subgraph_nodes, &topo_var)
+        }
+        PositionWidth::U16 => {
+            let topo_var: Vec<UInt16<ConstraintF>> = position_witnesses::<_, _, u16>(cs, toposort)?;
+            check_subgraph_topo_sort_vec(adj_matrix_var, subgraph_nodes, &topo_var)
+        }
+        PositionWidth::U32 => {
+            let topo_var: Vec<UInt32<ConstraintF>> = position_witnesses::<_, _, u32>(cs, toposort)?;
+            check_subgraph_topo_sort_vec(adj_matrix_var, subgraph_nodes, &topo_var)
+        }
+        PositionWidth::Field(bits) => {
+            let topo_var = toposort
+                .iter()
+                .map(|&pos| BoundedFpVar::new_witness_with_bits(cs.clone(), || Ok(ConstraintF::from(pos)), bits))
+                .collect::<Result<Vec<_>, _>>()?;
+            check_subgraph_topo_sort_vec(adj_matrix_var, subgraph_nodes, &topo_var)
+        }
+    }
+}
+
+#[test]
+fn position_width_bounds() {
+    assert_eq!(PositionWidth::for_nodes(256), Some(PositionWidth::U8));
+    assert_eq!(PositionWidth::for_nodes(257), Some(PositionWidth::U16));
+    assert_eq!(PositionWidth::Field(12).max_nodes(), 4096);
+    // saturates rather than overflowing where a usize has 32 bits
+    for width in [PositionWidth::U32, PositionWidth::Field(32)] {
+        assert_eq!(width.max_nodes() as u64, (1u64 << 32).min(usize::MAX as u64));
+    }
+}
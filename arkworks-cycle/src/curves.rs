@@ -0,0 +1,49 @@
+use ark_bls12_377::Bls12_377;
+use ark_bls12_381::Bls12_381;
+use ark_bn254::Bn254;
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::pairing::Pairing;
+
+// A pairing the Groth16 pipeline runs on, its scalar field is the circuit field and gets its own
+// Poseidon parameters (see hashing::poseidon_params)
+pub trait ZycleCurve: Pairing<ScalarField: Absorb> {
+    // recorded in key headers and proof bundles, so keys and proofs for another curve are refused
+    const CURVE_ID: &'static str;
+}
+
+// the default, what keys were generated on before other curves were supported
+impl ZycleCurve for Bls12_381 {
+    const CURVE_ID: &'static str = "bls12-381";
+}
+
+// cheap to verify on Ethereum, which has precompiles for its pairing
+impl ZycleCurve for Bn254 {
+    const CURVE_ID: &'static str = "bn254";
+}
+
+// its scalar field is the base field of BW6-761, so proofs can be verified inside another circuit
+impl ZycleCurve for Bls12_377 {
+    const CURVE_ID: &'static str = "bls12-377";
+}
+
+// the curves the CLI can generate keys on
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Curve {
+    Bls12_381,
+    Bn254,
+    Bls12_377,
+}
+
+impl Curve {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Curve::Bls12_381 => Bls12_381::CURVE_ID,
+            Curve::Bn254 => Bn254::CURVE_ID,
+            Curve::Bls12_377 => Bls12_377::CURVE_ID,
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        [Curve::Bls12_381, Curve::Bn254, Curve::Bls12_377].into_iter().find(|curve| curve.id() == id)
+    }
+}
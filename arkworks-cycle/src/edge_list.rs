@@ -0,0 +1,223 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use ark_ff::PrimeField;
+use ark_r1cs_std::alloc::AllocVar;
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::io::{self, Read, Write};
+
+use crate::graph_io::to_adjacency_array;
+use crate::utils::{Boolean2DArray, Boolean2DVec};
+
+// column names recognised as a header row rather than an edge
+const HEADER_NAMES: [&str; 8] = ["sender", "receiver", "source", "target", "from", "to", "src", "dst"];
+
+// A transaction graph read from an edge list, where accounts are named by string ids
+// node i of the adjacency matrix is the account ids[i], numbered in order of first appearance
+pub struct EdgeListGraph {
+    pub ids: Vec<String>,
+    pub adj_matrix: Vec<Vec<bool>>,
+}
+
+// The same graph as (sender, receiver) node indices, which takes memory in proportion to the edges
+// rather than an n x n matrix, for the sparse circuit
+pub struct EdgeList {
+    pub ids: Vec<String>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl EdgeList {
+    // Reads (sender, receiver) rows, any further columns (amounts, timestamps, ...) are ignored
+    // a first row made of column names like "sender,receiver" is skipped, as are lines starting with #
+    pub fn from_reader<R: Read>(reader: R, delimiter: u8) -> Result<Self, io::Error> {
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .comment(Some(b'#'))
+            .from_reader(reader);
+
+        let mut edges = Vec::new();
+        for (row, record) in csv_reader.records().enumerate() {
+            let record = record.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            if record.len() < 2 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("edge list row {} needs a sender and a receiver", row + 1),
+                ));
+            }
+            if row == 0 && is_header(&record[0]) && is_header(&record[1]) {
+                continue;
+            }
+            edges.push((record[0].to_string(), record[1].to_string()));
+        }
+        Ok(Self::from_edges(&[], &edges))
+    }
+
+    // the graph on the listed nodes and the endpoints of the edges, nodes first and then in order
+    // of first appearance in the edges, so isolated nodes can be included
+    pub fn from_edges<S: AsRef<str>>(nodes: &[S], edges: &[(S, S)]) -> Self {
+        let mut ids = Vec::new();
+        let mut index_of = HashMap::new();
+        let mut node = |id: &str| {
+            *index_of.entry(id.to_string()).or_insert_with(|| {
+                ids.push(id.to_string());
+                ids.len() - 1
+            })
+        };
+        for id in nodes {
+            node(id.as_ref());
+        }
+        let edges = edges.iter().map(|(sender, receiver)| (node(sender.as_ref()), node(receiver.as_ref()))).collect();
+        Self { ids, edges }
+    }
+
+    pub fn to_graph(&self) -> EdgeListGraph {
+        let mut adj_matrix = vec![vec![false; self.ids.len()]; self.ids.len()];
+        for &(sender, receiver) in &self.edges {
+            adj_matrix[sender][receiver] = true;
+        }
+        EdgeListGraph { ids: self.ids.clone(), adj_matrix }
+    }
+}
+
+impl EdgeListGraph {
+    // see EdgeList::from_reader
+    pub fn from_reader<R: Read>(reader: R, delimiter: u8) -> Result<Self, io::Error> {
+        Ok(EdgeList::from_reader(reader, delimiter)?.to_graph())
+    }
+
+    // see EdgeList::from_edges
+    pub fn from_edges<S: AsRef<str>>(nodes: &[S], edges: &[(S, S)]) -> Self {
+        EdgeList::from_edges(nodes, edges).to_graph()
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn index_of(&self, id: &str) -> Option<usize> {
+        self.ids.iter().position(|node| node == id)
+    }
+
+    pub fn to_adjacency_array<const N: usize>(&self) -> Result<[[bool; N]; N], io::Error> {
+        to_adjacency_array(&self.adj_matrix)
+    }
+
+    // allocates the adjacency matrix as a witness for the circuit
+    // a graph whose node count is not N can never satisfy the size N circuit
+    pub fn new_witness<const N: usize, ConstraintF: PrimeField>(
+        &self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<Boolean2DArray<N, ConstraintF>, SynthesisError> {
+        let adj_matrix = self
+            .to_adjacency_array::<N>()
+            .map_err(|_| SynthesisError::Unsatisfiable)?;
+        Boolean2DArray::new_witness(cs, || Ok(adj_matrix))
+    }
+
+    // runtime sized form of new_witness, the shape follows the graph
+    pub fn new_witness_vec<ConstraintF: PrimeField>(
+        &self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<Boolean2DVec<ConstraintF>, SynthesisError> {
+        Boolean2DVec::new_witness(cs, || Ok(self.adj_matrix.clone()))
+    }
+
+    // Writes the id -> node index mapping as "index,id" rows, so the matrix behind a proof
+    // can be related back to the accounts it came from
+    pub fn write_id_mapping<W: Write>(&self, writer: W) -> Result<(), io::Error> {
+        write_id_mapping(&self.ids, writer)
+    }
+}
+
+// the id -> node index mapping of EdgeListGraph::write_id_mapping, for graphs kept as an EdgeList
+pub fn write_id_mapping<W: Write>(ids: &[String], writer: W) -> Result<(), io::Error> {
+    let mut csv_writer = csv::Writer::from_writer(writer);
+    csv_writer.write_record(["index", "id"])?;
+    for (i, id) in ids.iter().enumerate() {
+        csv_writer.write_record([i.to_string().as_str(), id])?;
+    }
+    csv_writer.flush()
+}
+
+// Reads an edge list file, tab separated if the file ends in .tsv and comma separated otherwise
+pub fn read_edge_list(file_path: &str) -> Result<EdgeListGraph, io::Error> {
+    Ok(read_edges(file_path)?.to_graph())
+}
+
+// same as read_edge_list without building the adjacency matrix
+pub fn read_edges(file_path: &str) -> Result<EdgeList, io::Error> {
+    let file = std::fs::File::open(file_path)?;
+    EdgeList::from_reader(io::BufReader::new(file), delimiter_for(file_path))
+}
+
+pub fn is_edge_list_path(file_path: &str) -> bool {
+    matches!(extension(file_path).as_deref(), Some("csv") | Some("tsv"))
+}
+
+fn delimiter_for(file_path: &str) -> u8 {
+    if extension(file_path).as_deref() == Some("tsv") {
+        b'\t'
+    } else {
+        b','
+    }
+}
+
+fn extension(file_path: &str) -> Option<String> {
+    Path::new(file_path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+}
+
+fn is_header(cell: &str) -> bool {
+    HEADER_NAMES.contains(&cell.to_ascii_lowercase().as_str())
+}
+
+#[test]
+fn edge_list_maps_ids_to_indices() {
+    let csv = "sender,receiver,amount\nalice,bob,10\nbob,carol,5\n# refund\nalice,carol,1\n";
+    let graph = EdgeListGraph::from_reader(csv.as_bytes(), b',').unwrap();
+    assert_eq!(graph.ids, vec!["alice", "bob", "carol"]);
+    assert_eq!(
+        graph.adj_matrix,
+        vec![
+            vec![false, true, true],
+            vec![false, false, true],
+            vec![false, false, false],
+        ]
+    );
+    assert_eq!(graph.index_of("carol"), Some(2));
+    let edges = EdgeList::from_reader(csv.as_bytes(), b',').unwrap();
+    assert_eq!(edges.ids, graph.ids);
+    assert_eq!(edges.edges, vec![(0, 1), (1, 2), (0, 2)]);
+
+    let tsv = "acct-9\tacct-1\nacct-1\tacct-9\n";
+    let graph = EdgeListGraph::from_reader(tsv.as_bytes(), b'\t').unwrap();
+    assert_eq!(graph.ids, vec!["acct-9", "acct-1"]);
+    assert!(graph.adj_matrix[0][1] && graph.adj_matrix[1][0]);
+
+    let mut mapping = Vec::new();
+    graph.write_id_mapping(&mut mapping).unwrap();
+    assert_eq!(String::from_utf8(mapping).unwrap(), "index,id\n0,acct-9\n1,acct-1\n");
+}
+
+#[test]
+fn edge_list_witness_matches_matrix() {
+    use ark_bls12_381::Fr;
+    use ark_r1cs_std::R1CSVar;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let graph = EdgeListGraph::from_reader("a,b\nb,c\n".as_bytes(), b',').unwrap();
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let adj_matrix_var = graph.new_witness::<3, Fr>(cs.clone()).unwrap();
+    for i in 0..3 {
+        for j in 0..3 {
+            assert_eq!(adj_matrix_var.0[i][j].value().unwrap(), graph.adj_matrix[i][j]);
+        }
+    }
+    assert!(graph.new_witness::<4, Fr>(cs.clone()).is_err());
+    assert_eq!(graph.new_witness_vec::<Fr>(cs).unwrap().0.len(), 3);
+    assert!(EdgeListGraph::from_reader("a\n".as_bytes(), b',').is_err());
+}
@@ -0,0 +1,132 @@
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::SerializationError;
+use std::{fmt, io};
+
+use crate::witness::CycleError;
+
+// The error returned by everything from setup to verification, so callers can tell bad input
+// apart from failures inside the proof system without downcasting
+#[derive(Debug)]
+pub enum ZycleError {
+    // building or checking the constraints failed, Unsatisfiable when the witness breaks them
+    Synthesis(SynthesisError),
+    // a key, proof or field element could not be (de)serialized
+    Serialization(SerializationError),
+    Io(io::Error),
+    // the graph or its witness does not fit the keys or the circuit, e.g. the wrong number of nodes
+    InvalidGraph(String),
+    // the graph has no topological sort
+    CyclicGraph(CycleError),
+    // a key, proof bundle or SRS made for another circuit, curve or graph size
+    KeyMismatch(String),
+    // the proof system itself failed, e.g. a polynomial commitment could not be opened or checked
+    ProofSystem(String),
+}
+
+impl ZycleError {
+    // whether the caller's input is at fault rather than the proof system or the file system
+    pub fn is_bad_input(&self) -> bool {
+        match self {
+            ZycleError::Synthesis(e) => matches!(e, SynthesisError::Unsatisfiable),
+            // a truncated key or proof runs out of bytes
+            ZycleError::Io(e) => matches!(
+                e.kind(),
+                io::ErrorKind::InvalidInput | io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof
+            ),
+            ZycleError::Serialization(_)
+            | ZycleError::InvalidGraph(_)
+            | ZycleError::CyclicGraph(_)
+            | ZycleError::KeyMismatch(_) => true,
+            ZycleError::ProofSystem(_) => false,
+        }
+    }
+}
+
+impl fmt::Display for ZycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ZycleError::Synthesis(e) => write!(f, "{}", e),
+            ZycleError::Serialization(e) => write!(f, "{}", e),
+            ZycleError::Io(e) => write!(f, "{}", e),
+            ZycleError::InvalidGraph(msg) | ZycleError::KeyMismatch(msg) | ZycleError::ProofSystem(msg) => {
+                write!(f, "{}", msg)
+            }
+            ZycleError::CyclicGraph(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ZycleError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ZycleError::Synthesis(e) => Some(e),
+            ZycleError::Serialization(e) => Some(e),
+            ZycleError::Io(e) => Some(e),
+            ZycleError::CyclicGraph(e) => Some(e),
+            ZycleError::InvalidGraph(_) | ZycleError::KeyMismatch(_) | ZycleError::ProofSystem(_) => None,
+        }
+    }
+}
+
+impl From<SynthesisError> for ZycleError {
+    fn from(e: SynthesisError) -> Self {
+        ZycleError::Synthesis(e)
+    }
+}
+
+impl From<SerializationError> for ZycleError {
+    fn from(e: SerializationError) -> Self {
+        ZycleError::Serialization(e)
+    }
+}
+
+impl From<io::Error> for ZycleError {
+    fn from(e: io::Error) -> Self {
+        ZycleError::Io(e)
+    }
+}
+
+impl From<CycleError> for ZycleError {
+    fn from(e: CycleError) -> Self {
+        ZycleError::CyclicGraph(e)
+    }
+}
+
+impl From<serde_json::Error> for ZycleError {
+    fn from(e: serde_json::Error) -> Self {
+        ZycleError::Io(e.into())
+    }
+}
+
+#[test]
+fn bad_input_is_an_error_not_a_panic() {
+    use crate::{AcyclicityProver, AcyclicityVerifier};
+    use ark_serialize::Validate;
+    use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, _) = AcyclicityProver::setup(3, &mut rng).unwrap();
+    let adj_matrix = [[false, true, false], [false, false, true], [false, false, false]];
+
+    // a row longer than the matrix, a sort of the wrong length and positions too wide for u8
+    let ragged = [vec![false, false, false, true], vec![false; 3], vec![false; 3]];
+    assert!(matches!(prover.prove(&ragged, &mut rng), Err(ZycleError::InvalidGraph(_))));
+    let err = prover.prove_with_toposort(&adj_matrix, &[0, 1], &mut rng).err().unwrap();
+    assert!(matches!(err, ZycleError::InvalidGraph(_)));
+    let err = prover.prove_with_toposort(&adj_matrix, &[0, 1, 1000], &mut rng).err().unwrap();
+    assert!(matches!(err, ZycleError::InvalidGraph(_)));
+    assert!(err.is_bad_input());
+
+    let cyclic = [[false, true], [true, false]];
+    let (prover, _) = AcyclicityProver::setup(2, &mut rng).unwrap();
+    let err = prover.prove(&cyclic, &mut rng).err().unwrap();
+    assert!(matches!(&err, ZycleError::CyclicGraph(e) if e.cycle.len() == 2));
+    assert_eq!(err.to_string(), "graph has a cycle: 0 -> 1 -> 0");
+
+    // empty and truncated key files
+    let err = AcyclicityVerifier::from_bytes(&[], Validate::Yes).err().unwrap();
+    assert!(matches!(err, ZycleError::Io(_)) && err.is_bad_input());
+    let err = AcyclicityVerifier::from_bytes(b"not a key at all", Validate::Yes).err().unwrap();
+    assert!(matches!(err, ZycleError::KeyMismatch(_)));
+}
@@ -1,16 +1,21 @@
 use crate::utils::CmpGadget;
 use ark_ff::PrimeField;
+use ark_r1cs_std::fields::{fp::FpVar, FieldVar};
 use ark_r1cs_std::prelude::{Boolean, EqGadget};
+use ark_r1cs_std::R1CSVar;
 use ark_relations::r1cs::SynthesisError;
-use crate::utils::{Boolean2DArray, Boolean3DArray, BooleanArray, Uint8Array};
+use crate::hashing::hashing_utils::{CryptographicSpongeVar, PoseidonSpongeVar};
+use crate::hashing::poseidon_parameters;
+use crate::utils::{
+    Boolean2DArray, Boolean2DVec, Boolean3DArray, BooleanArray, BooleanVec, Uint8Array,
+};
 
 // special case where every node should be considered
 pub fn check_topo_sort<const N: usize, ConstraintF: PrimeField>(
     adj_matrix: &Boolean2DArray<N, ConstraintF>,
     topo: &Uint8Array<N, ConstraintF>,
 ) -> Result<(), SynthesisError> {
-    let subgraph_nodes = &BooleanArray([(); N].map(|_| Boolean::constant(true)));
-    check_subgraph_topo_sort(adj_matrix, subgraph_nodes, topo)
+    check_topo_sort_vec(&adj_matrix.into(), &topo.0)
 }
 
 // Challenge: can't leak the size of the subgraph
@@ -19,18 +24,50 @@ pub fn check_subgraph_topo_sort<const N: usize, ConstraintF: PrimeField>(
     subgraph_nodes: &BooleanArray<N, ConstraintF>,
     topo: &Uint8Array<N, ConstraintF>,
 ) -> Result<(), SynthesisError> {
-    // check that there are no duplicate numbers in the toposort
-    for i in 0..N {
-        for j in i + 1..N {
-            let gt = &topo.0[i].is_gt(&topo.0[j])?;
-            let lt = &topo.0[i].is_lt(&topo.0[j])?;
-            let _ = gt.or(lt)?.enforce_equal(&Boolean::TRUE);
-        }
+    check_subgraph_topo_sort_vec(&adj_matrix.into(), &subgraph_nodes.into(), &topo.0)
+}
+
+// Combines the adj matricies into one matrix
+pub fn check_multi_subgraph_topo_sort<const N: usize, const M: usize, ConstraintF: PrimeField>(
+    adj_matrix_array: &Boolean3DArray<N, M, ConstraintF>,
+    subgraph_nodes: &BooleanArray<N, ConstraintF>,
+    topo: &Uint8Array<N, ConstraintF>,
+) -> Result<(), SynthesisError> {
+    let adj_matrices: Vec<Boolean2DVec<ConstraintF>> = adj_matrix_array
+        .0
+        .iter()
+        .map(|matrix| Boolean2DVec(matrix.iter().map(|row| row.to_vec()).collect()))
+        .collect();
+    check_multi_subgraph_topo_sort_vec(&adj_matrices, &subgraph_nodes.into(), &topo.0)
+}
+
+// runtime sized version of check_topo_sort
+// positions can be any comparable gadget, so their width is not tied to UInt8
+pub fn check_topo_sort_vec<ConstraintF: PrimeField, P: CmpGadget<ConstraintF>>(
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+    topo: &[P],
+) -> Result<(), SynthesisError> {
+    let subgraph_nodes = &BooleanVec(vec![Boolean::constant(true); adj_matrix.0.len()]);
+    check_subgraph_topo_sort_vec(adj_matrix, subgraph_nodes, topo)
+}
+
+// runtime sized version of check_subgraph_topo_sort, the matrix and arrays must all have n entries
+pub fn check_subgraph_topo_sort_vec<ConstraintF: PrimeField, P: CmpGadget<ConstraintF>>(
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+    subgraph_nodes: &BooleanVec<ConstraintF>,
+    topo: &[P],
+) -> Result<(), SynthesisError> {
+    let n = adj_matrix.0.len();
+    if subgraph_nodes.0.len() != n || topo.len() != n || adj_matrix.0.iter().any(|row| row.len() != n) {
+        return Err(SynthesisError::Unsatisfiable);
     }
 
+    // check that there are no duplicate numbers in the toposort
+    check_permutation(topo)?;
+
     // do checks relating to individual edges
-    for i in 0..N {
-        for j in 0..N {
+    for i in 0..n {
+        for j in 0..n {
             let transacted = &adj_matrix.0[i][j]; // true if person i sent to person j
             let sender_in_subgraph = &subgraph_nodes.0[i];
             let reciever_in_subgraph = &subgraph_nodes.0[j];
@@ -40,37 +77,75 @@ pub fn check_subgraph_topo_sort<const N: usize, ConstraintF: PrimeField>(
             let bad_subgraph = transacted
                 .and(sender_in_subgraph)?
                 .and(&reciever_in_subgraph.not())?;
-            let _ = bad_subgraph.enforce_equal(&Boolean::FALSE);
+            enforce_false(&bad_subgraph)?;
 
             // check if toposort is invalid because of a backwards edge
-            let wrong_order = topo.0[i].is_gt(&topo.0[j])?; // i is later in the topo sort than j
+            let wrong_order = topo[i].is_gt(&topo[j])?; // i is later in the topo sort than j
             let backwards_edge = transacted
                 .and(sender_in_subgraph)?
                 .and(reciever_in_subgraph)?
                 .and(&wrong_order)?;
-            let _ = backwards_edge.enforce_equal(&Boolean::FALSE);
+            enforce_false(&backwards_edge)?;
         }
     }
     Ok(())
 }
 
-// Combines the adj matricies into one matrix
-pub fn check_multi_subgraph_topo_sort<const N: usize, const M: usize, ConstraintF: PrimeField>(
-    adj_matrix_array: &Boolean3DArray<N, M, ConstraintF>,
-    subgraph_nodes: &BooleanArray<N, ConstraintF>,
-    topo: &Uint8Array<N, ConstraintF>,
+// enforce_equal between two different constants adds no constraint and only returns an error,
+// so a constant true is refused here like the constants in check_permutation
+fn enforce_false<ConstraintF: PrimeField>(bit: &Boolean<ConstraintF>) -> Result<(), SynthesisError> {
+    if bit.is_constant() && bit.value()? {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    bit.enforce_equal(&Boolean::FALSE)
+}
+
+// Checks that topo holds every number in 0..n exactly once, in linear size rather than comparing every pair.
+// prod (gamma - topo[i]) and prod (gamma - i) are polynomials in gamma that only agree everywhere when
+// both hold the same numbers, and two different ones agree on at most n values. gamma is squeezed from a
+// Poseidon hash of the positions, so the prover cannot choose the positions after seeing it
+pub fn check_permutation<ConstraintF: PrimeField, P: CmpGadget<ConstraintF>>(
+    topo: &[P],
+) -> Result<(), SynthesisError> {
+    let positions = topo.iter().map(|pos| pos.to_fp_var()).collect::<Result<Vec<_>, _>>()?;
+    let mut sponge = PoseidonSpongeVar::<ConstraintF>::new(positions.cs(), &poseidon_parameters());
+    sponge.absorb(&positions)?;
+    let gamma = sponge.squeeze_field_elements(1)?.remove(0);
+
+    let mut claimed = FpVar::one();
+    let mut expected = FpVar::one();
+    for (i, position) in positions.iter().enumerate() {
+        claimed *= &gamma - position;
+        expected *= &gamma - FpVar::Constant(ConstraintF::from(i as u64));
+    }
+    // enforce_equal lets two different constants through, so compare those here
+    if claimed.is_constant() && claimed.value()? != expected.value()? {
+        return Err(SynthesisError::Unsatisfiable);
+    }
+    claimed.enforce_equal(&expected)
+}
+
+// runtime sized version of check_multi_subgraph_topo_sort
+pub fn check_multi_subgraph_topo_sort_vec<ConstraintF: PrimeField, P: CmpGadget<ConstraintF>>(
+    adj_matrices: &[Boolean2DVec<ConstraintF>],
+    subgraph_nodes: &BooleanVec<ConstraintF>,
+    topo: &[P],
 ) -> Result<(), SynthesisError> {
-    let combined_adj_matrix = &mut Boolean2DArray(adj_matrix_array.0[0].clone());
+    let n = subgraph_nodes.0.len();
+    let mut combined_adj_matrix = Boolean2DVec(vec![vec![Boolean::constant(false); n]; n]);
 
-    for k in 1..M {
-        for i in 0..N {
-            for j in 0..N {
+    for adj_matrix in adj_matrices {
+        if adj_matrix.0.len() != n || adj_matrix.0.iter().any(|row| row.len() != n) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        for i in 0..n {
+            for j in 0..n {
                 combined_adj_matrix.0[i][j] =
-                    combined_adj_matrix.0[i][j].or(&adj_matrix_array.0[k][i][j])?;
+                    combined_adj_matrix.0[i][j].or(&adj_matrix.0[i][j])?;
             }
         }
     }
-    check_subgraph_topo_sort(combined_adj_matrix, subgraph_nodes, topo)
+    check_subgraph_topo_sort_vec(&combined_adj_matrix, subgraph_nodes, topo)
 }
 
 #[test]
@@ -438,3 +513,155 @@ fn invalid_multi_bad_topo() {
     }
     assert!(!is_satisfied);
 }
+
+#[test]
+fn wide_positions() {
+    use crate::utils::{position_witnesses, BoundedFpVar};
+    use ark_bls12_381::Fq as F;
+    use ark_r1cs_std::{alloc::AllocVar, uint16::UInt16};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let adj_matrix = vec![
+        vec![false, true, true],  //   [0] -> [1]
+        vec![false, false, true], //     \     |
+        vec![false, false, false], //     --> [2]
+    ];
+    // positions have to be exactly 0..n, so distinct and ordered but larger ones are refused too
+    let cases = [
+        (vec![0u32, 1, 2], true),
+        (vec![1, 0, 2], false),
+        (vec![0, 2, 2], false),
+        (vec![7, 300, 1000], false),
+    ];
+    for (topo, valid) in cases {
+        let cs = ConstraintSystem::<F>::new_ref();
+        let adj_matrix_var = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_matrix.clone())).unwrap();
+        let topo_var: Vec<UInt16<F>> = position_witnesses::<_, _, u16>(cs.clone(), &topo).unwrap();
+        check_topo_sort_vec(&adj_matrix_var, &topo_var).unwrap();
+        assert_eq!(cs.is_satisfied().unwrap(), valid);
+
+        let cs = ConstraintSystem::<F>::new_ref();
+        let adj_matrix_var = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_matrix.clone())).unwrap();
+        let topo_var = topo
+            .iter()
+            .map(|&pos| BoundedFpVar::new_witness_with_bits(cs.clone(), || Ok(F::from(pos)), 12))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        check_topo_sort_vec(&adj_matrix_var, &topo_var).unwrap();
+        assert_eq!(cs.is_satisfied().unwrap(), valid);
+    }
+}
+
+#[test]
+fn permutation_check() {
+    use ark_bls12_381::Fq as F;
+    use ark_r1cs_std::uint8::UInt8;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    for (topo, valid) in [
+        (vec![3u8, 0, 2, 1, 4], true),
+        (vec![0, 1, 2, 3, 3], false),
+        (vec![0, 1, 2, 3, 5], false),
+        (vec![4, 3, 2, 1, 0], true),
+    ] {
+        let cs = ConstraintSystem::<F>::new_ref();
+        let topo_var = UInt8::new_witness_vec(cs.clone(), &topo).unwrap();
+        check_permutation(&topo_var).unwrap();
+        assert_eq!(cs.is_satisfied().unwrap(), valid);
+    }
+
+    // constants are checked right away
+    assert!(check_permutation::<F, _>(&UInt8::constant_vec(&[1, 0])).is_ok());
+    assert!(check_permutation::<F, _>(&UInt8::constant_vec(&[1, 1])).is_err());
+
+    // grows linearly with the number of positions: doubling them doubles the constraints up to a constant
+    // for the sponge, where comparing every pair would take four times as many
+    let constraints = |n: usize| {
+        let cs = ConstraintSystem::<F>::new_ref();
+        let topo: Vec<u8> = (0..n as u8).collect();
+        check_permutation(&UInt8::new_witness_vec(cs.clone(), &topo).unwrap()).unwrap();
+        cs.num_constraints()
+    };
+    for n in [16, 32, 64] {
+        let (single, double) = (constraints(n), constraints(2 * n));
+        assert!(
+            double.abs_diff(2 * single) <= 16,
+            "{} constraints for {} positions, {} for {}", single, n, double, 2 * n
+        );
+    }
+}
+
+#[test]
+fn soundness_minimal_violations() {
+    use ark_bls12_381::Fq as F;
+    use ark_r1cs_std::{alloc::AllocVar, uint8::UInt8};
+    use ark_relations::r1cs::ConstraintSystem;
+
+    // the single edge 0 -> 1, sorted and fully in the subgraph
+    let edge = vec![vec![false, true], vec![false, false]];
+    // each witness breaks one family of constraints and keeps the others satisfied
+    let edge_violations = vec![
+        ("duplicate position", vec![true, true], vec![0u8, 0]),
+        ("edge leaving the subgraph", vec![true, false], vec![0, 1]),
+        ("backward edge", vec![true, true], vec![1, 0]),
+    ];
+
+    // the same families where other edges are around and the positions are not the identity:
+    // 3 -> 1, 3 -> 0, 2 -> 4, 4 -> 0, 1 -> 5 and 0 -> 5, sorted 3, 2, 4, 1, 0, 5, with node 2 outside
+    // the subgraph so its edge may point anywhere
+    let mut dag = vec![vec![false; 6]; 6];
+    for (i, j) in [(3, 1), (3, 0), (2, 4), (4, 0), (1, 5), (0, 5)] {
+        dag[i][j] = true;
+    }
+    let subgraph = vec![true, true, false, true, true, true];
+    let dag_violations = vec![
+        // node 5 takes the position of node 0, which keeps 0 -> 5 from going backwards
+        ("duplicate position", subgraph.clone(), vec![4u8, 3, 1, 0, 2, 4]),
+        // 3 -> 1 leaves, 1 -> 5 is fine as its sender is outside
+        ("edge leaving the subgraph", vec![true, false, false, true, true, true], vec![4, 3, 1, 0, 2, 5]),
+        // nodes 0 and 5 swap, only 0 -> 5 goes backwards
+        ("backward edge", subgraph.clone(), vec![5, 3, 1, 0, 2, 4]),
+    ];
+    let cases = [
+        (edge, vec![(vec![true, true], vec![0u8, 1])], edge_violations),
+        (
+            dag,
+            // 2 -> 4 may go backwards, node 2 is not in the subgraph
+            vec![(subgraph.clone(), vec![4, 3, 1, 0, 2, 5]), (subgraph, vec![4, 3, 2, 0, 1, 5])],
+            dag_violations,
+        ),
+    ];
+
+    for (adj_matrix, valid, violations) in &cases {
+        let check = |subgraph_nodes: &[bool], topo: &[u8]| {
+            let cs = ConstraintSystem::<F>::new_ref();
+            let adj_matrix_var = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_matrix.clone())).unwrap();
+            let subgraph_var = BooleanVec::new_witness(cs.clone(), || Ok(subgraph_nodes.to_vec())).unwrap();
+            let topo_var = UInt8::new_witness_vec(cs.clone(), topo).unwrap();
+            check_subgraph_topo_sort_vec(&adj_matrix_var, &subgraph_var, &topo_var).unwrap();
+            cs.is_satisfied().unwrap()
+        };
+        for (subgraph_nodes, topo) in valid {
+            assert!(check(subgraph_nodes, topo), "{:?} was refused", topo);
+        }
+        for (family, subgraph_nodes, topo) in violations {
+            assert!(!check(subgraph_nodes, topo), "{} was accepted", family);
+        }
+
+        // with constants there is nothing to enforce, so the violation has to surface as an error
+        // rather than be dropped along with the constraint
+        let check_constants = |subgraph_nodes: &[bool], topo: &[u8]| {
+            let constants = |row: &[bool]| row.iter().map(|&bit| Boolean::constant(bit)).collect::<Vec<_>>();
+            let adj_matrix_var = Boolean2DVec::<F>(adj_matrix.iter().map(|row| constants(row)).collect());
+            let subgraph_var = BooleanVec(constants(subgraph_nodes));
+            check_subgraph_topo_sort_vec(&adj_matrix_var, &subgraph_var, &UInt8::constant_vec(topo))
+        };
+        for (subgraph_nodes, topo) in valid {
+            check_constants(subgraph_nodes, topo).unwrap();
+        }
+        for (family, subgraph_nodes, topo) in violations {
+            let result = check_constants(subgraph_nodes, topo);
+            assert!(matches!(result, Err(SynthesisError::Unsatisfiable)), "{} gave {:?}", family, result);
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use ark_std::{fs, io};
+
+// Reads an adjacency matrix from a text file, see parse_adjacency_matrix for the format
+pub fn read_adjacency_matrix(file_path: &str) -> Result<Vec<Vec<bool>>, io::Error> {
+    let text = fs::read_to_string(file_path)?;
+    parse_adjacency_matrix(&text)
+}
+
+// Parses an adjacency matrix written as one row per line
+// cells are 0 or 1 and may be separated by whitespace or commas, lines starting with # are ignored
+// e.g.
+//   0 1 1
+//   0 0 1
+//   0 0 0
+pub fn parse_adjacency_matrix(text: &str) -> Result<Vec<Vec<bool>>, io::Error> {
+    let mut rows = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut row = Vec::new();
+        for cell in line.chars().filter(|c| !c.is_whitespace() && *c != ',') {
+            match cell {
+                '0' => row.push(false),
+                '1' => row.push(true),
+                _ => return Err(invalid_data(format!("unexpected character {:?} in adjacency matrix", cell))),
+            }
+        }
+        rows.push(row);
+    }
+
+    let n = rows.len();
+    if n == 0 {
+        return Err(invalid_data("adjacency matrix is empty".to_string()));
+    }
+    if let Some(i) = rows.iter().position(|row| row.len() != n) {
+        return Err(invalid_data(format!(
+            "adjacency matrix must be square: row {} has {} entries, expected {}",
+            i,
+            rows[i].len(),
+            n
+        )));
+    }
+    Ok(rows)
+}
+
+// Copies a square matrix of runtime size into the fixed size form used by the circuit
+pub fn to_adjacency_array<const N: usize>(rows: &[Vec<bool>]) -> Result<[[bool; N]; N], io::Error> {
+    if rows.len() != N || rows.iter().any(|row| row.len() != N) {
+        return Err(invalid_data(format!("expected a {}x{} adjacency matrix", N, N)));
+    }
+    let mut adj_matrix = [[false; N]; N];
+    for (i, row) in rows.iter().enumerate() {
+        adj_matrix[i].copy_from_slice(row);
+    }
+    Ok(adj_matrix)
+}
+
+// Parses a topological ordering given as comma separated node positions, e.g. "0,1,2,3"
+pub fn parse_toposort(text: &str, nodes: usize) -> Result<Vec<u32>, io::Error> {
+    let positions = text
+        .split(',')
+        .map(|pos| pos.trim().parse::<u32>())
+        .collect::<Result<Vec<u32>, _>>()
+        .map_err(|e| invalid_data(format!("invalid topological sort: {}", e)))?;
+    if positions.len() != nodes {
+        return Err(invalid_data(format!(
+            "topological sort has {} entries, expected {}",
+            positions.len(),
+            nodes
+        )));
+    }
+    Ok(positions)
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[test]
+fn parse_matrix_formats() {
+    let spaced = "# 0 -> 1 -> 2\n0 1 0\n0 0 1\n0 0 0\n";
+    let packed = "010\n001\n000";
+    let commas = "0,1,0\n0,0,1\n0,0,0";
+    let expected = vec![
+        vec![false, true, false],
+        vec![false, false, true],
+        vec![false, false, false],
+    ];
+    assert_eq!(parse_adjacency_matrix(spaced).unwrap(), expected);
+    assert_eq!(parse_adjacency_matrix(packed).unwrap(), expected);
+    assert_eq!(parse_adjacency_matrix(commas).unwrap(), expected);
+    assert_eq!(to_adjacency_array::<3>(&expected).unwrap()[1], [false, false, true]);
+    assert!(to_adjacency_array::<4>(&expected).is_err());
+}
+
+#[test]
+fn parse_matrix_rejects_bad_input() {
+    assert!(parse_adjacency_matrix("").is_err());
+    assert!(parse_adjacency_matrix("0 1\n0").is_err());
+    assert!(parse_adjacency_matrix("0 2\n0 0").is_err());
+    assert_eq!(parse_toposort("2, 0,1", 3).unwrap(), vec![2, 0, 1]);
+    assert!(parse_toposort("0,1", 3).is_err());
+}
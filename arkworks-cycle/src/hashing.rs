@@ -1,34 +1,32 @@
-use ark_bls12_381::fr::Fr;
 use ark_crypto_primitives::sponge::{
+    Absorb,
     CryptographicSponge, 
     FieldBasedCryptographicSponge,
     poseidon::{PoseidonSponge},
 };
 
-use ark_ff::{PrimeField};
-use ark_r1cs_std::{boolean::Boolean, R1CSVar, fields::fp::FpVar};
+use ark_ff::{BigInteger, PrimeField};
+use ark_r1cs_std::{boolean::Boolean, R1CSVar, fields::{fp::FpVar, FieldVar}};
 use ark_relations::r1cs::{SynthesisError, ConstraintSystemRef};
 
-mod hashing_utils;
+pub mod hashing_utils;
 use crate::utils::{
     Boolean2DArray, 
+    Boolean2DVec,
     // Boolean3DArray, 
     // BooleanArray, 
     // Uint8Array,
 };
 use crate::hashing::hashing_utils::{PoseidonSpongeVar, CryptographicSpongeVar};
 pub use hashing_utils::poseidon_parameters_for_test;
+pub mod poseidon_params;
+pub use poseidon_params::poseidon_parameters;
 
-// calculates the hash
-pub fn hasher<const N: usize, ConstraintF: PrimeField>(
+// calculates the hash, over the same field as hasher_var so both give the same value
+pub fn hasher<const N: usize, ConstraintF: PrimeField + Absorb>(
     adj_matrix: &Boolean2DArray<N, ConstraintF>,
-) -> Result<Vec<Fr>, SynthesisError> {
-    let sponge_param = poseidon_parameters_for_test();
-    let mut sponge = PoseidonSponge::<Fr>::new(&sponge_param);
-    let flattened_matrix = matrix_flattener(&adj_matrix).unwrap();
-    sponge.absorb(&flattened_matrix);
-    let hash = sponge.squeeze_native_field_elements(1).to_vec();
-    Ok(hash)
+) -> Result<Vec<ConstraintF>, SynthesisError> {
+    hasher_vec(&adj_matrix.into())
 }
 
 // calculates the hash and checks correctness as a circuit
@@ -36,14 +34,100 @@ pub fn hasher_var<const N: usize, ConstraintF: PrimeField>(
     cs: ConstraintSystemRef<ConstraintF>,
     adj_matrix: &Boolean2DArray<N, ConstraintF>,
 ) -> Result<Vec<FpVar<ConstraintF>>, SynthesisError> {
-    let sponge_param = poseidon_parameters_for_test();
+    hasher_var_vec(cs, &adj_matrix.into())
+}
+
+// runtime sized version of hasher, gives the same hash for the same matrix
+pub fn hasher_vec<ConstraintF: PrimeField + Absorb>(
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+) -> Result<Vec<ConstraintF>, SynthesisError> {
+    let sponge_param = poseidon_parameters();
+    let mut sponge = PoseidonSponge::<ConstraintF>::new(&sponge_param);
+    sponge.absorb(&matrix_packer_vec(adj_matrix)?);
+    let hash = sponge.squeeze_native_field_elements(1).to_vec();
+    Ok(hash)
+}
+
+// runtime sized version of hasher_var
+pub fn hasher_var_vec<ConstraintF: PrimeField>(
+    cs: ConstraintSystemRef<ConstraintF>,
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+) -> Result<Vec<FpVar<ConstraintF>>, SynthesisError> {
+    let sponge_param = poseidon_parameters();
     let mut sponge = PoseidonSpongeVar::<ConstraintF>::new(cs, &sponge_param);
-    let flattened_matrix = matrix_flattener_var(&adj_matrix).unwrap();
-    sponge.absorb(&flattened_matrix)?;
+    sponge.absorb(&matrix_packer_var_vec(adj_matrix)?)?;
     let hash = sponge.squeeze_field_elements(1)?;
     Ok(hash)
 }
 
+// hiding version of hasher_vec, the secret salt is absorbed ahead of the matrix
+// so the hash cannot be matched by hashing likely graphs
+pub fn salted_hasher_vec<ConstraintF: PrimeField + Absorb>(
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+    salt: ConstraintF,
+) -> Result<Vec<ConstraintF>, SynthesisError> {
+    let sponge_param = poseidon_parameters();
+    let mut sponge = PoseidonSponge::<ConstraintF>::new(&sponge_param);
+    sponge.absorb(&salt);
+    sponge.absorb(&matrix_packer_vec(adj_matrix)?);
+    Ok(sponge.squeeze_native_field_elements(1).to_vec())
+}
+
+// salted_hasher_vec as a circuit, with the salt as a witness
+pub fn salted_hasher_var_vec<ConstraintF: PrimeField>(
+    cs: ConstraintSystemRef<ConstraintF>,
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+    salt: &FpVar<ConstraintF>,
+) -> Result<Vec<FpVar<ConstraintF>>, SynthesisError> {
+    let sponge_param = poseidon_parameters();
+    let mut sponge = PoseidonSpongeVar::<ConstraintF>::new(cs, &sponge_param);
+    sponge.absorb(salt)?;
+    sponge.absorb(&matrix_packer_var_vec(adj_matrix)?)?;
+    sponge.squeeze_field_elements(1)
+}
+
+// matrix bits packed into each absorbed field element, one less than the modulus has so every
+// chunk is below it (254 for BLS12-381 Fr)
+pub fn packed_bits<ConstraintF: PrimeField>() -> usize {
+    ConstraintF::MODULUS_BIT_SIZE as usize - 1
+}
+
+// bits as the field elements the hashers absorb: the number of bits first, so bit strings that
+// only differ by trailing zeros do not collide, then the bits little endian in chunks of packed_bits
+pub fn bit_packer<ConstraintF: PrimeField>(bits: &[bool]) -> Result<Vec<ConstraintF>, SynthesisError> {
+    let mut packed = vec![ConstraintF::from(bits.len() as u64)];
+    for chunk in bits.chunks(packed_bits::<ConstraintF>()) {
+        let element = ConstraintF::from_bigint(ConstraintF::BigInt::from_bits_le(chunk));
+        packed.push(element.ok_or(SynthesisError::Unsatisfiable)?);
+    }
+    Ok(packed)
+}
+
+// bit_packer as a circuit, packing costs no constraints as the chunks are below the modulus
+pub fn bit_packer_var<ConstraintF: PrimeField>(
+    bits: &[Boolean<ConstraintF>],
+) -> Result<Vec<FpVar<ConstraintF>>, SynthesisError> {
+    let mut packed = vec![FpVar::constant(ConstraintF::from(bits.len() as u64))];
+    for chunk in bits.chunks(packed_bits::<ConstraintF>()) {
+        packed.push(Boolean::le_bits_to_fp_var(chunk)?);
+    }
+    Ok(packed)
+}
+
+// the flattened matrix as packed by bit_packer
+pub fn matrix_packer_vec<ConstraintF: PrimeField>(
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+) -> Result<Vec<ConstraintF>, SynthesisError> {
+    bit_packer(&matrix_flattener_vec(adj_matrix)?)
+}
+
+pub fn matrix_packer_var_vec<ConstraintF: PrimeField>(
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+) -> Result<Vec<FpVar<ConstraintF>>, SynthesisError> {
+    let bits: Vec<Boolean<ConstraintF>> = adj_matrix.0.iter().flatten().cloned().collect();
+    bit_packer_var(&bits)
+}
+
 // Takes in a 2D Boolean array (representing an adjacency matrix) and flattens it into a boolean vector
 pub fn matrix_flattener<const N: usize, ConstraintF: PrimeField>(
     adj_matrix: &Boolean2DArray<N, ConstraintF>,
@@ -52,7 +136,7 @@ pub fn matrix_flattener<const N: usize, ConstraintF: PrimeField>(
     for i in 0..N {
         for j in 0..N {
             let transacted = &adj_matrix.0[i][j]; // true if person i sent to person j
-            if transacted.value()? == true {
+            if transacted.value()? {
                 flattened_matrix.push(true);
             } else {
                 flattened_matrix.push(false);
@@ -77,6 +161,26 @@ pub fn matrix_flattener_var<const N: usize, ConstraintF: PrimeField>(
 }
 
 
+// runtime sized version of matrix_flattener
+pub fn matrix_flattener_vec<ConstraintF: PrimeField>(
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+) -> Result<Vec<bool>, SynthesisError> {
+    let mut flattened_matrix = Vec::new();
+    for row in &adj_matrix.0 {
+        for transacted in row {
+            flattened_matrix.push(transacted.value()?);
+        }
+    }
+    Ok(flattened_matrix)
+}
+
+// runtime sized version of matrix_flattener_var
+pub fn matrix_flattener_var_vec<ConstraintF: PrimeField>(
+    adj_matrix: &Boolean2DVec<ConstraintF>,
+) -> Result<Vec<&Boolean<ConstraintF>>, SynthesisError> {
+    Ok(adj_matrix.0.iter().flatten().collect())
+}
+
 #[test]
 fn mod_gen_hash_test() {
     use ark_bls12_381::Fq as F;
@@ -172,7 +276,7 @@ fn test_hashing_one_changed_element() {
     use ark_r1cs_std::alloc::AllocVar;
 
     let adj_matrix_1 = [[false; 3]; 3];
-    let mut adj_matrix_2 = adj_matrix_1.clone();
+    let mut adj_matrix_2 = adj_matrix_1;
     adj_matrix_2[1][1] = true; // Change one element
 
     let cs = ConstraintSystem::<F>::new_ref();
@@ -215,15 +319,15 @@ fn test_hashing_large_diagonal_matrices() {
     let mut adj_matrix = [[false; N]; N];
 
     // Diagonal true values
-    for i in 0..N {
-        adj_matrix[i][i] = true;
+    for (i, row) in adj_matrix.iter_mut().enumerate() {
+        row[i] = true;
     }
 
     let cs = ConstraintSystem::<F>::new_ref();
     let adj_matrix_var_1 =
-        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix.clone())).unwrap();
+        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
     let adj_matrix_var_2 =
-        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix.clone())).unwrap();
+        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
 
     let hash1 = hasher(&adj_matrix_var_1).unwrap();
     let hash2 = hasher(&adj_matrix_var_2).unwrap();
@@ -249,9 +353,9 @@ fn test_hashing_large_sparse_matrices() {
 
     let cs = ConstraintSystem::<F>::new_ref();
     let adj_matrix_var_1 =
-        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix.clone())).unwrap();
+        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
     let adj_matrix_var_2 =
-        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix.clone())).unwrap();
+        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
 
     let hash1 = hasher(&adj_matrix_var_1).unwrap();
     let hash2 = hasher(&adj_matrix_var_2).unwrap();
@@ -259,34 +363,139 @@ fn test_hashing_large_sparse_matrices() {
     assert_eq!(hash1, hash2);
 }
 
-// Test failing because matrix is too large
-
-// #[test]
-// fn test_hashing_large_identical_matrices() {
-//     use ark_bls12_381::Fq as F;
-//     use ark_relations::r1cs::ConstraintSystem;
-//     use ark_r1cs_std::alloc::AllocVar;
-
-//     const N: usize = 100; // Large size
-//     let mut adj_matrix_1 = [[false; N]; N];
-//     let mut adj_matrix_2 = [[false; N]; N];
-
-//     // Initialize both matrices with the same pattern
-//     for i in 0..N {
-//         for j in 0..N {
-//             if i % 2 == 0 && j % 3 == 0 {
-//                 adj_matrix_1[i][j] = true;
-//                 adj_matrix_2[i][j] = true;
-//             }
-//         }
-//     }
-
-//     let cs = ConstraintSystem::<F>::new_ref();
-//     let adj_matrix_var_1 = Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix_1)).unwrap();
-//     let adj_matrix_var_2 = Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix_2)).unwrap();
-
-//     let hash1 = hasher(&adj_matrix_var_1).unwrap();
-//     let hash2 = hasher(&adj_matrix_var_2).unwrap();
-
-//     assert_eq!(hash1, hash2);
-// }
\ No newline at end of file
+#[test]
+fn test_hashing_vec_matches_array() {
+    use ark_bls12_381::Fr as F;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_r1cs_std::alloc::AllocVar;
+
+    let adj_matrix = [
+        [false, true, true, false],   //               [0]
+        [false, false, true, false],  //               / \
+        [false, false, false, true],  //             [1]->[2] -> 3
+        [false, false, false, false], //
+    ];
+    let adj_rows: Vec<Vec<bool>> = adj_matrix.iter().map(|row| row.to_vec()).collect();
+
+    let cs = ConstraintSystem::<F>::new_ref();
+    let adj_matrix_var = Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
+    let adj_vec_var = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_rows)).unwrap();
+
+    let hash = hasher(&adj_matrix_var).unwrap();
+    assert_eq!(hash, hasher_vec(&adj_vec_var).unwrap());
+    let hash_var = hasher_var_vec(cs.clone(), &adj_vec_var).unwrap();
+    assert_eq!(hash_var[0].value().unwrap(), hash[0]);
+    assert!(cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn test_hashing_large_identical_matrices() {
+    use ark_bls12_381::Fq as F;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_r1cs_std::alloc::AllocVar;
+
+    // runtime sized, 100x100 arrays of Booleans do not fit on the test thread's stack
+    const N: usize = 100; // Large size
+    let mut adj_matrix_1 = vec![vec![false; N]; N];
+    let mut adj_matrix_2 = vec![vec![false; N]; N];
+
+    // Initialize both matrices with the same pattern
+    for i in 0..N {
+        for j in 0..N {
+            if i % 2 == 0 && j % 3 == 0 {
+                adj_matrix_1[i][j] = true;
+                adj_matrix_2[i][j] = true;
+            }
+        }
+    }
+
+    let cs = ConstraintSystem::<F>::new_ref();
+    let adj_matrix_var_1 = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_matrix_1)).unwrap();
+    let adj_matrix_var_2 = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_matrix_2)).unwrap();
+
+    let hash1 = hasher_vec(&adj_matrix_var_1).unwrap();
+    let hash2 = hasher_vec(&adj_matrix_var_2).unwrap();
+
+    assert_eq!(hash1, hash2);
+
+    // packing keeps the hash well below one constraint per matrix bit
+    let before = cs.num_constraints();
+    let hash_var = hasher_var_vec(cs.clone(), &adj_matrix_var_1).unwrap();
+    assert!(cs.num_constraints() - before < N * N);
+    assert_eq!(hash_var.value().unwrap(), hash1);
+    assert!(cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn salted_hash_matches_circuit() {
+    use ark_bls12_381::Fr;
+    use ark_r1cs_std::alloc::AllocVar;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let adj_matrix = vec![vec![false, true], vec![false, false]];
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let adj_matrix_var = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
+    let salt = Fr::from(12345u64);
+    let salt_var = FpVar::new_witness(cs.clone(), || Ok(salt)).unwrap();
+
+    let hash = salted_hasher_vec(&adj_matrix_var, salt).unwrap();
+    let hash_var = salted_hasher_var_vec(cs.clone(), &adj_matrix_var, &salt_var).unwrap();
+    assert_eq!(hash_var[0].value().unwrap(), hash[0]);
+    assert!(cs.is_satisfied().unwrap());
+
+    // a different salt hides the same graph behind a different hash
+    assert_ne!(salted_hasher_vec(&adj_matrix_var, Fr::from(1u64)).unwrap(), hash);
+    assert_ne!(hasher_vec(&adj_matrix_var).unwrap(), hash);
+}
+
+// native and in-circuit hashes agree over every field, for random matrices of several sizes
+#[test]
+fn native_hash_matches_circuit() {
+    use ark_r1cs_std::alloc::AllocVar;
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+
+    fn check<F: PrimeField + Absorb, R: Rng>(rng: &mut R) {
+        for n in [1, 2, 3, 7, 16, 23] {
+            let adj_matrix: Vec<Vec<bool>> = (0..n).map(|_| (0..n).map(|_| rng.gen()).collect()).collect();
+            let cs = ConstraintSystem::<F>::new_ref();
+            let adj_matrix_var = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
+            let hash = hasher_vec(&adj_matrix_var).unwrap();
+            let hash_var = hasher_var_vec(cs.clone(), &adj_matrix_var).unwrap();
+            assert_eq!(hash_var.value().unwrap(), hash);
+            assert!(cs.is_satisfied().unwrap());
+        }
+
+        let adj_matrix: [[bool; 5]; 5] = rng.gen();
+        let cs = ConstraintSystem::<F>::new_ref();
+        let adj_matrix_var = Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
+        let hash_var = hasher_var(cs.clone(), &adj_matrix_var).unwrap();
+        assert_eq!(hash_var.value().unwrap(), hasher(&adj_matrix_var).unwrap());
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    check::<ark_bls12_381::Fr, _>(&mut rng);
+    check::<ark_bls12_381::Fq, _>(&mut rng);
+    check::<ark_bn254::Fr, _>(&mut rng);
+}
+
+#[test]
+fn packed_matrix_lengths_do_not_collide() {
+    use ark_bls12_381::Fr;
+    use ark_r1cs_std::alloc::AllocVar;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    // the bits of the 2x2 cycle are a prefix of the bits of an acyclic 3x3 graph
+    let cycle = vec![vec![false, true], vec![true, false]];
+    let acyclic = vec![vec![false, true, true], vec![false; 3], vec![false; 3]];
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    let cycle_var = Boolean2DVec::new_witness(cs.clone(), || Ok(cycle)).unwrap();
+    let acyclic_var = Boolean2DVec::new_witness(cs.clone(), || Ok(acyclic)).unwrap();
+    assert_ne!(hasher_vec(&cycle_var).unwrap(), hasher_vec(&acyclic_var).unwrap());
+
+    let packed = matrix_packer_vec(&acyclic_var).unwrap();
+    assert_eq!(packed, vec![Fr::from(9u64), Fr::from(6u64)]);
+    assert_eq!(matrix_packer_var_vec(&acyclic_var).unwrap().value().unwrap(), packed);
+}
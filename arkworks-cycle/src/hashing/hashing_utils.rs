@@ -752,12 +752,12 @@ impl<F: PrimeField> PoseidonSpongeVar<F> {
         // Full rounds apply the S Box (x^alpha) to every element of state
         if is_full_round {
             for state_item in state.iter_mut() {
-                *state_item = state_item.pow_by_constant(&[self.parameters.alpha])?;
+                *state_item = state_item.pow_by_constant([self.parameters.alpha])?;
             }
         }
         // Partial rounds apply the S Box (x^alpha) to just the first element of state
         else {
-            state[0] = state[0].pow_by_constant(&[self.parameters.alpha])?;
+            state[0] = state[0].pow_by_constant([self.parameters.alpha])?;
         }
 
         Ok(())
@@ -939,7 +939,7 @@ impl<F: PrimeField> CryptographicSpongeVar<F, PoseidonSponge<F>> for PoseidonSpo
     fn squeeze_bytes(&mut self, num_bytes: usize) -> Result<Vec<UInt8<F>>, SynthesisError> {
         let usable_bytes = ((F::MODULUS_BIT_SIZE - 1) / 8) as usize;
 
-        let num_elements = (num_bytes + usable_bytes - 1) / usable_bytes;
+        let num_elements = num_bytes.div_ceil(usable_bytes);
         let src_elements = self.squeeze_field_elements(num_elements)?;
 
         let mut bytes: Vec<UInt8<F>> = Vec::with_capacity(usable_bytes * num_elements);
@@ -955,7 +955,7 @@ impl<F: PrimeField> CryptographicSpongeVar<F, PoseidonSponge<F>> for PoseidonSpo
     fn squeeze_bits(&mut self, num_bits: usize) -> Result<Vec<Boolean<F>>, SynthesisError> {
         let usable_bits = (F::MODULUS_BIT_SIZE - 1) as usize;
 
-        let num_elements = (num_bits + usable_bits - 1) / usable_bits;
+        let num_elements = num_bits.div_ceil(usable_bits);
         let src_elements = self.squeeze_field_elements(num_elements)?;
 
         let mut bits: Vec<Boolean<F>> = Vec::with_capacity(usable_bits * num_elements);
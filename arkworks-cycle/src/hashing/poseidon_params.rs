@@ -0,0 +1,377 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use ark_crypto_primitives::sponge::poseidon::{find_poseidon_ark_and_mds, PoseidonConfig};
+use ark_ff::PrimeField;
+
+// Poseidon parameters generated the way the reference implementation (hadeshash,
+// generate_parameters_grain.sage and calc_round_numbers.py) does it:
+// - alpha is the smallest exponent for which x^alpha is a permutation of the field
+// - the round numbers are the cheapest meeting the statistical, interpolation and Groebner
+//   basis bounds at the security level, plus the recommended margin
+// - round constants and the Cauchy MDS matrix come from the Grain LFSR seeded with the field
+//   size, width and round numbers, drawing matrices until one is free of invariant subspaces
+
+// security level in bits the round numbers are picked for
+pub const SECURITY_BITS: u32 = 128;
+// field elements absorbed per permutation, the state holds one more for the capacity
+pub const RATE: usize = 2;
+
+// the parameters used by all hashing in the crate, generated once per field
+pub fn poseidon_parameters<F: PrimeField>() -> PoseidonConfig<F> {
+    type Cache = Mutex<HashMap<TypeId, Box<dyn Any + Send + Sync>>>;
+    static CACHE: OnceLock<Cache> = OnceLock::new();
+    let mut cache = CACHE.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .entry(TypeId::of::<F>())
+        .or_insert_with(|| Box::new(generate_poseidon_parameters::<F>(RATE, SECURITY_BITS)))
+        .downcast_ref::<PoseidonConfig<F>>()
+        .expect("parameters are cached under the type id of their field")
+        .clone()
+}
+
+pub fn generate_poseidon_parameters<F: PrimeField>(rate: usize, security_bits: u32) -> PoseidonConfig<F> {
+    let alpha = sbox_exponent::<F>();
+    let (full_rounds, partial_rounds) = round_numbers(log2_modulus::<F>(), rate + 1, alpha, security_bits);
+    // the reference draws matrices from the LFSR until one is secure, skip_matrices replays that
+    let (ark, mds) = (0..)
+        .map(|skip| {
+            find_poseidon_ark_and_mds::<F>(
+                F::MODULUS_BIT_SIZE as u64,
+                rate,
+                full_rounds as u64,
+                partial_rounds as u64,
+                skip,
+            )
+        })
+        .find(|(_, mds)| is_secure_mds(mds))
+        .expect("the search only ends at a secure matrix");
+    PoseidonConfig { full_rounds, partial_rounds, alpha, ark, mds, rate, capacity: 1 }
+}
+
+// smallest alpha >= 3 coprime to p - 1
+pub fn sbox_exponent<F: PrimeField>() -> u64 {
+    (3u64..)
+        .find(|&alpha| {
+            // (p - 1) mod alpha, limb by limb from the most significant one
+            let rem = F::MODULUS
+                .as_ref()
+                .iter()
+                .rev()
+                .fold(0u128, |rem, &limb| ((rem << 64) + limb as u128) % alpha as u128);
+            let rem = (rem + alpha as u128 - 1) % alpha as u128;
+            gcd(alpha, rem as u64) == 1
+        })
+        .expect("some small exponent is coprime to p - 1")
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn log2_modulus<F: PrimeField>() -> f64 {
+    F::MODULUS.as_ref().iter().rev().fold(0.0, |acc, &limb| acc * 2f64.powi(64) + limb as f64).log2()
+}
+
+// find_FD_round_numbers of calc_round_numbers.py: the cheapest (full, partial) rounds by number of
+// S-boxes over every secure pair, including the margin of 2 more full rounds and 7.5% more partial
+// rounds. As in the reference, the margin is added to the partial round counter itself, so the
+// full round counts after a secure one are tried with the raised partial rounds
+pub fn round_numbers(log2_p: f64, width: usize, alpha: u64, security_bits: u32) -> (usize, usize) {
+    let mut best = (0, 0);
+    let mut min_cost = usize::MAX;
+    for partial_rounds in 1..500 {
+        let mut partial_rounds = partial_rounds;
+        for full_rounds in (4..100).step_by(2) {
+            if !rounds_are_secure(log2_p, width, full_rounds, partial_rounds, alpha, security_bits) {
+                continue;
+            }
+            let full_rounds = full_rounds + 2;
+            partial_rounds = (partial_rounds as f64 * 1.075).ceil() as usize;
+            let cost = width * full_rounds + partial_rounds;
+            if cost < min_cost || (cost == min_cost && full_rounds < best.0) {
+                best = (full_rounds, partial_rounds);
+                min_cost = cost;
+            }
+        }
+    }
+    best
+}
+
+// the bounds of calc_round_numbers.py for x^alpha, including the Groebner basis attack of
+// eprint 2023/537
+fn rounds_are_secure(
+    log2_p: f64,
+    t: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    alpha: u64,
+    security_bits: u32,
+) -> bool {
+    let (t, r_f, r_p, m) = (t as f64, full_rounds as f64, partial_rounds as f64, security_bits as f64);
+    let n = log2_p.ceil();
+    let log_alpha = |x: f64| x.ln() / (alpha as f64).ln();
+    let statistical = if m <= (log2_p - (alpha as f64 - 1.0) / 2.0).floor() * (t + 1.0) { 6.0 } else { 10.0 };
+    let interpolation = 1.0 + (log_alpha(2.0) * m.min(n)).ceil() + log_alpha(t).ceil() - r_p;
+    let groebner_1 = log_alpha(2.0) * m.min(log2_p) - r_p;
+    let groebner_2 = t - 1.0 + log_alpha(2.0) * (m / (t + 1.0)).min(log2_p / 2.0) - r_p;
+    let groebner_3 = (t - 2.0 + m / (2.0 * ((alpha as f64).ln() / 2f64.ln())) - r_p) / (t - 1.0);
+    let bound = [statistical, interpolation, groebner_1, groebner_2, groebner_3]
+        .iter()
+        .fold(f64::MIN, |bound, rounds| bound.max(rounds.ceil()));
+    if r_f < bound {
+        return false;
+    }
+    let r = (t / 3.0).floor();
+    let over = (r_f - 1.0) * t + r_p + r + r * (r_f / 2.0) + r_p + alpha as f64;
+    let under = r * (r_f / 2.0) + r_p + alpha as f64;
+    binomial_cost_reaches(over as u64, under as u64, m)
+}
+
+// ceil(2 log2 (n choose k)) >= m, the terms of the sum are all positive so it stops once the
+// partial sum gets there, which keeps the huge binomials of the margin-raised partial rounds cheap
+fn binomial_cost_reaches(n: u64, k: u64, m: f64) -> bool {
+    let mut log2_binomial = 0.0;
+    for i in 1..=k {
+        log2_binomial += ((n - k + i) as f64).log2() - (i as f64).log2();
+        if (2.0 * log2_binomial).ceil() >= m {
+            return true;
+        }
+    }
+    (2.0 * log2_binomial).ceil() >= m
+}
+
+// MDS (every square submatrix is invertible) and no invariant subspace trails: the minimal
+// polynomial of M^i is irreducible of full degree for i up to 2t, the sufficient condition of
+// the reference
+pub fn is_secure_mds<F: PrimeField>(mds: &[Vec<F>]) -> bool {
+    let t = mds.len();
+    if (1..=t).any(|k| has_singular_minor(mds, k)) {
+        return false;
+    }
+    let mut power = mds.to_vec();
+    for _ in 0..2 * t {
+        if !is_irreducible(&characteristic_polynomial(&power)) {
+            return false;
+        }
+        power = matrix_mul(mds, &power);
+    }
+    true
+}
+
+fn has_singular_minor<F: PrimeField>(matrix: &[Vec<F>], k: usize) -> bool {
+    let subsets = index_subsets(matrix.len(), k);
+    subsets.iter().any(|rows| {
+        subsets.iter().any(|cols| {
+            let minor: Vec<Vec<F>> = rows.iter().map(|&i| cols.iter().map(|&j| matrix[i][j]).collect()).collect();
+            determinant(minor).is_zero()
+        })
+    })
+}
+
+fn index_subsets(n: usize, k: usize) -> Vec<Vec<usize>> {
+    (0u32..1 << n)
+        .filter(|mask| mask.count_ones() as usize == k)
+        .map(|mask| (0..n).filter(|i| mask & (1 << i) != 0).collect())
+        .collect()
+}
+
+fn determinant<F: PrimeField>(mut matrix: Vec<Vec<F>>) -> F {
+    let n = matrix.len();
+    let mut det = F::one();
+    for col in 0..n {
+        let Some(pivot) = (col..n).find(|&row| !matrix[row][col].is_zero()) else {
+            return F::zero();
+        };
+        if pivot != col {
+            matrix.swap(pivot, col);
+            det = -det;
+        }
+        det *= matrix[col][col];
+        let inverse = matrix[col][col].inverse().expect("pivot is non-zero");
+        let (above, below) = matrix.split_at_mut(col + 1);
+        for row in below {
+            let factor = row[col] * inverse;
+            for (entry, pivot_entry) in row[col..].iter_mut().zip(&above[col][col..]) {
+                *entry -= factor * pivot_entry;
+            }
+        }
+    }
+    det
+}
+
+fn matrix_mul<F: PrimeField>(a: &[Vec<F>], b: &[Vec<F>]) -> Vec<Vec<F>> {
+    (0..a.len())
+        .map(|i| (0..b[0].len()).map(|j| (0..b.len()).map(|k| a[i][k] * b[k][j]).sum()).collect())
+        .collect()
+}
+
+// monic, coefficients from the constant term up, by the Faddeev-LeVerrier recurrence
+fn characteristic_polynomial<F: PrimeField>(matrix: &[Vec<F>]) -> Vec<F> {
+    let n = matrix.len();
+    let mut coeffs = vec![F::zero(); n + 1];
+    coeffs[n] = F::one();
+    let mut m = vec![vec![F::zero(); n]; n];
+    for k in 1..=n {
+        let mut next = matrix_mul(matrix, &m);
+        for (i, row) in next.iter_mut().enumerate() {
+            row[i] += coeffs[n - k + 1];
+        }
+        let product = matrix_mul(matrix, &next);
+        let trace: F = (0..n).map(|i| product[i][i]).sum();
+        coeffs[n - k] = -trace * F::from(k as u64).inverse().expect("k is below the characteristic");
+        m = next;
+    }
+    coeffs
+}
+
+// Rabin's test: f of degree d divides x^(p^d) - x and is coprime to x^(p^(d/q)) - x for every
+// prime q dividing d
+fn is_irreducible<F: PrimeField>(f: &[F]) -> bool {
+    let d = f.len() - 1;
+    let x = vec![F::zero(), F::one()];
+    let mut frobenius = vec![x.clone()];
+    for _ in 0..d {
+        let last = frobenius.last().expect("starts with x");
+        frobenius.push(poly_pow_mod(last, F::characteristic(), f));
+    }
+    if !poly_rem(&poly_sub(&frobenius[d], &x), f).is_empty() {
+        return false;
+    }
+    (2..=d).filter(|q| d.is_multiple_of(*q) && (2..*q).all(|r| q % r != 0)).all(|q| {
+        let g = poly_gcd(poly_sub(&frobenius[d / q], &x), f.to_vec());
+        g.len() == 1
+    })
+}
+
+fn poly_trim<F: PrimeField>(mut a: Vec<F>) -> Vec<F> {
+    while a.last().is_some_and(|c| c.is_zero()) {
+        a.pop();
+    }
+    a
+}
+
+fn poly_sub<F: PrimeField>(a: &[F], b: &[F]) -> Vec<F> {
+    let mut out = vec![F::zero(); a.len().max(b.len())];
+    for (i, c) in a.iter().enumerate() {
+        out[i] += c;
+    }
+    for (i, c) in b.iter().enumerate() {
+        out[i] -= c;
+    }
+    poly_trim(out)
+}
+
+fn poly_rem<F: PrimeField>(a: &[F], m: &[F]) -> Vec<F> {
+    let mut a = poly_trim(a.to_vec());
+    let m = poly_trim(m.to_vec());
+    let lead_inverse = m[m.len() - 1].inverse().expect("divisor is non-zero");
+    while a.len() >= m.len() {
+        let factor = a[a.len() - 1] * lead_inverse;
+        let shift = a.len() - m.len();
+        for (i, c) in m.iter().enumerate() {
+            a[shift + i] -= factor * c;
+        }
+        a = poly_trim(a);
+    }
+    a
+}
+
+fn poly_mul_mod<F: PrimeField>(a: &[F], b: &[F], m: &[F]) -> Vec<F> {
+    if a.is_empty() || b.is_empty() {
+        return Vec::new();
+    }
+    let mut out = vec![F::zero(); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            out[i + j] += *x * y;
+        }
+    }
+    poly_rem(&out, m)
+}
+
+fn poly_pow_mod<F: PrimeField>(base: &[F], exponent: &[u64], m: &[F]) -> Vec<F> {
+    let mut out = vec![F::one()];
+    for i in (0..exponent.len() * 64).rev() {
+        out = poly_mul_mod(&out, &out, m);
+        if exponent[i / 64] >> (i % 64) & 1 == 1 {
+            out = poly_mul_mod(&out, base, m);
+        }
+    }
+    out
+}
+
+fn poly_gcd<F: PrimeField>(mut a: Vec<F>, mut b: Vec<F>) -> Vec<F> {
+    a = poly_trim(a);
+    b = poly_trim(b);
+    while !b.is_empty() {
+        let r = poly_rem(&a, &b);
+        a = b;
+        b = r;
+    }
+    a
+}
+
+
+#[test]
+fn round_numbers_per_field() {
+    use ark_bls12_377::Fr as Fr377;
+    use ark_bls12_381::Fr as Fr381;
+    use ark_bn254::Fr as Fr254;
+
+    assert_eq!(sbox_exponent::<Fr381>(), 5);
+    assert_eq!(sbox_exponent::<Fr254>(), 5);
+    // p - 1 of the BLS12-377 scalar field is divisible by 3, 5 and 7
+    assert_eq!(sbox_exponent::<Fr377>(), 11);
+
+    // (t, alpha, security bits, R_F, R_P) as printed by find_FD_round_numbers of calc_round_numbers.py
+    // with the security margin, for each field
+    let bn254 = [(2, 5, 128, 8, 56), (3, 5, 128, 8, 56), (4, 5, 128, 8, 56), (5, 5, 128, 8, 56), (9, 5, 128, 8, 57)];
+    let bls12_381 = [(2, 5, 128, 8, 56), (3, 5, 128, 8, 56), (4, 5, 128, 8, 56), (5, 5, 128, 8, 56)];
+    for (t, alpha, security_bits, full, partial) in bn254 {
+        assert_eq!(round_numbers(log2_modulus::<Fr254>(), t, alpha, security_bits), (full, partial));
+    }
+    for (t, alpha, security_bits, full, partial) in bls12_381 {
+        assert_eq!(round_numbers(log2_modulus::<Fr381>(), t, alpha, security_bits), (full, partial));
+    }
+    assert_eq!(round_numbers(log2_modulus::<Fr377>(), 3, 11, 128), (8, 37));
+    assert_eq!(round_numbers(log2_modulus::<Fr254>(), 3, 5, 256), (8, 114));
+}
+
+#[test]
+fn generated_parameters_are_secure() {
+    use ark_crypto_primitives::sponge::{poseidon::PoseidonSponge, Absorb, CryptographicSponge};
+    use ark_ff::Zero;
+
+    fn check<F: PrimeField + Absorb>(alpha: u64, partial_rounds: usize) -> F {
+        let params = poseidon_parameters::<F>();
+        assert_eq!((params.alpha, params.full_rounds, params.partial_rounds), (alpha, 8, partial_rounds));
+        assert_eq!((params.rate, params.capacity), (RATE, 1));
+        assert_eq!(params.ark.len(), params.full_rounds + params.partial_rounds);
+        assert!(params.ark.iter().all(|round| round.len() == RATE + 1));
+        assert!(is_secure_mds(&params.mds));
+        let mut sponge = PoseidonSponge::new(&params);
+        sponge.absorb(&F::from(1u64));
+        sponge.squeeze_field_elements::<F>(1)[0]
+    }
+    let hash_381 = check::<ark_bls12_381::Fr>(5, 56);
+    check::<ark_bn254::Fr>(5, 56);
+    check::<ark_bls12_377::Fr>(11, 37);
+    // generation is deterministic
+    let fresh = generate_poseidon_parameters::<ark_bls12_381::Fr>(RATE, SECURITY_BITS);
+    assert_eq!(fresh.ark, poseidon_parameters::<ark_bls12_381::Fr>().ark);
+    assert!(!hash_381.is_zero());
+
+    // a zero entry, a singular 2x2 minor and a reducible characteristic polynomial all fail
+    let f = |rows: [[u64; 3]; 3]| -> Vec<Vec<ark_bn254::Fr>> {
+        rows.iter().map(|row| row.iter().map(|&x| ark_bn254::Fr::from(x)).collect()).collect()
+    };
+    assert!(!is_secure_mds(&f([[1, 2, 3], [4, 0, 6], [7, 8, 10]])));
+    assert!(!is_secure_mds(&f([[1, 2, 3], [2, 4, 5], [7, 8, 9]])));
+    assert!(!is_secure_mds(&f([[1, 1, 1], [1, 2, 3], [1, 3, 6]])));
+}
@@ -0,0 +1,431 @@
+use ark_bls12_381::Bls12_381;
+use ark_groth16::{prepare_verifying_key, PreparedVerifyingKey, ProvingKey, VerifyingKey};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Read, Validate, Write};
+use ark_std::{
+    fs::{self, File},
+    io,
+};
+
+use crate::circuit::{CircuitVariant, CommitmentKind, PositionWidth};
+use crate::curves::ZycleCurve;
+use crate::error::ZycleError;
+use crate::universal::{Backend, MarlinProvingKey, MarlinSrs, MarlinVerifyingKey};
+
+// every key file starts with these bytes, followed by the header and then the key itself
+pub const KEY_MAGIC: [u8; 8] = *b"ZYCLEKEY";
+pub const KEY_FORMAT_VERSION: u8 = 1;
+
+// the curve keys are generated on unless another is asked for, and the only one Marlin keys use
+pub const CURVE_ID: &str = Bls12_381::CURVE_ID;
+// how the circuits hash: the Poseidon parameters (see hashing::poseidon_params) and the matrix
+// absorbed as packed bits
+pub const HASH_ID: &str = "poseidon-128-packed";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyKind {
+    Proving,
+    Verifying,
+    PreparedVerifying,
+    MarlinProving,
+    MarlinVerifying,
+    // the universal SRS Marlin keys are derived from, its nodes are the most it covers
+    MarlinSrs,
+}
+
+impl KeyKind {
+    // which proof system keys of this kind belong to
+    pub fn backend(self) -> Backend {
+        match self {
+            KeyKind::Proving | KeyKind::Verifying | KeyKind::PreparedVerifying => Backend::Groth16,
+            KeyKind::MarlinProving | KeyKind::MarlinVerifying | KeyKind::MarlinSrs => Backend::Marlin,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            KeyKind::Proving => 0,
+            KeyKind::Verifying => 1,
+            KeyKind::PreparedVerifying => 2,
+            KeyKind::MarlinProving => 3,
+            KeyKind::MarlinVerifying => 4,
+            KeyKind::MarlinSrs => 5,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(KeyKind::Proving),
+            1 => Some(KeyKind::Verifying),
+            2 => Some(KeyKind::PreparedVerifying),
+            3 => Some(KeyKind::MarlinProving),
+            4 => Some(KeyKind::MarlinVerifying),
+            5 => Some(KeyKind::MarlinSrs),
+            _ => None,
+        }
+    }
+}
+
+// the key types that can be written with a header
+pub trait ZycleKey: CanonicalSerialize + CanonicalDeserialize {
+    const KIND: KeyKind;
+    const CURVE: &'static str;
+}
+
+impl<E: ZycleCurve> ZycleKey for ProvingKey<E> {
+    const KIND: KeyKind = KeyKind::Proving;
+    const CURVE: &'static str = E::CURVE_ID;
+}
+
+impl<E: ZycleCurve> ZycleKey for VerifyingKey<E> {
+    const KIND: KeyKind = KeyKind::Verifying;
+    const CURVE: &'static str = E::CURVE_ID;
+}
+
+impl<E: ZycleCurve> ZycleKey for PreparedVerifyingKey<E> {
+    const KIND: KeyKind = KeyKind::PreparedVerifying;
+    const CURVE: &'static str = E::CURVE_ID;
+}
+
+impl ZycleKey for MarlinProvingKey {
+    const KIND: KeyKind = KeyKind::MarlinProving;
+    const CURVE: &'static str = CURVE_ID;
+}
+
+impl ZycleKey for MarlinVerifyingKey {
+    const KIND: KeyKind = KeyKind::MarlinVerifying;
+    const CURVE: &'static str = CURVE_ID;
+}
+
+impl ZycleKey for MarlinSrs {
+    const KIND: KeyKind = KeyKind::MarlinSrs;
+    const CURVE: &'static str = CURVE_ID;
+}
+
+// everything about a circuit that changes its keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CircuitShape {
+    pub variant: CircuitVariant,
+    pub nodes: usize,
+    // most edges the sparse circuit takes, 0 for the dense one
+    pub edges: usize,
+    pub position_width: PositionWidth,
+    // what the public input commits to, anything but plain only exists for the dense circuit
+    pub commitment: CommitmentKind,
+}
+
+impl CircuitShape {
+    pub fn dense(nodes: usize, position_width: PositionWidth) -> Self {
+        Self {
+            variant: CircuitVariant::TopoSort,
+            nodes,
+            edges: 0,
+            position_width,
+            commitment: CommitmentKind::Plain,
+        }
+    }
+
+    pub fn reachability(nodes: usize, position_width: PositionWidth) -> Self {
+        Self {
+            variant: CircuitVariant::Reachability,
+            nodes,
+            edges: 0,
+            position_width,
+            commitment: CommitmentKind::Plain,
+        }
+    }
+
+    pub fn sparse(nodes: usize, edges: usize, position_width: PositionWidth) -> Self {
+        Self {
+            variant: CircuitVariant::Sparse,
+            nodes,
+            edges,
+            position_width,
+            commitment: CommitmentKind::Plain,
+        }
+    }
+}
+
+// records what a key was generated for, so a key for another circuit is refused instead of
+// producing proofs that never verify
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyHeader {
+    pub kind: KeyKind,
+    pub nodes: usize,
+    pub edges: usize,
+    pub curve: String,
+    pub hash: String,
+    pub variant: String,
+    pub position_width: PositionWidth,
+    pub commitment: CommitmentKind,
+    pub compressed: bool,
+}
+
+impl KeyHeader {
+    pub fn new(kind: KeyKind, shape: CircuitShape, compress: Compress) -> Self {
+        Self {
+            kind,
+            nodes: shape.nodes,
+            edges: shape.edges,
+            curve: CURVE_ID.to_string(),
+            hash: HASH_ID.to_string(),
+            variant: shape.variant.id().to_string(),
+            position_width: shape.position_width,
+            commitment: shape.commitment,
+            compressed: compress == Compress::Yes,
+        }
+    }
+
+    // errors unless the key was generated for the given circuit
+    pub fn expect_variant(&self, variant: CircuitVariant) -> Result<(), ZycleError> {
+        if self.variant != variant.id() {
+            return Err(key_mismatch(format!(
+                "key is for the {:?} circuit, expected {:?}",
+                self.variant,
+                variant.id()
+            )));
+        }
+        Ok(())
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<(), io::Error> {
+        writer.write_all(&KEY_MAGIC)?;
+        writer.write_all(&[KEY_FORMAT_VERSION, self.kind.to_byte(), self.compressed as u8])?;
+        writer.write_all(&(self.nodes as u64).to_le_bytes())?;
+        write_str(&mut writer, &self.curve)?;
+        write_str(&mut writer, &self.variant)?;
+        write_str(&mut writer, &self.position_width.id())?;
+        writer.write_all(&(self.edges as u64).to_le_bytes())?;
+        writer.write_all(&[self.commitment.to_byte()])?;
+        write_str(&mut writer, &self.hash)?;
+        Ok(())
+    }
+
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, ZycleError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != KEY_MAGIC {
+            return Err(key_mismatch("not a zycle key file".to_string()));
+        }
+        let mut flags = [0u8; 3];
+        reader.read_exact(&mut flags)?;
+        if flags[0] != KEY_FORMAT_VERSION {
+            return Err(key_mismatch(format!("unsupported key format version {}", flags[0])));
+        }
+        let kind = KeyKind::from_byte(flags[1])
+            .ok_or_else(|| key_mismatch(format!("unknown key kind {}", flags[1])))?;
+        let mut nodes = [0u8; 8];
+        reader.read_exact(&mut nodes)?;
+        let curve = read_str(&mut reader)?;
+        let variant = read_str(&mut reader)?;
+        let id = read_str(&mut reader)?;
+        let position_width =
+            PositionWidth::from_id(&id).ok_or_else(|| key_mismatch(format!("unknown position width {:?}", id)))?;
+        let mut edges = [0u8; 8];
+        reader.read_exact(&mut edges)?;
+        let mut commitment = [0u8; 1];
+        reader.read_exact(&mut commitment)?;
+        let commitment = CommitmentKind::from_byte(commitment[0])
+            .ok_or_else(|| key_mismatch(format!("unknown commitment kind {}", commitment[0])))?;
+        let hash = read_str(&mut reader)?;
+        Ok(Self {
+            kind,
+            nodes: read_count(nodes, "nodes")?,
+            edges: read_count(edges, "edges")?,
+            compressed: flags[2] != 0,
+            curve,
+            hash,
+            variant,
+            position_width,
+            commitment,
+        })
+    }
+}
+
+// writes the header followed by the key
+pub fn write_key<K: ZycleKey, W: Write>(
+    key: &K,
+    shape: CircuitShape,
+    compress: Compress,
+    mut writer: W,
+) -> Result<(), ZycleError> {
+    KeyHeader { curve: K::CURVE.to_string(), ..KeyHeader::new(K::KIND, shape, compress) }.write(&mut writer)?;
+    Ok(key.serialize_with_mode(&mut writer, compress)?)
+}
+
+// reads a key written by write_key, checking it is the expected kind of key for this curve
+// validate controls whether curve points are checked to be in the prime order subgroup
+pub fn read_key<K: ZycleKey, R: Read>(
+    mut reader: R,
+    validate: Validate,
+) -> Result<(KeyHeader, K), ZycleError> {
+    let header = KeyHeader::read(&mut reader)?;
+    if header.kind != K::KIND {
+        return Err(key_mismatch(format!(
+            "expected a {:?} key, found a {:?} key",
+            K::KIND,
+            header.kind
+        )));
+    }
+    if header.curve != K::CURVE {
+        return Err(key_mismatch(format!(
+            "key is for curve {}, expected {}",
+            header.curve, K::CURVE
+        )));
+    }
+    // the circuit the key was made for hashes differently, proofs with it would never verify
+    if header.hash != HASH_ID {
+        return Err(key_mismatch(format!(
+            "key hashes with {} parameters, expected {}, run setup again",
+            header.hash, HASH_ID
+        )));
+    }
+    let compress = if header.compressed { Compress::Yes } else { Compress::No };
+    let key = K::deserialize_with_mode(&mut reader, compress, validate)?;
+    Ok((header, key))
+}
+
+pub fn write_key_to_file<K: ZycleKey>(
+    key: &K,
+    shape: CircuitShape,
+    compress: Compress,
+    file_path: &str,
+) -> Result<(), ZycleError> {
+    let mut file = io::BufWriter::new(File::create(file_path)?);
+    write_key(key, shape, compress, &mut file)?;
+    Ok(file.flush()?)
+}
+
+pub fn read_key_from_file<K: ZycleKey>(
+    file_path: &str,
+    validate: Validate,
+) -> Result<(KeyHeader, K), ZycleError> {
+    read_key(io::BufReader::new(File::open(file_path)?), validate)
+}
+
+// reads the key of a Groth16 verifier for the given circuit, which may have been written either as
+// a verifying key or as a prepared verifying key
+pub fn read_verifying_key<E: ZycleCurve>(
+    bytes: &[u8],
+    variant: CircuitVariant,
+    validate: Validate,
+) -> Result<(KeyHeader, PreparedVerifyingKey<E>), ZycleError> {
+    let header = KeyHeader::read(bytes)?;
+    header.expect_variant(variant)?;
+    let pvk = if header.kind == KeyKind::PreparedVerifying {
+        read_key(bytes, validate)?.1
+    } else {
+        let (_, vk): (_, VerifyingKey<E>) = read_key(bytes, validate)?;
+        prepare_verifying_key(&vk)
+    };
+    Ok((header, pvk))
+}
+
+pub fn read_verifying_key_from_file<E: ZycleCurve>(
+    file_path: &str,
+    variant: CircuitVariant,
+    validate: Validate,
+) -> Result<(KeyHeader, PreparedVerifyingKey<E>), ZycleError> {
+    read_verifying_key(&fs::read(file_path)?, variant, validate)
+}
+
+// reads just the header, e.g. to find out which kind of key a file holds
+pub fn read_key_header_from_file(file_path: &str) -> Result<KeyHeader, ZycleError> {
+    KeyHeader::read(File::open(file_path)?)
+}
+
+fn write_str<W: Write>(writer: &mut W, s: &str) -> Result<(), io::Error> {
+    writer.write_all(&(s.len() as u16).to_le_bytes())?;
+    writer.write_all(s.as_bytes())
+}
+
+fn read_str<R: Read>(reader: &mut R) -> Result<String, io::Error> {
+    let mut len = [0u8; 2];
+    reader.read_exact(&mut len)?;
+    let mut bytes = vec![0u8; u16::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+// a node or edge count, which may not fit in a usize on 32-bit targets
+fn read_count(bytes: [u8; 8], what: &str) -> Result<usize, ZycleError> {
+    let count = u64::from_le_bytes(bytes);
+    usize::try_from(count)
+        .map_err(|_| key_mismatch(format!("key is for {} {}, more than this platform can hold", count, what)))
+}
+
+fn key_mismatch(msg: String) -> ZycleError {
+    ZycleError::KeyMismatch(msg)
+}
+
+#[test]
+fn key_roundtrip_and_mismatch() {
+    use crate::AcyclicityProver;
+    use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = AcyclicityProver::setup(3, &mut rng).unwrap();
+    let variant = CircuitVariant::TopoSort;
+    let width = PositionWidth::U8;
+    let shape = CircuitShape::dense(3, width);
+
+    for compress in [Compress::Yes, Compress::No] {
+        let mut bytes = Vec::new();
+        write_key(prover.proving_key(), shape, compress, &mut bytes).unwrap();
+        let (header, pk): (_, ProvingKey<Bls12_381>) =
+            read_key(bytes.as_slice(), Validate::Yes).unwrap();
+        assert_eq!(&pk, prover.proving_key());
+        assert_eq!(header.compressed, compress == Compress::Yes);
+        assert_eq!(header.position_width, width);
+        header.expect_variant(variant).unwrap();
+        assert!(header.expect_variant(CircuitVariant::Sparse).is_err());
+
+        let mut bytes = Vec::new();
+        write_key(verifier.prepared_verifying_key(), shape, compress, &mut bytes).unwrap();
+        let (_, pvk): (_, PreparedVerifyingKey<Bls12_381>) =
+            read_key(bytes.as_slice(), Validate::No).unwrap();
+        assert_eq!(&pvk, verifier.prepared_verifying_key());
+
+        // a verifying key cannot be loaded as a proving key
+        let mut bytes = Vec::new();
+        write_key(verifier.verifying_key(), shape, compress, &mut bytes).unwrap();
+        let err = read_key::<ProvingKey<Bls12_381>, _>(bytes.as_slice(), Validate::Yes).err().unwrap();
+        assert!(matches!(err, ZycleError::KeyMismatch(_)));
+        // and a truncated key is a serialization error rather than a panic
+        let err = read_key::<VerifyingKey<Bls12_381>, _>(&bytes[..bytes.len() - 1], Validate::Yes).err().unwrap();
+        assert!(matches!(err, ZycleError::Serialization(_)));
+    }
+
+    // other format versions are refused, as are keys for circuits hashing with other parameters
+    let mut bytes = Vec::new();
+    write_key(verifier.verifying_key(), shape, Compress::Yes, &mut bytes).unwrap();
+    for version in [0, KEY_FORMAT_VERSION + 1] {
+        let mut other = bytes.clone();
+        other[KEY_MAGIC.len()] = version;
+        assert!(matches!(KeyHeader::read(other.as_slice()), Err(ZycleError::KeyMismatch(_))));
+    }
+    let mut other_hash = Vec::new();
+    KeyHeader { hash: "poseidon-test".to_string(), ..KeyHeader::new(KeyKind::Verifying, shape, Compress::Yes) }
+        .write(&mut other_hash)
+        .unwrap();
+    verifier.verifying_key().serialize_compressed(&mut other_hash).unwrap();
+    let err = read_key::<VerifyingKey<Bls12_381>, _>(other_hash.as_slice(), Validate::Yes).err().unwrap();
+    assert!(matches!(err, ZycleError::KeyMismatch(_)));
+
+    let mut bytes = Vec::new();
+    let sparse = CircuitShape::sparse(3, 10, PositionWidth::Field(20));
+    KeyHeader::new(KeyKind::Proving, sparse, Compress::Yes).write(&mut bytes).unwrap();
+    let header = KeyHeader::read(bytes.as_slice()).unwrap();
+    assert_eq!(header.position_width, PositionWidth::Field(20));
+    assert_eq!(header.edges, 10);
+    assert!(header.expect_variant(variant).is_err());
+
+    let mut bytes = Vec::new();
+    let salted = CircuitShape { commitment: CommitmentKind::Salted, ..CircuitShape::dense(3, width) };
+    KeyHeader::new(KeyKind::Verifying, salted, Compress::Yes).write(&mut bytes).unwrap();
+    assert_eq!(KeyHeader::read(bytes.as_slice()).unwrap().commitment, CommitmentKind::Salted);
+    let mut bytes = Vec::new();
+    let row_tree = CircuitShape { commitment: CommitmentKind::RowTree, ..CircuitShape::dense(3, width) };
+    KeyHeader::new(KeyKind::Verifying, row_tree, Compress::Yes).write(&mut bytes).unwrap();
+    assert_eq!(KeyHeader::read(bytes.as_slice()).unwrap().commitment, CommitmentKind::RowTree);
+}
@@ -0,0 +1,23 @@
+pub mod buckets;
+pub mod bundle;
+pub mod circuit;
+pub mod curves;
+pub mod edge_list;
+pub mod error;
+pub mod graph_checks;
+pub mod graph_io;
+pub mod hashing;
+pub mod keys;
+pub mod merkle;
+pub mod prover;
+pub mod reachability;
+pub mod salt;
+pub mod solidity;
+pub mod sparse;
+pub mod universal;
+pub mod utils;
+pub mod witness;
+
+pub use circuit::MyGraphCircuitStruct;
+pub use error::ZycleError;
+pub use prover::{AcyclicityProver, AcyclicityProverOn, AcyclicityVerifier, AcyclicityVerifierOn};
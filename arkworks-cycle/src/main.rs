@@ -1,202 +1,1188 @@
-// #![allow(unused_variables)]
-// #![allow(unused_imports)]
-// #![allow(dead_code)]
+use std::fs::File;
+use std::process::ExitCode;
 
-use ark_crypto_primitives::snark::{CircuitSpecificSetupSNARK, SNARK};
+use ark_bls12_377::Bls12_377;
+use ark_bls12_381::{fr::Fr, Bls12_381};
+use ark_bn254::Bn254;
 use ark_ff::PrimeField;
-use ark_groth16::{prepare_verifying_key, Groth16, Proof};
-use ark_relations::{
-    r1cs::{ConstraintSystem, ConstraintSystemRef, ConstraintSynthesizer, SynthesisError},
-};
-use ark_std::{
-    rand::{RngCore, SeedableRng},
-    test_rng,
-};
-use ark_bls12_381::{
-    Bls12_381,
-    fr::Fr,
-    Config,
-};
-use ark_ec::bls12::Bls12;
-use ark_ec::pairing::Pairing;
-
-use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, Write};
-use ark_std::{
-    error::Error,
-    fs::File,
-    io,
-    Zero
-};
+use ark_groth16::Proof;
+use ark_relations::r1cs::SynthesisError;
+use ark_serialize::{Compress, Validate};
+use clap::{Parser, Subcommand};
+use rand::{rngs::StdRng, SeedableRng};
 
-// use crate::hashing::poseidon_parameters_for_test;
-use crate::hashing::{hasher_var, hasher};
-use ark_r1cs_std::{
-    fields::fp::FpVar,
-    eq::EqGadget,
-    alloc::AllocVar,
+use zycle::buckets::{
+    buckets_up_to, padded_commitment, proving_key_path, verifying_key_path, BucketedProverOn, BUCKET_SIZES,
 };
+use zycle::bundle::{field_to_string, GraphInput, ProofBundle};
+use zycle::circuit::{CircuitVariant, CommitmentKind, PositionWidth};
+use zycle::curves::{Curve, ZycleCurve};
+use zycle::edge_list::{is_edge_list_path, read_edge_list, read_edges, write_id_mapping, EdgeListGraph};
+use zycle::graph_io::{parse_toposort, read_adjacency_matrix};
+use zycle::keys::read_key_header_from_file;
+use zycle::merkle::{row_tree_commitment, RowOpening, RowTree};
+use zycle::solidity::{solidity_verifier, to_hex, verifier_calldata, CONTRACT_NAME};
+use zycle::sparse::{edge_commitment, SparseGraph, SparseProverOn, SparseVerifierOn};
+use zycle::witness::{reachable_from, subgraph_toposort_witness, toposort_witness_vec};
+use zycle::prover::{commitment, read_compressed_from_file, write_compressed_to_file};
+use zycle::reachability::{ReachabilityProverOn, ReachabilityVerifierOn};
+use zycle::salt::{generate_salt, open_commitment, read_salt_from_file, salted_commitment, write_salt_to_file};
+use zycle::universal::{Backend, MarlinProof, MarlinProver, MarlinVerifier, UniversalSrs};
+use zycle::{AcyclicityProverOn, AcyclicityVerifierOn, ZycleError};
+
+// runs a function generic over the pairing on the curve picked at runtime
+macro_rules! on_curve {
+    ($curve:expr, $f:ident($($arg:expr),* $(,)?)) => {
+        match $curve {
+            Curve::Bls12_381 => $f::<Bls12_381>($($arg),*),
+            Curve::Bn254 => $f::<Bn254>($($arg),*),
+            Curve::Bls12_377 => $f::<Bls12_377>($($arg),*),
+        }
+    };
+}
 
-mod utils;
-mod graph_checks;
-mod hashing;
+// exit codes, so callers can tell a rejected proof apart from a problem with what they passed in
+const EXIT_INVALID_PROOF: u8 = 1;
+const EXIT_BAD_INPUT: u8 = 2;
+const EXIT_INTERNAL_ERROR: u8 = 3;
 
-use crate::graph_checks::{
-    check_topo_sort,
-    // check_subgraph_topo_sort, 
-    // check_multi_subgraph_topo_sort, 
-};
-use crate::utils::{
-    Boolean2DArray,
-    Uint8Array
-    // Boolean3DArray, 
-    // BooleanArray, 
-};
+#[derive(Parser)]
+#[command(name = "zycle", version, about = "zk-SNARKs proving that a graph has no cycles")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Generate the proving and verifying keys for graphs with a given number of nodes
+    Setup {
+        /// Number of nodes in the graphs the keys will be used for
+        #[arg(long)]
+        nodes: usize,
+        #[arg(long, default_value = "pk.bin")]
+        pk: String,
+        #[arg(long, default_value = "vk.bin")]
+        vk: String,
+        /// Also write the prepared verifying key, which is faster to verify with
+        #[arg(long)]
+        pvk: Option<String>,
+        /// Write keys without point compression (larger files, faster to load)
+        #[arg(long)]
+        uncompressed: bool,
+        /// Write a key pair for every bucket size (16, 32, 64, ..., 4096) up to the one fitting
+        /// --nodes into this directory, as pk_<size>.bin and vk_<size>.bin
+        #[arg(long, conflicts_with_all = ["pk", "vk", "pvk"])]
+        buckets: Option<String>,
+        /// How topological positions are held in the circuit: u8, u16, u32 or field<bits>
+        /// (e.g. field12, cheapest to check), defaults to the smallest unsigned width that fits
+        #[arg(long, value_parser = parse_position_width)]
+        position_width: Option<PositionWidth>,
+        /// Use the sparse edge-list circuit for graphs with at most this many edges, which costs
+        /// constraints per node and edge instead of per pair of nodes
+        #[arg(long, conflicts_with_all = ["buckets", "position_width"])]
+        edges: Option<usize>,
+        /// Generate keys for proofs that no cycle is reachable from a start node given to `prove --start`,
+        /// instead of proofs about the whole graph
+        #[arg(long, conflicts_with_all = ["buckets", "edges"])]
+        reachability: bool,
+        /// Generate keys for proofs against a salted commitment made with `zycle commit`, which hides
+        /// the graph from anyone testing guesses against the commitment
+        #[arg(long, conflicts_with_all = ["buckets", "edges", "reachability"])]
+        salted: bool,
+        /// Generate keys for proofs against the root of a salted Merkle tree over the matrix rows made
+        /// with `zycle commit --row-tree`, so single rows can be revealed later with `zycle reveal`
+        #[arg(long, conflicts_with_all = ["buckets", "edges", "reachability", "salted"])]
+        row_tree: bool,
+        /// Proof system to make keys for: groth16, with a trusted setup for these keys alone, or marlin,
+        /// deriving the keys from the universal SRS written by `zycle srs`
+        #[arg(long, default_value = "groth16", value_parser = parse_backend)]
+        backend: Backend,
+        /// Universal SRS the marlin keys are derived from
+        #[arg(long, default_value = "srs.bin")]
+        srs: String,
+        /// Curve to generate the keys on: bls12-381, bn254 (cheap to verify on Ethereum) or bls12-377 (whose
+        /// proofs can be verified inside a BW6-761 circuit), marlin keys are only made on bls12-381
+        #[arg(long, default_value = "bls12-381", value_parser = parse_curve)]
+        curve: Curve,
+    },
+    /// Generate the universal SRS Marlin keys are derived from, one SRS covers every graph with up to
+    /// a given number of nodes
+    Srs {
+        /// Most nodes the graphs of keys derived from the SRS can have
+        #[arg(long)]
+        max_nodes: usize,
+        #[arg(long, default_value = "srs.bin")]
+        srs: String,
+        /// Write the SRS without point compression (larger file, faster to load)
+        #[arg(long)]
+        uncompressed: bool,
+    },
+    /// Prove that the graph in a file has no cycles
+    Prove {
+        #[arg(long, default_value = "pk.bin")]
+        pk: String,
+        /// Directory written by `setup --buckets`, the graph is padded with isolated nodes up to the
+        /// smallest bucket it fits in so the proof only reveals the bucket size
+        #[arg(long, conflicts_with = "pk")]
+        buckets: Option<String>,
+        /// Adjacency matrix file, a .csv/.tsv edge list of (sender, receiver) rows or a .json graph
+        #[arg(long)]
+        graph: String,
+        /// Position of every node in a topological sort of the graph, using each of 0..n once, e.g. 0,1,2,3
+        /// (worked out from the graph when not given)
+        #[arg(long)]
+        order: Option<String>,
+        /// Account id (or node index) the proof starts from, required by keys made with `setup --reachability`
+        #[arg(long, conflicts_with = "buckets")]
+        start: Option<String>,
+        /// Salt file written by `zycle commit`, required by keys made with `setup --salted` or `setup --row-tree`
+        #[arg(long, conflicts_with = "buckets")]
+        salt: Option<String>,
+        /// A .json path writes a proof bundle with the commitment and metadata,
+        /// any other path writes just the compressed proof
+        #[arg(long, default_value = "proof.json")]
+        proof: String,
+        /// Where to write the account id -> node index mapping of the graph
+        /// (defaults to the proof path with .ids.csv appended, unless the proof is a .json bundle,
+        /// which holds the ids itself)
+        #[arg(long)]
+        ids: Option<String>,
+        /// Skip the subgroup checks when loading the proving key
+        #[arg(long)]
+        no_validate: bool,
+    },
+    /// Check a proof against a verifying key and the commitment of the graph
+    Verify {
+        /// Verifying key or prepared verifying key
+        #[arg(long, default_value = "vk.bin")]
+        vk: String,
+        /// Directory written by `setup --buckets`, the key is picked by the size in the proof bundle
+        #[arg(long, conflicts_with = "vk")]
+        buckets: Option<String>,
+        /// Proof bundle (.json) or compressed proof
+        #[arg(long, default_value = "proof.json")]
+        proof: String,
+        /// Commitment printed by `zycle hash`, required unless the proof is a .json bundle
+        #[arg(long)]
+        commitment: Option<String>,
+        /// Start node of a reachability proof, as an account id in the bundle or a node index
+        /// (required unless the proof is a .json bundle)
+        #[arg(long)]
+        start: Option<String>,
+        /// Skip the subgroup checks when loading the verifying key
+        #[arg(long)]
+        no_validate: bool,
+    },
+    /// Print the Poseidon commitment of the graph in a file
+    Hash {
+        /// Adjacency matrix file, a .csv/.tsv edge list of (sender, receiver) rows or a .json graph
+        #[arg(long)]
+        graph: String,
+        /// Commit to the graph padded to its bucket, as checked by proofs made with `prove --buckets`
+        #[arg(long)]
+        padded: bool,
+        /// Commit to the edge list padded to this many edges, as checked by sparse proofs
+        #[arg(long, conflicts_with = "padded")]
+        edges: Option<usize>,
+        /// Curve of the keys the commitment is for, as passed to `setup --curve`
+        #[arg(long, default_value = "bls12-381", value_parser = parse_curve)]
+        curve: Curve,
+    },
+    /// Draw a secret salt and print the salted commitment of the graph in a file, for keys made
+    /// with `setup --salted`
+    Commit {
+        /// Adjacency matrix file, a .csv/.tsv edge list of (sender, receiver) rows or a .json graph
+        #[arg(long)]
+        graph: String,
+        /// Where to write the salt, keep it secret until the commitment is opened
+        #[arg(long, default_value = "salt.txt")]
+        salt: String,
+        /// Print the root of the salted Merkle tree over the matrix rows instead, for keys made with
+        /// `setup --row-tree`
+        #[arg(long)]
+        row_tree: bool,
+        /// Curve of the keys the commitment is for, as passed to `setup --curve`
+        #[arg(long, default_value = "bls12-381", value_parser = parse_curve)]
+        curve: Curve,
+    },
+    /// Check that a graph and salt open a salted commitment, exits with 1 if they do not
+    Open {
+        /// Adjacency matrix file, a .csv/.tsv edge list of (sender, receiver) rows or a .json graph
+        #[arg(long)]
+        graph: String,
+        /// Salt file written by `zycle commit`
+        #[arg(long, default_value = "salt.txt")]
+        salt: String,
+        /// Salted commitment printed by `zycle commit`
+        #[arg(long)]
+        commitment: String,
+        /// The commitment is a row tree root printed by `zycle commit --row-tree`
+        #[arg(long)]
+        row_tree: bool,
+        /// Curve of the keys the commitment is for, as passed to `setup --curve`
+        #[arg(long, default_value = "bls12-381", value_parser = parse_curve)]
+        curve: Curve,
+    },
+    /// Write an opening revealing one row of the graph under its row tree root
+    Reveal {
+        /// Adjacency matrix file, a .csv/.tsv edge list of (sender, receiver) rows or a .json graph
+        #[arg(long)]
+        graph: String,
+        /// Account id (or node index) whose outgoing edges are revealed
+        #[arg(long)]
+        row: String,
+        /// Salt file written by `zycle commit --row-tree`, the opening holds only the salt of the row
+        #[arg(long, default_value = "salt.txt")]
+        salt: String,
+        #[arg(long, default_value = "opening.json")]
+        opening: String,
+        /// Curve of the keys the commitment is for, as passed to `setup --curve`
+        #[arg(long, default_value = "bls12-381", value_parser = parse_curve)]
+        curve: Curve,
+    },
+    /// Check an opening written by `zycle reveal` against a row tree root and print the row,
+    /// exits with 1 if the row is not in the tree
+    CheckRow {
+        #[arg(long, default_value = "opening.json")]
+        opening: String,
+        /// Root printed by `zycle commit --row-tree` or held by a row tree proof
+        #[arg(long)]
+        commitment: String,
+        /// Curve of the keys the commitment is for, as passed to `setup --curve`
+        #[arg(long, default_value = "bls12-381", value_parser = parse_curve)]
+        curve: Curve,
+    },
+    /// Write a Solidity contract verifying proofs against a verifying key made with `setup --curve bn254`
+    ExportSolidity {
+        #[arg(long, default_value = "vk.bin")]
+        vk: String,
+        #[arg(long, default_value = "ZycleVerifier.sol")]
+        contract: String,
+        /// Skip the subgroup checks when loading the verifying key
+        #[arg(long)]
+        no_validate: bool,
+    },
+    /// Print the hex calldata calling the contract written by `export-solidity` on a BN254 proof
+    Calldata {
+        /// Proof bundle (.json) or compressed proof
+        #[arg(long, default_value = "proof.json")]
+        proof: String,
+        /// Commitment printed by `zycle hash --curve bn254`, required unless the proof is a .json bundle
+        #[arg(long)]
+        commitment: Option<String>,
+    },
+}
 
+// a Groth16 verifying key for any circuit, picked by the variant in its header
+enum Verifier<E: ZycleCurve> {
+    TopoSort(AcyclicityVerifierOn<E>),
+    Sparse(SparseVerifierOn<E>),
+    Reachability(ReachabilityVerifierOn<E>),
+}
 
+// a proof made with either backend, with its public inputs
+enum AnyProof<E: ZycleCurve> {
+    Groth16(Box<Proof<E>>, Vec<E::ScalarField>),
+    Marlin(MarlinProof, Vec<Fr>),
+}
 
+impl<E: ZycleCurve> Verifier<E> {
+    fn load(vk_path: &str, validate: Validate) -> Result<Self, CliError> {
+        Ok(match key_variant(vk_path)? {
+            CircuitVariant::TopoSort => {
+                Verifier::TopoSort(AcyclicityVerifierOn::load(vk_path, validate).map_err(bad_input)?)
+            }
+            CircuitVariant::Sparse => Verifier::Sparse(SparseVerifierOn::load(vk_path, validate).map_err(bad_input)?),
+            CircuitVariant::Reachability => {
+                Verifier::Reachability(ReachabilityVerifierOn::load(vk_path, validate).map_err(bad_input)?)
+            }
+        })
+    }
 
+    fn check_matches(&self, bundle: &ProofBundle) -> Result<(), CliError> {
+        match self {
+            Verifier::TopoSort(verifier) => bundle.check_matches(verifier),
+            Verifier::Sparse(verifier) => {
+                bundle.check_matches_circuit::<E>(CircuitVariant::Sparse, CommitmentKind::Plain, verifier.nodes())
+            }
+            Verifier::Reachability(verifier) => {
+                bundle.check_matches_circuit::<E>(CircuitVariant::Reachability, CommitmentKind::Plain, verifier.nodes())
+            }
+        }
+        .map_err(bad_input)
+    }
 
-fn main() {
-    //function called by cargo run
-    let adj_matrix = [
-        [false, true, true, false],   //               [0]
-        [false, false, true, false],  //               / \
-        [false, false, false, true],  //             [1]->[2] -> 3
-        [false, false, false, false], //
-    ];
-    let topological_sort = [0, 1, 2, 3];
+    fn verify(&self, proof: &Proof<E>, public_inputs: &[E::ScalarField]) -> Result<bool, ZycleError> {
+        match self {
+            Verifier::TopoSort(verifier) => verifier.verify(proof, public_inputs),
+            Verifier::Sparse(verifier) => verifier.verify(proof, public_inputs),
+            Verifier::Reachability(verifier) => verifier.verify(proof, public_inputs),
+        }
+    }
+}
 
-    match test_prove_and_verify::<Bls12_381, 4>(adj_matrix, topological_sort) {
-        Ok(()) => println!("finished successfully!"),
-        Err(e) => eprintln!("Back in Main. Error: {:?}", e),
+impl<E: ZycleCurve> AnyProof<E> {
+    // the commitment the proof is checked against, its first public input
+    fn commitment(&self) -> String {
+        match self {
+            AnyProof::Groth16(_, public_inputs) => field_to_string(&public_inputs[0]),
+            AnyProof::Marlin(_, public_inputs) => field_to_string(&public_inputs[0]),
+        }
     }
 }
 
-// struct for generating the circuit trace
-//the fields are the inputs to the circuit
-struct MyGraphCircuitStruct<const N: usize, ConstraintF: PrimeField> {
-    adj_matrix: [[bool; N]; N],
-    toposort: [u8; N],
-    adj_hash: ConstraintF,
+// what prove is told on top of the graph
+struct Witness<'a> {
+    order: Option<&'a str>,
+    start: Option<&'a str>,
+    salt: Option<&'a str>,
+}
+
+// public inputs verify is told, instead of taking them from the bundle
+struct Claim<'a> {
+    commitment: Option<&'a str>,
+    start: Option<&'a str>,
 }
 
-// implementing cloning for MyGraphCircuitStruct
-impl<const N: usize, ConstraintF: PrimeField> Clone for MyGraphCircuitStruct<N, ConstraintF> {
-    fn clone(&self) -> Self {
-        Self {
-            adj_matrix: self.adj_matrix.clone(),
-            toposort: self.toposort.clone(),
-            adj_hash: self.adj_hash.clone(),
+// where setup writes the keys
+struct KeyFiles<'a> {
+    pk: &'a str,
+    vk: &'a str,
+    pvk: Option<&'a str>,
+}
+
+// where prove gets its proving key from
+enum ProvingKeys<'a> {
+    Single(&'a str),
+    Buckets(&'a str),
+}
+
+// which circuit setup makes keys for, apart from the sparse and bucketed ones
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SetupCircuit {
+    TopoSort,
+    Salted,
+    RowTree,
+    Reachability,
+}
+
+enum CliError {
+    InvalidProof,
+    // a check other than proof verification failed, exits like an invalid proof
+    Rejected(String),
+    BadInput(String),
+    Internal(String),
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Setup {
+            nodes,
+            pk,
+            vk,
+            pvk,
+            uncompressed,
+            buckets,
+            position_width,
+            edges,
+            reachability,
+            salted,
+            row_tree,
+            backend,
+            srs,
+            curve,
+        } => {
+            let compress = if uncompressed { Compress::No } else { Compress::Yes };
+            let circuit = match (reachability, salted, row_tree) {
+                (true, _, _) => SetupCircuit::Reachability,
+                (false, true, _) => SetupCircuit::Salted,
+                (false, false, true) => SetupCircuit::RowTree,
+                (false, false, false) => SetupCircuit::TopoSort,
+            };
+            match (backend, buckets, edges) {
+                (Backend::Marlin, _, _) if curve != Curve::Bls12_381 => Err(CliError::BadInput(format!(
+                    "marlin keys are only made on bls12-381, not {}",
+                    curve.id()
+                ))),
+                (Backend::Marlin, None, None) if circuit != SetupCircuit::Reachability && pvk.is_none() => {
+                    setup_marlin(nodes, circuit, &srs, &pk, &vk, position_width, compress)
+                }
+                (Backend::Marlin, _, _) => Err(CliError::BadInput(
+                    "marlin keys are only made for the dense circuit, without --buckets, --edges, --reachability \
+                     or --pvk"
+                        .to_string(),
+                )),
+                (Backend::Groth16, Some(dir), _) => {
+                    on_curve!(curve, setup_buckets(nodes, &dir, position_width, compress))
+                }
+                (Backend::Groth16, None, Some(edges)) => {
+                    on_curve!(curve, setup_sparse(nodes, edges, &pk, &vk, pvk.as_deref(), compress))
+                }
+                (Backend::Groth16, None, None) => {
+                    let files = KeyFiles { pk: &pk, vk: &vk, pvk: pvk.as_deref() };
+                    setup(nodes, circuit, curve, &files, position_width, compress)
+                }
+            }
+        }
+        Command::Srs { max_nodes, srs, uncompressed } => {
+            srs_setup(max_nodes, &srs, if uncompressed { Compress::No } else { Compress::Yes })
+        }
+        Command::Prove { pk, buckets, graph, order, start, salt, proof, ids, no_validate } => {
+            // a bundle made with bucketed keys leaves out the ids, as their number would give away
+            // the size of the graph
+            let ids = ids.or_else(|| {
+                (buckets.is_some() || !is_json_path(&proof)).then(|| format!("{}.ids.csv", proof))
+            });
+            let keys = match &buckets {
+                Some(dir) => ProvingKeys::Buckets(dir),
+                None => ProvingKeys::Single(&pk),
+            };
+            let witness = Witness { order: order.as_deref(), start: start.as_deref(), salt: salt.as_deref() };
+            prove(keys, &graph, witness, &proof, ids.as_deref(), validate_mode(no_validate))
+        }
+        Command::Verify { vk, buckets, proof, commitment, start, no_validate } => {
+            let claim = Claim { commitment: commitment.as_deref(), start: start.as_deref() };
+            verify(&vk, buckets.as_deref(), &proof, claim, validate_mode(no_validate))
+        }
+        Command::Hash { graph, padded, edges, curve } => hash(&graph, padded, edges, curve),
+        Command::Commit { graph, salt, row_tree, curve } => on_curve!(curve, commit(&graph, &salt, row_tree)),
+        Command::Open { graph, salt, commitment, row_tree, curve } => {
+            on_curve!(curve, open(&graph, &salt, &commitment, row_tree))
+        }
+        Command::Reveal { graph, row, salt, opening, curve } => on_curve!(curve, reveal(&graph, &row, &salt, &opening)),
+        Command::CheckRow { opening, commitment, curve } => on_curve!(curve, check_row(&opening, &commitment)),
+        Command::ExportSolidity { vk, contract, no_validate } => {
+            export_solidity(&vk, &contract, validate_mode(no_validate))
+        }
+        Command::Calldata { proof, commitment } => calldata(&proof, commitment.as_deref()),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(CliError::InvalidProof) => {
+            eprintln!("proof is invalid");
+            ExitCode::from(EXIT_INVALID_PROOF)
+        }
+        Err(CliError::Rejected(msg)) => {
+            eprintln!("{}", msg);
+            ExitCode::from(EXIT_INVALID_PROOF)
+        }
+        Err(CliError::BadInput(msg)) => {
+            eprintln!("error: {}", msg);
+            ExitCode::from(EXIT_BAD_INPUT)
+        }
+        Err(CliError::Internal(msg)) => {
+            eprintln!("internal error: {}", msg);
+            ExitCode::from(EXIT_INTERNAL_ERROR)
         }
     }
 }
 
-// Takes the struct that holds inputs and generates the entire circuit
-impl<ConstraintF: PrimeField, const N: usize> ConstraintSynthesizer<ConstraintF>
-    for MyGraphCircuitStruct<N, ConstraintF>
-{
-    fn generate_constraints(
-        self,
-        cs: ConstraintSystemRef<ConstraintF>,
-    ) -> Result<(), SynthesisError> {
-        // create input vars
-        let adj_matrix_var =
-            Boolean2DArray::new_witness(cs.clone(), || Ok(self.adj_matrix)).unwrap();
-        let topo_var = Uint8Array::new_witness(cs.clone(), || Ok(self.toposort)).unwrap();
-        let hash_claim_var: FpVar<ConstraintF> = FpVar::new_input(cs.clone(), || Ok(self.adj_hash))?;
+fn setup(
+    nodes: usize,
+    circuit: SetupCircuit,
+    curve: Curve,
+    files: &KeyFiles,
+    position_width: Option<PositionWidth>,
+    compress: Compress,
+) -> Result<(), CliError> {
+    if nodes == 0 {
+        return Err(CliError::BadInput("graphs must have at least 1 node".to_string()));
+    }
+    let position_width = check_position_width(nodes, position_width)?;
+    let mut rng = StdRng::from_entropy();
+    if circuit == SetupCircuit::Reachability {
+        on_curve!(curve, setup_reachability(nodes, position_width, files, compress, &mut rng))?;
+    } else {
+        let commitment = match circuit {
+            SetupCircuit::Salted => CommitmentKind::Salted,
+            SetupCircuit::RowTree => CommitmentKind::RowTree,
+            _ => CommitmentKind::Plain,
+        };
+        on_curve!(curve, setup_dense(nodes, commitment, position_width, files, compress, &mut rng))?;
+    }
+    let name = match circuit {
+        SetupCircuit::TopoSort => "toposort",
+        SetupCircuit::Salted => "salted toposort",
+        SetupCircuit::RowTree => "row tree toposort",
+        SetupCircuit::Reachability => "reachability",
+    };
+    println!("wrote {} keys for {} node graphs to {} and {}", name, nodes, files.pk, files.vk);
+    Ok(())
+}
 
-        // check the claimed hash is correct
-        let hash_real: &FpVar<ConstraintF> = &hasher_var::<N, ConstraintF>(cs.clone(), &adj_matrix_var).unwrap()[0];
-        hash_real.enforce_equal(&hash_claim_var)?;
+fn setup_dense<E: ZycleCurve>(
+    nodes: usize,
+    commitment: CommitmentKind,
+    position_width: PositionWidth,
+    files: &KeyFiles,
+    compress: Compress,
+    rng: &mut StdRng,
+) -> Result<(), CliError> {
+    let (prover, verifier) =
+        AcyclicityProverOn::<E>::setup_with_commitment(nodes, position_width, commitment, rng).map_err(internal)?;
+    prover.write_proving_key(files.pk, compress).map_err(internal)?;
+    verifier.write_verifying_key(files.vk, compress).map_err(internal)?;
+    if let Some(pvk_path) = files.pvk {
+        verifier.write_prepared_verifying_key(pvk_path, compress).map_err(internal)?;
+    }
+    Ok(())
+}
 
-        // check the graph properties
-        check_topo_sort(&adj_matrix_var, &topo_var).unwrap();
+fn setup_reachability<E: ZycleCurve>(
+    nodes: usize,
+    position_width: PositionWidth,
+    files: &KeyFiles,
+    compress: Compress,
+    rng: &mut StdRng,
+) -> Result<(), CliError> {
+    let (prover, verifier) =
+        ReachabilityProverOn::<E>::setup_with_width(nodes, position_width, rng).map_err(internal)?;
+    prover.write_proving_key(files.pk, compress).map_err(internal)?;
+    verifier.write_verifying_key(files.vk, compress).map_err(internal)?;
+    if let Some(pvk_path) = files.pvk {
+        verifier.write_prepared_verifying_key(pvk_path, compress).map_err(internal)?;
+    }
+    Ok(())
+}
 
-        // finish
-        println!("Number of constraints: {}", cs.num_constraints());
-        Ok(())
+fn setup_marlin(
+    nodes: usize,
+    circuit: SetupCircuit,
+    srs_path: &str,
+    pk_path: &str,
+    vk_path: &str,
+    position_width: Option<PositionWidth>,
+    compress: Compress,
+) -> Result<(), CliError> {
+    if nodes == 0 {
+        return Err(CliError::BadInput("graphs must have at least 1 node".to_string()));
     }
+    let position_width = check_position_width(nodes, position_width)?;
+    let commitment = match circuit {
+        SetupCircuit::Salted => CommitmentKind::Salted,
+        SetupCircuit::RowTree => CommitmentKind::RowTree,
+        _ => CommitmentKind::Plain,
+    };
+    let srs = UniversalSrs::load(srs_path, Validate::Yes).map_err(bad_input)?;
+    let (prover, verifier) = MarlinProver::index(&srs, nodes, position_width, commitment).map_err(prove_error)?;
+    prover.write_proving_key(pk_path, compress).map_err(internal)?;
+    verifier.write_verifying_key(vk_path, compress).map_err(internal)?;
+    println!("wrote marlin keys for {} node graphs to {} and {}", nodes, pk_path, vk_path);
+    Ok(())
 }
 
+fn srs_setup(max_nodes: usize, srs_path: &str, compress: Compress) -> Result<(), CliError> {
+    if max_nodes == 0 {
+        return Err(CliError::BadInput("graphs must have at least 1 node".to_string()));
+    }
+    let srs = UniversalSrs::setup(max_nodes, &mut StdRng::from_entropy()).map_err(prove_error)?;
+    srs.write(srs_path, compress).map_err(internal)?;
+    println!("wrote an SRS for graphs with up to {} nodes to {}", max_nodes, srs_path);
+    Ok(())
+}
 
+fn setup_sparse<E: ZycleCurve>(
+    nodes: usize,
+    edges: usize,
+    pk_path: &str,
+    vk_path: &str,
+    pvk_path: Option<&str>,
+    compress: Compress,
+) -> Result<(), CliError> {
+    if nodes == 0 || edges == 0 {
+        return Err(CliError::BadInput("graphs must have at least 1 node and 1 edge".to_string()));
+    }
+    let mut rng = StdRng::from_entropy();
+    let (prover, verifier) = SparseProverOn::<E>::setup(nodes, edges, &mut rng).map_err(internal)?;
+    prover.write_proving_key(pk_path, compress).map_err(internal)?;
+    verifier.write_verifying_key(vk_path, compress).map_err(internal)?;
+    if let Some(pvk_path) = pvk_path {
+        verifier.write_prepared_verifying_key(pvk_path, compress).map_err(internal)?;
+    }
+    println!(
+        "wrote keys for graphs with up to {} nodes and {} edges to {} and {}",
+        nodes, edges, pk_path, vk_path
+    );
+    Ok(())
+}
 
-// takes the adj matrix and toposort defined, builds the circuit, gens the proof, & verifies it
-// also will write the proof and read the proof for I/O  demonstration
-// hardcoded for bls12_381 because our hash function is as well
-fn test_prove_and_verify<E: Pairing, const N: usize>(
-    adj_matrix: [[bool; N]; N], 
-    topological_sort: [u8; N],
-) -> Result<(), Box<dyn Error>> {
+fn setup_buckets<E: ZycleCurve>(
+    nodes: usize,
+    dir: &str,
+    position_width: Option<PositionWidth>,
+    compress: Compress,
+) -> Result<(), CliError> {
+    let sizes = buckets_up_to(nodes).ok_or_else(|| {
+        let largest = BUCKET_SIZES[BUCKET_SIZES.len() - 1];
+        CliError::BadInput(format!("bucketed graphs can have at most {} nodes, got {}", largest, nodes))
+    })?;
+    // the width has to fit the largest bucket, the smaller ones then fit too
+    if let Some(position_width) = position_width {
+        check_position_width(sizes[sizes.len() - 1], Some(position_width))?;
+    }
+    std::fs::create_dir_all(dir).map_err(internal)?;
+    let mut rng = StdRng::from_entropy();
+    let (prover, verifier) =
+        BucketedProverOn::<E>::setup_with_width(&sizes, position_width, &mut rng).map_err(internal)?;
+    prover.write_dir(dir, compress).map_err(internal)?;
+    verifier.write_dir(dir, compress).map_err(internal)?;
+    println!("wrote keys for {:?} node graphs to {}", sizes, dir);
+    Ok(())
+}
 
-    let cs = ConstraintSystem::<Fr>::new_ref();
-    //defining the inputs
+fn prove(
+    keys: ProvingKeys,
+    graph_path: &str,
+    witness: Witness,
+    proof_path: &str,
+    ids_path: Option<&str>,
+    validate: Validate,
+) -> Result<(), CliError> {
+    let curve = match keys {
+        ProvingKeys::Single(pk_path) => key_curve(pk_path)?,
+        ProvingKeys::Buckets(dir) => buckets_curve(dir)?,
+    };
+    on_curve!(curve, prove_on(keys, graph_path, witness, proof_path, ids_path, validate))
+}
 
-    // Convert the adjacency matrix to Boolean2DArray
-    let adj_matrix_boolean_2_d_array =
-        Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
-    let adj_hash_result = hasher(&adj_matrix_boolean_2_d_array);
+fn prove_on<E: ZycleCurve>(
+    keys: ProvingKeys,
+    graph_path: &str,
+    witness: Witness,
+    proof_path: &str,
+    ids_path: Option<&str>,
+    validate: Validate,
+) -> Result<(), CliError> {
+    let variant = match keys {
+        ProvingKeys::Single(pk_path) => key_variant(pk_path)?,
+        ProvingKeys::Buckets(_) => CircuitVariant::TopoSort,
+    };
+    if let (ProvingKeys::Single(pk_path), CircuitVariant::Sparse) = (&keys, variant) {
+        return prove_sparse::<E>(pk_path, graph_path, witness, proof_path, ids_path, validate);
+    }
+    let (adj_matrix, edge_list) = load_graph(graph_path)?;
+    let start = match (variant, witness.start) {
+        (CircuitVariant::Reachability, Some(start)) => {
+            Some(parse_node(start, edge_list.as_ref().map(|graph| graph.ids.as_slice()))?)
+        }
+        (CircuitVariant::Reachability, None) => {
+            return Err(CliError::BadInput("reachability keys need a --start node".to_string()))
+        }
+        (_, Some(_)) => return Err(CliError::BadInput("--start needs keys made with setup --reachability".to_string())),
+        (_, None) => None,
+    };
+    if witness.salt.is_some() && variant != CircuitVariant::TopoSort {
+        return Err(CliError::BadInput("--salt needs keys made with setup --salted or --row-tree".to_string()));
+    }
+    // only the nodes reachable from the start node have to be sorted, the rest may hold cycles
+    let topological_sort = match (witness.order, start) {
+        (Some(order), _) => parse_toposort(order, adj_matrix.len()).map_err(bad_input)?,
+        (None, Some(start)) if start < adj_matrix.len() => {
+            let subgraph_nodes = reachable_from(&adj_matrix, start).map_err(bad_input)?;
+            subgraph_toposort_witness(&adj_matrix, &subgraph_nodes).map_err(bad_input)?
+        }
+        (None, _) => toposort_witness_vec(&adj_matrix).map_err(bad_input)?,
+    };
+    let mut rng = StdRng::from_entropy();
+    let (proof, nodes, commitment) = match keys {
+        ProvingKeys::Single(pk_path) if variant == CircuitVariant::Reachability => {
+            let prover = ReachabilityProverOn::<E>::load(pk_path, validate).map_err(bad_input)?;
+            check_graph_size(prover.nodes(), adj_matrix.len())?;
+            let start = start.unwrap_or(0);
+            if start >= adj_matrix.len() {
+                return Err(CliError::BadInput(format!("start node {} is not in the graph", start)));
+            }
+            let subgraph_nodes = reachable_from(&adj_matrix, start).map_err(bad_input)?;
+            let (proof, public_inputs) = prover
+                .prove_with_witness(&adj_matrix, start, &subgraph_nodes, &topological_sort, &mut rng)
+                .map_err(prove_error)?;
+            (AnyProof::Groth16(Box::new(proof), public_inputs), prover.nodes(), CommitmentKind::Plain)
+        }
+        ProvingKeys::Single(pk_path) if key_backend(pk_path)? == Backend::Marlin => {
+            let prover = MarlinProver::load(pk_path, validate).map_err(bad_input)?;
+            check_graph_size(prover.nodes(), adj_matrix.len())?;
+            let (proof, public_inputs) = match witness_salt(prover.is_salted(), witness.salt)? {
+                Some(salt) => prover.prove_salted_with_toposort(&adj_matrix, &topological_sort, salt, &mut rng),
+                None => prover.prove_with_toposort(&adj_matrix, &topological_sort, &mut rng),
+            }
+            .map_err(prove_error)?;
+            (AnyProof::Marlin(proof, public_inputs), prover.nodes(), prover.commitment())
+        }
+        ProvingKeys::Single(pk_path) => {
+            let prover = AcyclicityProverOn::<E>::load(pk_path, validate).map_err(bad_input)?;
+            check_graph_size(prover.nodes(), adj_matrix.len())?;
+            let (proof, public_inputs) = match witness_salt(prover.is_salted(), witness.salt)? {
+                Some(salt) => prover.prove_salted_with_toposort(&adj_matrix, &topological_sort, salt, &mut rng),
+                None => prover.prove_with_toposort(&adj_matrix, &topological_sort, &mut rng),
+            }
+            .map_err(prove_error)?;
+            (AnyProof::Groth16(Box::new(proof), public_inputs), prover.nodes(), prover.commitment())
+        }
+        ProvingKeys::Buckets(dir) => {
+            let prover = BucketedProverOn::<E>::load_dir(dir, validate).map_err(bad_input)?;
+            if prover.prover_for(adj_matrix.len()).is_none() {
+                return Err(CliError::BadInput(format!(
+                    "graph has {} nodes, but the keys in {} are for at most {} nodes",
+                    adj_matrix.len(),
+                    dir,
+                    prover.buckets().last().copied().unwrap_or(0)
+                )));
+            }
+            let (proof, public_inputs, nodes) = prover
+                .prove_with_toposort(&adj_matrix, &topological_sort, &mut rng)
+                .map_err(prove_error)?;
+            (AnyProof::Groth16(Box::new(proof), public_inputs), nodes, CommitmentKind::Plain)
+        }
+    };
+    let node_ids = edge_list.map(|graph| graph.ids);
+    let bundle_ids = match keys {
+        ProvingKeys::Single(_) => node_ids.clone(),
+        ProvingKeys::Buckets(_) => None,
+    };
+    write_proof(&proof, nodes, variant, commitment, bundle_ids, proof_path)?;
+    write_ids(node_ids.as_deref(), ids_path)?;
+    println!("{}", proof.commitment());
+    Ok(())
+}
 
-    let adj_hash = match adj_hash_result {
-        Ok(hash_vec) => hash_vec[0],
-        Err(e) => return Err(Box::new(e)),
+// proves with sparse keys, reading the graph as an edge list so nothing is sized by nodes x nodes
+fn prove_sparse<E: ZycleCurve>(
+    pk_path: &str,
+    graph_path: &str,
+    witness: Witness,
+    proof_path: &str,
+    ids_path: Option<&str>,
+    validate: Validate,
+) -> Result<(), CliError> {
+    if witness.start.is_some() {
+        return Err(CliError::BadInput("--start needs keys made with setup --reachability".to_string()));
+    }
+    if witness.salt.is_some() {
+        return Err(CliError::BadInput("--salt needs keys made with setup --salted".to_string()));
+    }
+    let (graph, node_ids) = load_sparse_graph(graph_path)?;
+    let positions = match witness.order {
+        Some(order) => parse_toposort(order, graph.nodes).map_err(bad_input)?,
+        None => graph.positions().map_err(bad_input)?,
     };
+    let prover = SparseProverOn::<E>::load(pk_path, validate).map_err(bad_input)?;
+    if graph.nodes > prover.nodes() {
+        return Err(CliError::BadInput(format!(
+            "key is for graphs with at most {} nodes, but the graph has {} nodes",
+            prover.nodes(),
+            graph.nodes
+        )));
+    }
+    let (proof, public_inputs) =
+        prover.prove_with_positions(&graph, &positions, &mut StdRng::from_entropy()).map_err(prove_error)?;
+    let proof = AnyProof::Groth16(Box::new(proof), public_inputs);
+    write_proof(&proof, prover.nodes(), CircuitVariant::Sparse, CommitmentKind::Plain, node_ids.clone(), proof_path)?;
+    write_ids(node_ids.as_deref(), ids_path)?;
+    println!("{}", proof.commitment());
+    Ok(())
+}
 
-    let circuit_inputs: MyGraphCircuitStruct<N, Fr> = MyGraphCircuitStruct {
-        adj_matrix: adj_matrix,
-        toposort: topological_sort,
-        adj_hash: adj_hash,
+// writes a proof bundle to a .json path and just the compressed proof to any other
+fn write_proof<E: ZycleCurve>(
+    proof: &AnyProof<E>,
+    nodes: usize,
+    variant: CircuitVariant,
+    commitment: CommitmentKind,
+    node_ids: Option<Vec<String>>,
+    proof_path: &str,
+) -> Result<(), CliError> {
+    if is_json_path(proof_path) {
+        match proof {
+            AnyProof::Groth16(proof, public_inputs) => {
+                ProofBundle::new(proof.as_ref(), public_inputs, nodes, variant, commitment, node_ids)
+            }
+            AnyProof::Marlin(proof, public_inputs) => {
+                ProofBundle::new_marlin(proof, public_inputs, nodes, variant, commitment, node_ids)
+            }
+        }
+        .and_then(|bundle| bundle.write_to_file(proof_path))
+        .map_err(internal)
+    } else {
+        match proof {
+            AnyProof::Groth16(proof, _) => write_compressed_to_file(proof.as_ref(), proof_path),
+            AnyProof::Marlin(proof, _) => write_compressed_to_file(proof, proof_path),
+        }
+        .map_err(internal)
+    }
+}
+
+fn write_ids(ids: Option<&[String]>, ids_path: Option<&str>) -> Result<(), CliError> {
+    if let (Some(ids), Some(ids_path)) = (ids, ids_path) {
+        let file = File::create(ids_path).map_err(internal)?;
+        write_id_mapping(ids, file).map_err(internal)?;
+    }
+    Ok(())
+}
+
+// the salt to prove with, which salted keys need and the others refuse
+fn witness_salt<F: PrimeField>(is_salted: bool, salt_path: Option<&str>) -> Result<Option<F>, CliError> {
+    match (is_salted, salt_path) {
+        (true, Some(salt_path)) => read_salt_from_file(salt_path).map(Some).map_err(bad_input),
+        (false, None) => Ok(None),
+        (true, None) => Err(CliError::BadInput("salted and row tree keys need a --salt file".to_string())),
+        (false, Some(_)) => {
+            Err(CliError::BadInput("--salt needs keys made with setup --salted or --row-tree".to_string()))
+        }
+    }
+}
+
+fn check_graph_size(key_nodes: usize, graph_nodes: usize) -> Result<(), CliError> {
+    if key_nodes != graph_nodes {
+        return Err(CliError::BadInput(format!(
+            "key is for graphs with {} nodes, but the graph has {} nodes",
+            key_nodes, graph_nodes
+        )));
+    }
+    Ok(())
+}
+
+// a node is given as an account id when the graph has them, or else as a node index
+fn parse_node(node: &str, ids: Option<&[String]>) -> Result<usize, CliError> {
+    if let Some(index) = ids.and_then(|ids| ids.iter().position(|id| id == node)) {
+        return Ok(index);
+    }
+    node.parse().map_err(|_| CliError::BadInput(format!("unknown node {:?}", node)))
+}
+
+// picks the default width, or checks the requested one can give every node its own position
+fn check_position_width(nodes: usize, position_width: Option<PositionWidth>) -> Result<PositionWidth, CliError> {
+    let position_width = match position_width {
+        Some(position_width) => position_width,
+        None => PositionWidth::for_nodes(nodes)
+            .ok_or_else(|| CliError::BadInput(format!("graphs with {} nodes are too large", nodes)))?,
+    };
+    if nodes > position_width.max_nodes() {
+        return Err(CliError::BadInput(format!(
+            "{} positions only number up to {} nodes, got {}",
+            position_width.id(),
+            position_width.max_nodes(),
+            nodes
+        )));
+    }
+    Ok(position_width)
+}
+
+fn parse_backend(id: &str) -> Result<Backend, String> {
+    Backend::from_id(id).ok_or_else(|| format!("expected groth16 or marlin, got {:?}", id))
+}
+
+fn parse_curve(id: &str) -> Result<Curve, String> {
+    Curve::from_id(id).ok_or_else(|| format!("expected bls12-381, bn254 or bls12-377, got {:?}", id))
+}
+
+fn parse_position_width(id: &str) -> Result<PositionWidth, String> {
+    PositionWidth::from_id(id).ok_or_else(|| format!("expected u8, u16, u32 or field<1-32>, got {:?}", id))
+}
+
+// a proof that fails the constraints means the order was wrong, anything else is our problem
+fn prove_error(e: ZycleError) -> CliError {
+    match e {
+        ZycleError::Synthesis(SynthesisError::Unsatisfiable) => {
+            CliError::BadInput("the order is not a topological sort of the graph".to_string())
+        }
+        e if e.is_bad_input() => bad_input(e),
+        e => internal(e),
+    }
+}
+
+// which circuit the key file holds a key for
+fn key_variant(key_path: &str) -> Result<CircuitVariant, CliError> {
+    let header = read_key_header_from_file(key_path).map_err(bad_input)?;
+    CircuitVariant::from_id(&header.variant)
+        .ok_or_else(|| CliError::BadInput(format!("key is for unknown circuit {:?}", header.variant)))
+}
+
+// which proof system the key file holds a key for
+fn key_backend(key_path: &str) -> Result<Backend, CliError> {
+    let header = read_key_header_from_file(key_path).map_err(bad_input)?;
+    Ok(header.kind.backend())
+}
+
+// which curve the key file holds a key for
+fn key_curve(key_path: &str) -> Result<Curve, CliError> {
+    let header = read_key_header_from_file(key_path).map_err(bad_input)?;
+    Curve::from_id(&header.curve)
+        .ok_or_else(|| CliError::BadInput(format!("key is for unknown curve {:?}", header.curve)))
+}
+
+// which curve the keys in a directory written by `setup --buckets` are on, going by the smallest bucket
+fn buckets_curve(dir: &str) -> Result<Curve, CliError> {
+    let pk_path = BUCKET_SIZES
+        .iter()
+        .map(|&size| proving_key_path(dir, size))
+        .find(|pk_path| std::path::Path::new(pk_path).exists())
+        .ok_or_else(|| CliError::BadInput(format!("no proving keys in {}", dir)))?;
+    key_curve(&pk_path)
+}
+
+fn verify(
+    vk_path: &str,
+    buckets_dir: Option<&str>,
+    proof_path: &str,
+    claim: Claim,
+    validate: Validate,
+) -> Result<(), CliError> {
+    let vk_path = match buckets_dir {
+        Some(dir) => {
+            if !is_json_path(proof_path) {
+                return Err(CliError::BadInput(
+                    "--buckets needs a .json proof bundle to know the bucket size".to_string(),
+                ));
+            }
+            let bundle = ProofBundle::read_from_file(proof_path).map_err(bad_input)?;
+            verifying_key_path(dir, bundle.n)
+        }
+        None => vk_path.to_string(),
+    };
+    if key_backend(&vk_path)? == Backend::Marlin {
+        return verify_marlin(&vk_path, proof_path, claim, validate);
+    }
+    let curve = key_curve(&vk_path)?;
+    on_curve!(curve, verify_on(&vk_path, proof_path, claim, validate))
+}
+
+// verify with a Groth16 key for any circuit on the curve E
+fn verify_on<E: ZycleCurve>(vk_path: &str, proof_path: &str, claim: Claim, validate: Validate) -> Result<(), CliError> {
+    let verifier = Verifier::<E>::load(vk_path, validate)?;
+    let commitment = claim.commitment.map(parse_commitment).transpose()?;
+
+    // public inputs given on the command line take precedence over the ones in the bundle,
+    // so a bundle for some other graph or start node is rejected
+    let (proof, bundle_inputs, node_ids) = if is_json_path(proof_path) {
+        let bundle = ProofBundle::read_from_file(proof_path).map_err(bad_input)?;
+        verifier.check_matches(&bundle)?;
+        let public_inputs = bundle.public_inputs().map_err(bad_input)?;
+        (bundle.proof().map_err(bad_input)?, public_inputs, bundle.node_ids)
+    } else if commitment.is_none() {
+        return Err(CliError::BadInput("--commitment is required for a proof without a bundle".to_string()));
+    } else {
+        (read_compressed_from_file(proof_path).map_err(bad_input)?, Vec::new(), None)
     };
-    // generate the proof
-    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(test_rng().next_u64());
-    let (pk, vk) = Groth16::<Bls12_381>::setup(circuit_inputs.clone(), &mut rng).unwrap();
-    let pvk = prepare_verifying_key::<Bls12_381>(&vk);
-    let proof: Proof<Bls12<Config>> = Groth16::<Bls12_381>::prove(&pk, circuit_inputs, &mut rng).unwrap();
+    let mut public_inputs = match commitment {
+        Some(commitment) => vec![commitment],
+        None => bundle_inputs.clone(),
+    };
+    if let Verifier::Reachability(_) = verifier {
+        let start = match claim.start {
+            Some(start) => E::ScalarField::from(parse_node(start, node_ids.as_deref())? as u64),
+            None => bundle_inputs.get(1).copied().ok_or_else(|| {
+                CliError::BadInput("--start is required for a proof without a bundle".to_string())
+            })?,
+        };
+        public_inputs.truncate(1);
+        public_inputs.push(start);
+    }
 
-    // test some verification checks
-    assert!(Groth16::<Bls12_381>::verify_with_processed_vk(&pvk, &[adj_hash], &proof).unwrap());
-    let false_hash = Fr::zero();
-    assert!(!Groth16::<Bls12_381>::verify_with_processed_vk(&pvk, &[false_hash], &proof).unwrap());
+    report_verification(verifier.verify(&proof, &public_inputs))
+}
 
-    // test IO
-    let file_path = "./proof.bin";
-    write_proof_to_file(&proof, file_path)?;
-    let read_proof: Proof<Bls12<Config>> = read_proof::<Bls12_381>(file_path)?;
-    assert!(Groth16::<Bls12_381>::verify_with_processed_vk(&pvk, &[adj_hash], &read_proof).unwrap());
+// verify with a marlin key, these are only made for the dense circuit on bls12-381
+fn verify_marlin(vk_path: &str, proof_path: &str, claim: Claim, validate: Validate) -> Result<(), CliError> {
+    let verifier = MarlinVerifier::load(vk_path, validate).map_err(bad_input)?;
+    let commitment = claim.commitment.map(parse_commitment).transpose()?;
+    let (proof, bundle_inputs) = if is_json_path(proof_path) {
+        let bundle = ProofBundle::read_from_file(proof_path).map_err(bad_input)?;
+        bundle
+            .check_matches_circuit::<Bls12_381>(CircuitVariant::TopoSort, verifier.commitment(), verifier.nodes())
+            .map_err(bad_input)?;
+        (bundle.marlin_proof().map_err(bad_input)?, bundle.public_inputs().map_err(bad_input)?)
+    } else if commitment.is_none() {
+        return Err(CliError::BadInput("--commitment is required for a proof without a bundle".to_string()));
+    } else {
+        (read_compressed_from_file(proof_path).map_err(bad_input)?, Vec::new())
+    };
+    let public_inputs: Vec<Fr> = match commitment {
+        Some(commitment) => vec![commitment],
+        None => bundle_inputs,
+    };
+    report_verification(verifier.verify(&proof, &public_inputs))
+}
 
+fn report_verification(result: Result<bool, ZycleError>) -> Result<(), CliError> {
+    match result {
+        Ok(true) => {
+            println!("proof is valid");
+            Ok(())
+        }
+        Ok(false) => Err(CliError::InvalidProof),
+        Err(e) => Err(internal(e)),
+    }
+}
+
+fn parse_commitment<F: PrimeField>(commitment: &str) -> Result<F, CliError> {
+    F::from_str(commitment).map_err(|_| CliError::BadInput(format!("invalid commitment {:?}", commitment)))
+}
+
+fn export_solidity(vk_path: &str, contract_path: &str, validate: Validate) -> Result<(), CliError> {
+    let verifier = AcyclicityVerifierOn::<Bn254>::load(vk_path, validate).map_err(bad_input)?;
+    let contract = solidity_verifier(verifier.verifying_key()).map_err(bad_input)?;
+    std::fs::write(contract_path, contract).map_err(internal)?;
+    println!("wrote {} to {}", CONTRACT_NAME, contract_path);
     Ok(())
 }
 
-// Generate proof and write to file
-fn write_proof_to_file(
-    proof: &Proof<Bls12<Config>>,
-    file_path: &str,
-) -> Result<(), io::Error> {
-    let mut compressed_bytes = Vec::new();
-    proof.serialize_compressed(&mut compressed_bytes).unwrap();
-    
-    let mut file: File = std::fs::OpenOptions::new()
-        .create(true)
-        .write(true)
-        .read(true)
-        .open(file_path)
-        .unwrap();
-    file.write_all(&compressed_bytes)?;
-    file.flush()?;
+fn calldata(proof_path: &str, commitment: Option<&str>) -> Result<(), CliError> {
+    let commitment = commitment.map(parse_commitment).transpose()?;
+    let (proof, bundle_inputs) = if is_json_path(proof_path) {
+        let bundle = ProofBundle::read_from_file(proof_path).map_err(bad_input)?;
+        (bundle.proof::<Bn254>().map_err(bad_input)?, bundle.public_inputs().map_err(bad_input)?)
+    } else if commitment.is_none() {
+        return Err(CliError::BadInput("--commitment is required for a proof without a bundle".to_string()));
+    } else {
+        (read_compressed_from_file(proof_path).map_err(bad_input)?, Vec::new())
+    };
+    let commitment = match (commitment, bundle_inputs.as_slice()) {
+        (Some(commitment), _) => commitment,
+        (None, [commitment]) => *commitment,
+        (None, _) => return Err(CliError::BadInput("the contract takes proofs with one public input".to_string())),
+    };
+    println!("{}", to_hex(&verifier_calldata(&proof, commitment)));
+    Ok(())
+}
+
+fn hash(graph_path: &str, padded: bool, edges: Option<usize>, curve: Curve) -> Result<(), CliError> {
+    let hash = match edges {
+        Some(edges) => {
+            let (graph, _) = load_sparse_graph(graph_path)?;
+            on_curve!(curve, sparse_commitment(&graph, edges))?
+        }
+        None => {
+            let (adj_matrix, _) = load_graph(graph_path)?;
+            on_curve!(curve, graph_commitment(&adj_matrix, padded))?
+        }
+    };
+    println!("{}", hash);
     Ok(())
 }
 
-// Read proof from file
-fn read_proof<E: Pairing>(
-    file_path: &str,
-) -> Result<Proof<E>, Box<dyn Error>> {
-    // Open and read the file
-    let mut file = File::open(file_path)?;
-    let mut buffer = Vec::new();
-    file.read_to_end(&mut buffer)?;
+// the public input of proofs on the curve E about the graph, for the kind of keys the flags of hash pick
+fn graph_commitment<E: ZycleCurve>(adj_matrix: &[Vec<bool>], padded: bool) -> Result<String, CliError> {
+    let hash: E::ScalarField = if padded {
+        padded_commitment(adj_matrix).map_err(bad_input)?
+    } else {
+        commitment(adj_matrix).map_err(internal)?
+    };
+    Ok(field_to_string(&hash))
+}
+
+// the public input of sparse proofs on the curve E about the graph, padded to max_edges edges
+fn sparse_commitment<E: ZycleCurve>(graph: &SparseGraph, max_edges: usize) -> Result<String, CliError> {
+    let hash: E::ScalarField = edge_commitment(graph, max_edges).map_err(bad_input)?;
+    Ok(field_to_string(&hash))
+}
+
+fn commit<E: ZycleCurve>(graph_path: &str, salt_path: &str, row_tree: bool) -> Result<(), CliError> {
+    let (adj_matrix, _) = load_graph(graph_path)?;
+    // a new salt would make the commitment already published with the old one impossible to open
+    if std::path::Path::new(salt_path).exists() {
+        return Err(CliError::BadInput(format!("{} already exists, not overwriting it", salt_path)));
+    }
+    let salt: E::ScalarField = generate_salt(&mut StdRng::from_entropy());
+    let hash = if row_tree {
+        row_tree_commitment(&adj_matrix, salt).map_err(internal)?
+    } else {
+        salted_commitment(&adj_matrix, salt).map_err(internal)?
+    };
+    write_salt_to_file(&salt, salt_path).map_err(internal)?;
+    println!("{}", field_to_string(&hash));
+    Ok(())
+}
 
-    // Deserialize the proof from the buffer
-    let proof: Proof<E> = Proof::<E>::deserialize_compressed(&mut buffer.as_slice())
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+fn open<E: ZycleCurve>(graph_path: &str, salt_path: &str, commitment: &str, row_tree: bool) -> Result<(), CliError> {
+    let (adj_matrix, _) = load_graph(graph_path)?;
+    let salt: E::ScalarField = read_salt_from_file(salt_path).map_err(bad_input)?;
+    let commitment = parse_commitment(commitment)?;
+    let opens = if row_tree {
+        row_tree_commitment(&adj_matrix, salt).map_err(internal)? == commitment
+    } else {
+        open_commitment(&adj_matrix, salt, commitment).map_err(internal)?
+    };
+    if opens {
+        println!("graph and salt open the commitment");
+        Ok(())
+    } else {
+        Err(CliError::Rejected("graph and salt do not open the commitment".to_string()))
+    }
+}
+
+fn reveal<E: ZycleCurve>(graph_path: &str, row: &str, salt_path: &str, opening_path: &str) -> Result<(), CliError> {
+    let (adj_matrix, edge_list) = load_graph(graph_path)?;
+    let index = parse_node(row, edge_list.as_ref().map(|graph| graph.ids.as_slice()))?;
+    let salt = read_salt_from_file(salt_path).map_err(bad_input)?;
+    let tree = RowTree::<E::ScalarField>::new(&adj_matrix, salt).map_err(internal)?;
+    let opening = tree
+        .open(index)
+        .ok_or_else(|| CliError::BadInput(format!("node {} is not in the graph", index)))?;
+    opening.write_to_file(opening_path).map_err(internal)?;
+    println!("{}", field_to_string(&tree.root()));
+    Ok(())
+}
+
+fn check_row<E: ZycleCurve>(opening_path: &str, commitment: &str) -> Result<(), CliError> {
+    let opening = RowOpening::<E::ScalarField>::read_from_file(opening_path).map_err(bad_input)?;
+    if !opening.verify(parse_commitment(commitment)?).map_err(bad_input)? {
+        return Err(CliError::Rejected(format!("row {} is not in the tree with this root", opening.index)));
+    }
+    let row: Vec<&str> = opening.row.iter().map(|&edge| if edge { "1" } else { "0" }).collect();
+    println!("row {}: {}", opening.index, row.join(","));
+    Ok(())
+}
+
+// reads an adjacency matrix, edge list or JSON graph, keeping the account ids if there are any
+fn load_graph(graph_path: &str) -> Result<(Vec<Vec<bool>>, Option<EdgeListGraph>), CliError> {
+    if is_json_path(graph_path) {
+        let graph = GraphInput::read_from_file(graph_path)
+            .and_then(|input| input.to_graph())
+            .map_err(bad_input)?;
+        Ok((graph.adj_matrix.clone(), Some(graph)))
+    } else if is_edge_list_path(graph_path) {
+        let edge_list = read_edge_list(graph_path).map_err(bad_input)?;
+        Ok((edge_list.adj_matrix.clone(), Some(edge_list)))
+    } else {
+        Ok((read_adjacency_matrix(graph_path).map_err(bad_input)?, None))
+    }
+}
+
+// reads a graph for the sparse keys as a list of edges, which an adjacency matrix file is turned into
+fn load_sparse_graph(graph_path: &str) -> Result<(SparseGraph, Option<Vec<String>>), CliError> {
+    let edge_list = if is_json_path(graph_path) {
+        GraphInput::read_from_file(graph_path)
+            .and_then(|input| input.to_edge_list())
+            .map_err(bad_input)?
+    } else if is_edge_list_path(graph_path) {
+        read_edges(graph_path).map_err(bad_input)?
+    } else {
+        let adj_matrix = read_adjacency_matrix(graph_path).map_err(bad_input)?;
+        return Ok((SparseGraph::from_adjacency_matrix(&adj_matrix).map_err(bad_input)?, None));
+    };
+    let graph = SparseGraph::new(edge_list.ids.len(), edge_list.edges).map_err(bad_input)?;
+    Ok((graph, Some(edge_list.ids)))
+}
+
+fn is_json_path(file_path: &str) -> bool {
+    file_path.to_ascii_lowercase().ends_with(".json")
+}
+
+fn validate_mode(no_validate: bool) -> Validate {
+    if no_validate {
+        Validate::No
+    } else {
+        Validate::Yes
+    }
+}
+
+fn bad_input(e: impl ToString) -> CliError {
+    CliError::BadInput(e.to_string())
+}
 
-    return Ok(proof)
+fn internal(e: impl ToString) -> CliError {
+    CliError::Internal(e.to_string())
 }
@@ -0,0 +1,342 @@
+
+use ark_crypto_primitives::sponge::{
+    poseidon::PoseidonSponge, Absorb, CryptographicSponge, FieldBasedCryptographicSponge,
+};
+use ark_ff::PrimeField;
+use ark_r1cs_std::{boolean::Boolean, fields::fp::FpVar, fields::FieldVar};
+use ark_relations::r1cs::{ConstraintSystemRef, SynthesisError};
+use ark_std::{fs, io};
+use serde::{Deserialize, Serialize};
+
+use crate::bundle::field_to_string;
+use crate::hashing::hashing_utils::{CryptographicSpongeVar, PoseidonSpongeVar};
+use crate::hashing::{bit_packer, bit_packer_var, poseidon_parameters};
+use crate::utils::Boolean2DVec;
+
+// Poseidon Merkle tree over the rows of the adjacency matrix. Each leaf hashes one packed row
+// followed by a salt of its own, the leaves are padded with zeros to a power of two, and each
+// node hashes its two children. Leaves start with the row length and nodes with a hash, so one
+// is not mistaken for the other. The root is the public input of proofs made with row tree keys,
+// and a row can be revealed later with its opening. An opening shows the hashes of the sibling
+// subtrees, which could be matched by hashing every possible row if the leaves were not salted.
+// The leaf salts are derived from one secret salt (see salt.rs) and the row index, and an
+// opening carries only the salt of its own leaf, so revealing a row tells nothing about the others.
+
+// bumped whenever a field of the opening file is added, removed or changes meaning
+pub const OPENING_VERSION: u32 = 2;
+
+// the salt of the leaf of row `index`, which only the holder of the secret salt can work out
+pub fn leaf_salt<F: PrimeField + Absorb>(salt: F, index: usize) -> F {
+    let mut sponge = PoseidonSponge::<F>::new(&poseidon_parameters());
+    sponge.absorb(&vec![salt, F::from(index as u64)]);
+    sponge.squeeze_native_field_elements(1)[0]
+}
+
+pub fn leaf_salt_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    salt: &FpVar<F>,
+    index: usize,
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut sponge = PoseidonSpongeVar::<F>::new(cs, &poseidon_parameters());
+    sponge.absorb(&vec![salt.clone(), FpVar::constant(F::from(index as u64))])?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
+
+pub fn row_leaf<F: PrimeField + Absorb>(row: &[bool], leaf_salt: F) -> Result<F, SynthesisError> {
+    let mut sponge = PoseidonSponge::<F>::new(&poseidon_parameters());
+    sponge.absorb(&bit_packer::<F>(row)?);
+    sponge.absorb(&leaf_salt);
+    Ok(sponge.squeeze_native_field_elements(1)[0])
+}
+
+pub fn row_leaf_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    row: &[Boolean<F>],
+    leaf_salt: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut sponge = PoseidonSpongeVar::<F>::new(cs, &poseidon_parameters());
+    sponge.absorb(&bit_packer_var(row)?)?;
+    sponge.absorb(leaf_salt)?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
+
+fn hash_pair<F: PrimeField + Absorb>(left: F, right: F) -> F {
+    let mut sponge = PoseidonSponge::<F>::new(&poseidon_parameters());
+    sponge.absorb(&vec![left, right]);
+    sponge.squeeze_native_field_elements(1)[0]
+}
+
+fn hash_pair_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    left: &FpVar<F>,
+    right: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut sponge = PoseidonSpongeVar::<F>::new(cs, &poseidon_parameters());
+    sponge.absorb(&vec![left.clone(), right.clone()])?;
+    Ok(sponge.squeeze_field_elements(1)?.remove(0))
+}
+
+// the root of the row tree of a witnessed matrix and salt, as RowTree::root computes it natively
+pub fn row_tree_root_var<F: PrimeField>(
+    cs: ConstraintSystemRef<F>,
+    adj_matrix: &Boolean2DVec<F>,
+    salt: &FpVar<F>,
+) -> Result<FpVar<F>, SynthesisError> {
+    let mut layer = adj_matrix
+        .0
+        .iter()
+        .enumerate()
+        .map(|(index, row)| row_leaf_var(cs.clone(), row, &leaf_salt_var(cs.clone(), salt, index)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    layer.resize(layer.len().next_power_of_two(), FpVar::zero());
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair_var(cs.clone(), &pair[0], &pair[1]))
+            .collect::<Result<Vec<_>, _>>()?;
+    }
+    Ok(layer.remove(0))
+}
+
+pub struct RowTree<F: PrimeField> {
+    rows: Vec<Vec<bool>>,
+    leaf_salts: Vec<F>,
+    // leaves first, the last layer holds only the root
+    layers: Vec<Vec<F>>,
+}
+
+impl<F: PrimeField + Absorb> RowTree<F> {
+    pub fn new<Row: AsRef<[bool]>>(adj_matrix: &[Row], salt: F) -> Result<Self, SynthesisError> {
+        let rows: Vec<Vec<bool>> = adj_matrix.iter().map(|row| row.as_ref().to_vec()).collect();
+        let leaf_salts: Vec<F> = (0..rows.len()).map(|index| leaf_salt(salt, index)).collect();
+        let mut leaves =
+            rows.iter().zip(&leaf_salts).map(|(row, &salt)| row_leaf(row, salt)).collect::<Result<Vec<F>, _>>()?;
+        leaves.resize(leaves.len().next_power_of_two(), F::zero());
+        let mut layers = vec![leaves];
+        while layers[layers.len() - 1].len() > 1 {
+            let layer = layers[layers.len() - 1].chunks(2).map(|pair| hash_pair(pair[0], pair[1])).collect();
+            layers.push(layer);
+        }
+        Ok(Self { rows, leaf_salts, layers })
+    }
+
+    pub fn root(&self) -> F {
+        self.layers[self.layers.len() - 1][0]
+    }
+
+    // the row of node `index` and its leaf salt with the sibling hashes from its leaf up to the root
+    pub fn open(&self, index: usize) -> Option<RowOpening<F>> {
+        let row = self.rows.get(index)?.clone();
+        let salt = self.leaf_salts[index];
+        let siblings = self.layers[..self.layers.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(level, layer)| layer[(index >> level) ^ 1])
+            .collect();
+        Some(RowOpening { index, row, salt, siblings })
+    }
+}
+
+// reveals one row of a graph committed to by a row tree root
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RowOpening<F: PrimeField> {
+    pub index: usize,
+    pub row: Vec<bool>,
+    // the salt of this leaf alone, not the secret salt the tree was built with
+    pub salt: F,
+    pub siblings: Vec<F>,
+}
+
+impl<F: PrimeField + Absorb> RowOpening<F> {
+    // whether the row is the one at index in the tree with this root
+    pub fn verify(&self, root: F) -> Result<bool, SynthesisError> {
+        if self.index >> self.siblings.len() != 0 {
+            return Ok(false);
+        }
+        let mut node = row_leaf(&self.row, self.salt)?;
+        for (level, sibling) in self.siblings.iter().enumerate() {
+            node = if (self.index >> level) & 1 == 0 {
+                hash_pair(node, *sibling)
+            } else {
+                hash_pair(*sibling, node)
+            };
+        }
+        Ok(node == root)
+    }
+}
+
+// JSON form of an opening, handed to whoever the row is revealed to
+//   {
+//     "version": 2,
+//     "index": 2,
+//     "row": [false, true, false, false],
+//     "salt": "<leaf salt as a decimal field element>",
+//     "siblings": ["<hash as a decimal field element>", ...]
+//   }
+#[derive(Serialize, Deserialize)]
+struct RowOpeningFile {
+    version: u32,
+    index: usize,
+    row: Vec<bool>,
+    salt: String,
+    siblings: Vec<String>,
+}
+
+impl<F: PrimeField + Absorb> RowOpening<F> {
+    pub fn to_json(&self) -> Result<String, io::Error> {
+        let file = RowOpeningFile {
+            version: OPENING_VERSION,
+            index: self.index,
+            row: self.row.clone(),
+            salt: field_to_string(&self.salt),
+            siblings: self.siblings.iter().map(field_to_string).collect(),
+        };
+        Ok(serde_json::to_string_pretty(&file)?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, io::Error> {
+        let file: RowOpeningFile = serde_json::from_str(json)?;
+        if file.version != OPENING_VERSION {
+            return Err(invalid_data(format!(
+                "unsupported opening version {}, expected {}",
+                file.version, OPENING_VERSION
+            )));
+        }
+        let siblings = file.siblings.iter().map(|s| parse_field(s)).collect::<Result<_, _>>()?;
+        Ok(Self { index: file.index, row: file.row, salt: parse_field(&file.salt)?, siblings })
+    }
+
+    pub fn write_to_file(&self, file_path: &str) -> Result<(), io::Error> {
+        fs::write(file_path, self.to_json()?)
+    }
+
+    pub fn read_from_file(file_path: &str) -> Result<Self, io::Error> {
+        Self::from_json(&fs::read_to_string(file_path)?)
+    }
+}
+
+// the public input of proofs made with row tree keys
+pub fn row_tree_commitment<F: PrimeField + Absorb, Row: AsRef<[bool]>>(
+    adj_matrix: &[Row],
+    salt: F,
+) -> Result<F, SynthesisError> {
+    Ok(RowTree::<F>::new(adj_matrix, salt)?.root())
+}
+
+fn parse_field<F: PrimeField>(s: &str) -> Result<F, io::Error> {
+    F::from_str(s).map_err(|_| invalid_data(format!("invalid field element {:?}", s)))
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[test]
+fn row_tree_root_matches_circuit() {
+    use ark_bls12_381::fr::Fr;
+    use ark_r1cs_std::{alloc::AllocVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    for n in [1, 2, 3, 5, 8] {
+        let adj_matrix: Vec<Vec<bool>> = (0..n).map(|_| (0..n).map(|_| rng.gen()).collect()).collect();
+        let salt = Fr::from(rng.next_u64());
+        let tree = RowTree::<Fr>::new(&adj_matrix, salt).unwrap();
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        let adj_matrix_var = Boolean2DVec::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
+        let salt_var = FpVar::new_witness(cs.clone(), || Ok(salt)).unwrap();
+        let root_var = row_tree_root_var(cs.clone(), &adj_matrix_var, &salt_var).unwrap();
+        assert_eq!(root_var.value().unwrap(), tree.root());
+        assert!(cs.is_satisfied().unwrap());
+    }
+}
+
+#[test]
+fn row_openings() {
+    use ark_bls12_381::fr::Fr;
+
+    let adj_matrix = [
+        [false, true, false, false, false],
+        [false, false, true, false, false],
+        [false, false, false, true, true],
+        [false, false, false, false, false],
+        [true, false, false, false, false],
+    ];
+    let salt = Fr::from(7u64);
+    let tree = RowTree::<Fr>::new(&adj_matrix, salt).unwrap();
+    let root = row_tree_commitment(&adj_matrix, salt).unwrap();
+    assert_eq!(tree.root(), root);
+    assert_ne!(row_tree_commitment(&adj_matrix, salt + Fr::from(1u64)).unwrap(), root);
+    for (i, row) in adj_matrix.iter().enumerate() {
+        let opening = tree.open(i).unwrap();
+        assert_eq!(opening.row, row.to_vec());
+        assert_eq!(opening.salt, leaf_salt(salt, i));
+        assert_ne!(opening.salt, salt);
+        assert_eq!(opening.siblings.len(), 3);
+        assert!(opening.verify(root).unwrap());
+        let read_back = RowOpening::from_json(&opening.to_json().unwrap()).unwrap();
+        assert_eq!(read_back, opening);
+    }
+    assert!(tree.open(5).is_none());
+
+    // a changed row, a row claimed for another node or another root are all rejected
+    let opening = tree.open(2).unwrap();
+    let mut changed = opening.clone();
+    changed.row[0] = true;
+    assert!(!changed.verify(root).unwrap());
+    let mut moved = opening.clone();
+    moved.index = 3;
+    assert!(!moved.verify(root).unwrap());
+    moved.index = 2 + 8;
+    assert!(!moved.verify(root).unwrap());
+    let mut resalted = opening.clone();
+    resalted.salt = salt;
+    assert!(!resalted.verify(root).unwrap());
+    assert!(!opening.verify(root + Fr::from(1u64)).unwrap());
+}
+
+#[test]
+fn sibling_rows_stay_hidden() {
+    use ark_bls12_381::fr::Fr;
+
+    let adj_matrix = [[false, false, true, true], [false, false, true, false], [false; 4], [false; 4]];
+    let salt = Fr::from(11u64);
+    let opening = RowTree::<Fr>::new(&adj_matrix, salt).unwrap().open(0).unwrap();
+    // the first sibling is the leaf of row 1, which no row of 4 bits matches under the salt the
+    // opening shows, or without a salt as an unsalted leaf would be matched
+    let candidates = (0..16u32).map(|bits| (0..4).map(|i| bits >> i & 1 == 1).collect::<Vec<bool>>());
+    for candidate in candidates {
+        for guess in [opening.salt, Fr::from(0u64)] {
+            assert_ne!(row_leaf::<Fr>(&candidate, guess).unwrap(), opening.siblings[0]);
+        }
+    }
+    // only the salt of row 1 itself, which the opening does not hold, gives its leaf
+    assert_eq!(row_leaf::<Fr>(&adj_matrix[1], leaf_salt(salt, 1)).unwrap(), opening.siblings[0]);
+}
+
+#[test]
+fn row_tree_prove_and_verify() {
+    use ark_bls12_381::fr::Fr;
+    use crate::circuit::{CommitmentKind, PositionWidth};
+    use crate::AcyclicityProver;
+    use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+
+    let adj_matrix = [[false, true, false], [false, false, true], [false, false, false]];
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) =
+        AcyclicityProver::setup_with_commitment(3, PositionWidth::U8, CommitmentKind::RowTree, &mut rng).unwrap();
+    let salt = Fr::from(rng.next_u64());
+    assert!(prover.is_salted() && prover.prove(&adj_matrix, &mut rng).is_err());
+    let (proof, public_inputs) = prover.prove_salted(&adj_matrix, salt, &mut rng).unwrap();
+    let root = row_tree_commitment(&adj_matrix, salt).unwrap();
+    assert_eq!(public_inputs, vec![root]);
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+    assert!(!verifier.verify(&proof, &[crate::prover::commitment(&adj_matrix).unwrap()]).unwrap());
+
+    // the row a regulator is shown belongs to the graph the proof is about
+    let opening = RowTree::<Fr>::new(&adj_matrix, salt).unwrap().open(1).unwrap();
+    assert!(opening.verify(public_inputs[0]).unwrap());
+}
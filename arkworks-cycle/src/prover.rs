@@ -0,0 +1,498 @@
+use ark_bls12_381::Bls12_381;
+use ark_crypto_primitives::snark::{CircuitSpecificSetupSNARK, SNARK};
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ec::pairing::Pairing;
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey};
+use ark_r1cs_std::alloc::AllocVar;
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystem, SynthesisError};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress, Read, Validate, Write};
+use ark_std::{
+    fs::{self, File},
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::circuit::{CircuitVariant, CommitmentKind, MyGraphCircuitStruct, PositionWidth};
+use crate::curves::ZycleCurve;
+use crate::error::ZycleError;
+use crate::keys::{read_key, read_verifying_key, write_key_to_file, CircuitShape};
+use crate::hashing::hasher_vec;
+use crate::merkle::row_tree_commitment;
+use crate::utils::Boolean2DVec;
+use crate::salt::salted_commitment;
+use crate::witness::toposort_witness_vec;
+
+// Holds the Groth16 proving key for graphs with a fixed number of nodes, on the curve E
+pub struct AcyclicityProverOn<E: ZycleCurve> {
+    nodes: usize,
+    position_width: PositionWidth,
+    commitment: CommitmentKind,
+    pk: ProvingKey<E>,
+}
+
+// Holds the Groth16 verifying key (and its prepared form), the number of nodes is fixed by the key
+pub struct AcyclicityVerifierOn<E: ZycleCurve> {
+    nodes: usize,
+    position_width: PositionWidth,
+    commitment: CommitmentKind,
+    vk: VerifyingKey<E>,
+    pvk: PreparedVerifyingKey<E>,
+}
+
+// a proof and the public inputs it is checked against
+pub type ProofWithInputs<E> = (Proof<E>, Vec<<E as Pairing>::ScalarField>);
+
+// the prover and verifier on the default curve
+pub type AcyclicityProver = AcyclicityProverOn<Bls12_381>;
+pub type AcyclicityVerifier = AcyclicityVerifierOn<Bls12_381>;
+
+impl<E: ZycleCurve> AcyclicityProverOn<E> {
+    // runs the circuit specific setup for graphs with `nodes` nodes
+    // and returns the matching prover and verifier
+    // positions get the smallest unsigned width that fits, see setup_with_width to choose it
+    pub fn setup<R: RngCore + CryptoRng>(
+        nodes: usize,
+        rng: &mut R,
+    ) -> Result<(Self, AcyclicityVerifierOn<E>), ZycleError> {
+        Self::setup_with_width(nodes, width_for_nodes(nodes)?, rng)
+    }
+
+    // a width too narrow to number every node can never be satisfied, so it is refused up front
+    pub fn setup_with_width<R: RngCore + CryptoRng>(
+        nodes: usize,
+        position_width: PositionWidth,
+        rng: &mut R,
+    ) -> Result<(Self, AcyclicityVerifierOn<E>), ZycleError> {
+        check_width(nodes, position_width)?;
+        let circuit = MyGraphCircuitStruct::<E::ScalarField>::blank(nodes, position_width);
+        let (pk, vk) = Groth16::<E>::setup(circuit, rng)?;
+        Ok((
+            Self::new(pk, nodes, position_width),
+            AcyclicityVerifierOn::new(vk, nodes, position_width),
+        ))
+    }
+
+    // keys for proofs against a salted commitment (see salt.rs), which hides the graph from
+    // anyone trying to match the commitment against graphs they can guess
+    pub fn setup_salted<R: RngCore + CryptoRng>(
+        nodes: usize,
+        position_width: PositionWidth,
+        rng: &mut R,
+    ) -> Result<(Self, AcyclicityVerifierOn<E>), ZycleError> {
+        Self::setup_with_commitment(nodes, position_width, CommitmentKind::Salted, rng)
+    }
+
+    // keys for proofs whose public input is the given kind of commitment, with RowTree single
+    // rows can be revealed later (see merkle.rs); Salted and RowTree keys prove with prove_salted
+    pub fn setup_with_commitment<R: RngCore + CryptoRng>(
+        nodes: usize,
+        position_width: PositionWidth,
+        commitment: CommitmentKind,
+        rng: &mut R,
+    ) -> Result<(Self, AcyclicityVerifierOn<E>), ZycleError> {
+        check_width(nodes, position_width)?;
+        let circuit = MyGraphCircuitStruct::<E::ScalarField>::blank_with_commitment(nodes, position_width, commitment);
+        let (pk, vk) = Groth16::<E>::setup(circuit, rng)?;
+        let verifier = AcyclicityVerifierOn { commitment, ..AcyclicityVerifierOn::new(vk, nodes, position_width) };
+        Ok((Self { commitment, ..Self::new(pk, nodes, position_width) }, verifier))
+    }
+
+    pub fn new(pk: ProvingKey<E>, nodes: usize, position_width: PositionWidth) -> Self {
+        Self { nodes, position_width, commitment: CommitmentKind::Plain, pk }
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<E> {
+        &self.pk
+    }
+
+    // number of nodes in the graphs this prover can prove things about
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn position_width(&self) -> PositionWidth {
+        self.position_width
+    }
+
+    // whether proofs are made against a salted or row tree commitment, with prove_salted
+    pub fn is_salted(&self) -> bool {
+        self.commitment.is_salted()
+    }
+
+    pub fn commitment(&self) -> CommitmentKind {
+        self.commitment
+    }
+
+    fn shape(&self) -> CircuitShape {
+        CircuitShape { commitment: self.commitment, ..CircuitShape::dense(self.nodes, self.position_width) }
+    }
+
+    // loads a proving key written by write_proving_key, the number of nodes comes from its header
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        Self::from_bytes(&fs::read(file_path)?, validate)
+    }
+
+    // same as load for the contents of a key file
+    pub fn from_bytes(bytes: &[u8], validate: Validate) -> Result<Self, ZycleError> {
+        let (header, pk) = read_key(bytes, validate)?;
+        header.expect_variant(CircuitVariant::TopoSort)?;
+        Ok(Self { commitment: header.commitment, ..Self::new(pk, header.nodes, header.position_width) })
+    }
+
+    pub fn write_proving_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.pk, self.shape(), compress, file_path)
+    }
+
+    // proves that adj_matrix has no cycles, working out the topological sort itself
+    // returns the proof and the public inputs (the hash of adj_matrix) it should be checked against
+    // fails with ZycleError::CyclicGraph naming a cycle if the graph is not acyclic
+    pub fn prove<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        check_matrix(adj_matrix, self.nodes)?;
+        let topological_sort = toposort_witness_vec(adj_matrix)?;
+        self.prove_with_toposort(adj_matrix, &topological_sort, rng)
+    }
+
+    // proves that topological_sort is a valid ordering of adj_matrix
+    // fails with ZycleError::Synthesis(SynthesisError::Unsatisfiable) if it is not
+    pub fn prove_with_toposort<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        topological_sort: &[u32],
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        self.prove_with_salt(adj_matrix, topological_sort, None, rng)
+    }
+
+    // same as prove for keys made with setup_salted, the public input is salted_commitment(adj_matrix, salt),
+    // or row_tree_commitment(adj_matrix, salt) for row tree keys
+    pub fn prove_salted<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        salt: E::ScalarField,
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        check_matrix(adj_matrix, self.nodes)?;
+        let topological_sort = toposort_witness_vec(adj_matrix)?;
+        self.prove_salted_with_toposort(adj_matrix, &topological_sort, salt, rng)
+    }
+
+    pub fn prove_salted_with_toposort<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        topological_sort: &[u32],
+        salt: E::ScalarField,
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        self.prove_with_salt(adj_matrix, topological_sort, Some(salt), rng)
+    }
+
+    fn prove_with_salt<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        topological_sort: &[u32],
+        salt: Option<E::ScalarField>,
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        let circuit_inputs =
+            dense_circuit(adj_matrix, topological_sort, salt, self.nodes, self.position_width, self.commitment)?;
+        let adj_hash = circuit_inputs.adj_hash;
+        let proof = prove_checked(&self.pk, circuit_inputs, rng)?;
+        Ok((proof, vec![adj_hash]))
+    }
+}
+
+// fills in the dense circuit for keys made for `nodes` nodes and the given commitment, refusing
+// inputs that do not fit the keys
+pub(crate) fn dense_circuit<F: PrimeField + Absorb, Row: AsRef<[bool]>>(
+    adj_matrix: &[Row],
+    topological_sort: &[u32],
+    salt: Option<F>,
+    nodes: usize,
+    position_width: PositionWidth,
+    commitment_kind: CommitmentKind,
+) -> Result<MyGraphCircuitStruct<F>, ZycleError> {
+    let salted = commitment_kind.is_salted();
+    if salt.is_some() != salted {
+        let msg = if salted {
+            "the keys were made for a salted commitment, prove with a salt"
+        } else {
+            "the keys were made for an unsalted commitment, prove without a salt"
+        };
+        return Err(ZycleError::KeyMismatch(msg.to_string()));
+    }
+    let adj_matrix = matrix_rows(adj_matrix, nodes)?;
+    if topological_sort.len() != nodes {
+        return Err(ZycleError::InvalidGraph(format!(
+            "topological sort has {} entries, expected {}",
+            topological_sort.len(),
+            nodes
+        )));
+    }
+    // the position gadgets would only report a missing assignment for these
+    if let Some(&pos) = topological_sort.iter().find(|&&pos| pos as usize >= position_width.max_nodes()) {
+        return Err(ZycleError::InvalidGraph(format!(
+            "position {} does not fit in {} positions",
+            pos,
+            position_width.id()
+        )));
+    }
+    let adj_hash = match (commitment_kind, salt) {
+        (CommitmentKind::Salted, Some(salt)) => salted_commitment(&adj_matrix, salt)?,
+        (CommitmentKind::RowTree, Some(salt)) => row_tree_commitment(&adj_matrix, salt)?,
+        _ => commitment(&adj_matrix)?,
+    };
+    Ok(MyGraphCircuitStruct {
+        adj_matrix,
+        toposort: topological_sort.to_vec(),
+        position_width,
+        adj_hash,
+        commitment: commitment_kind,
+        salt,
+    })
+}
+
+// proves with a Groth16 key after checking the witness, as Groth16 proves whatever it is given
+// and a bad witness would only show up as a proof that does not verify
+pub(crate) fn prove_checked<E: ZycleCurve, C: ConstraintSynthesizer<E::ScalarField> + Clone, R: RngCore + CryptoRng>(
+    pk: &ProvingKey<E>,
+    circuit: C,
+    rng: &mut R,
+) -> Result<Proof<E>, ZycleError> {
+    check_satisfied(circuit.clone())?;
+    Ok(Groth16::<E>::prove(pk, circuit, rng)?)
+}
+
+// fails with ZycleError::Synthesis(SynthesisError::Unsatisfiable) unless the witness satisfies the constraints
+pub(crate) fn check_satisfied<F: PrimeField, C: ConstraintSynthesizer<F>>(circuit: C) -> Result<(), ZycleError> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    circuit.generate_constraints(cs.clone())?;
+    if !cs.is_satisfied()? {
+        return Err(SynthesisError::Unsatisfiable.into());
+    }
+    Ok(())
+}
+
+// checks the matrix is square with the number of nodes the keys were made for, the witness
+// generation indexes rows by column and would panic on anything else
+pub(crate) fn check_matrix<Row: AsRef<[bool]>>(adj_matrix: &[Row], nodes: usize) -> Result<(), ZycleError> {
+    if adj_matrix.len() != nodes || adj_matrix.iter().any(|row| row.as_ref().len() != nodes) {
+        return Err(ZycleError::InvalidGraph(format!("expected a {}x{} adjacency matrix", nodes, nodes)));
+    }
+    Ok(())
+}
+
+// copies the matrix once check_matrix accepts it
+pub(crate) fn matrix_rows<Row: AsRef<[bool]>>(adj_matrix: &[Row], nodes: usize) -> Result<Vec<Vec<bool>>, ZycleError> {
+    check_matrix(adj_matrix, nodes)?;
+    Ok(adj_matrix.iter().map(|row| row.as_ref().to_vec()).collect())
+}
+
+// the smallest position width for `nodes` nodes, or an error if even u32 positions are too few
+pub(crate) fn width_for_nodes(nodes: usize) -> Result<PositionWidth, ZycleError> {
+    PositionWidth::for_nodes(nodes)
+        .ok_or_else(|| ZycleError::InvalidGraph(format!("no position width can number {} nodes", nodes)))
+}
+
+// a width too narrow to number every node can never be satisfied
+pub(crate) fn check_width(nodes: usize, position_width: PositionWidth) -> Result<(), ZycleError> {
+    if nodes > position_width.max_nodes() {
+        return Err(ZycleError::InvalidGraph(format!(
+            "{} positions cannot number {} nodes",
+            position_width.id(),
+            nodes
+        )));
+    }
+    Ok(())
+}
+
+impl<E: ZycleCurve> AcyclicityVerifierOn<E> {
+    pub fn new(vk: VerifyingKey<E>, nodes: usize, position_width: PositionWidth) -> Self {
+        let pvk = prepare_verifying_key::<E>(&vk);
+        Self { nodes, position_width, commitment: CommitmentKind::Plain, vk, pvk }
+    }
+
+    // loads either a verifying key or a prepared verifying key, whichever the file holds
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        Self::from_bytes(&fs::read(file_path)?, validate)
+    }
+
+    // same as load for the contents of a key file, e.g. when there is no file system
+    pub fn from_bytes(bytes: &[u8], validate: Validate) -> Result<Self, ZycleError> {
+        let (header, pvk) = read_verifying_key(bytes, CircuitVariant::TopoSort, validate)?;
+        Ok(Self {
+            nodes: header.nodes,
+            position_width: header.position_width,
+            commitment: header.commitment,
+            vk: pvk.vk.clone(),
+            pvk,
+        })
+    }
+
+    pub fn write_verifying_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.vk, self.shape(), compress, file_path)
+    }
+
+    pub fn write_prepared_verifying_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.pvk, self.shape(), compress, file_path)
+    }
+
+    // number of nodes in the graphs this verifier accepts proofs for
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    // whether proofs are checked against a salted or row tree commitment
+    pub fn is_salted(&self) -> bool {
+        self.commitment.is_salted()
+    }
+
+    pub fn commitment(&self) -> CommitmentKind {
+        self.commitment
+    }
+
+    fn shape(&self) -> CircuitShape {
+        CircuitShape { commitment: self.commitment, ..CircuitShape::dense(self.nodes, self.position_width) }
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+
+    pub fn prepared_verifying_key(&self) -> &PreparedVerifyingKey<E> {
+        &self.pvk
+    }
+
+    // checks the proof against the public inputs returned by AcyclicityProver::prove
+    pub fn verify(
+        &self,
+        proof: &Proof<E>,
+        public_inputs: &[E::ScalarField],
+    ) -> Result<bool, ZycleError> {
+        Ok(Groth16::<E>::verify_with_processed_vk(&self.pvk, public_inputs, proof)?)
+    }
+}
+
+// calculates the public hash of an adjacency matrix, as checked inside the circuit
+pub fn commitment<F: PrimeField + Absorb, Row: AsRef<[bool]>>(adj_matrix: &[Row]) -> Result<F, SynthesisError> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    let adj_rows: Vec<Vec<bool>> = adj_matrix.iter().map(|row| row.as_ref().to_vec()).collect();
+    let adj_matrix_var = Boolean2DVec::new_witness(cs, || Ok(adj_rows))?;
+    Ok(hasher_vec(&adj_matrix_var)?[0])
+}
+
+// Write a proof to file in compressed form
+pub fn write_proof_to_file<E: Pairing>(proof: &Proof<E>, file_path: &str) -> Result<(), ZycleError> {
+    write_compressed_to_file(proof, file_path)
+}
+
+// Read proof from file
+pub fn read_proof<E: Pairing>(file_path: &str) -> Result<Proof<E>, ZycleError> {
+    read_compressed_from_file(file_path)
+}
+
+// Serializes any arkworks object (proof, keys, ...) to file in compressed form
+pub fn write_compressed_to_file<T: CanonicalSerialize>(item: &T, file_path: &str) -> Result<(), ZycleError> {
+    let mut compressed_bytes = Vec::new();
+    item.serialize_compressed(&mut compressed_bytes)?;
+
+    let mut file: File = std::fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(true)
+        .open(file_path)?;
+    file.write_all(&compressed_bytes)?;
+    file.flush()?;
+    Ok(())
+}
+
+// Reads back an object written by write_compressed_to_file
+pub fn read_compressed_from_file<T: CanonicalDeserialize>(file_path: &str) -> Result<T, ZycleError> {
+    // Open and read the file
+    let mut file = File::open(file_path)?;
+    let mut buffer = Vec::new();
+    file.read_to_end(&mut buffer)?;
+
+    // Deserialize the object from the buffer
+    Ok(T::deserialize_compressed(&mut buffer.as_slice())?)
+}
+
+#[test]
+fn prove_and_verify_roundtrip() {
+    use ark_bls12_381::fr::Fr;
+    use ark_std::{rand::SeedableRng, test_rng, Zero};
+
+    let adj_matrix = [
+        [false, true, true, false],   //               [0]
+        [false, false, true, false],  //               / \
+        [false, false, false, true],  //             [1]->[2] -> 3
+        [false, false, false, false], //
+    ];
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = AcyclicityProver::setup(4, &mut rng).unwrap();
+    let (proof, public_inputs) = prover.prove(&adj_matrix, &mut rng).unwrap();
+
+    assert_eq!(public_inputs, vec![commitment(&adj_matrix).unwrap()]);
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+    assert!(!verifier.verify(&proof, &[Fr::zero()]).unwrap());
+
+    // an ordering that breaks the edge 0 -> 1 is refused rather than proven
+    let bad_sort = [1, 0, 2, 3];
+    let err = prover.prove_with_toposort(&adj_matrix, &bad_sort, &mut rng).unwrap_err();
+    assert!(matches!(err, ZycleError::Synthesis(SynthesisError::Unsatisfiable)));
+
+    // keys whose positions cannot number every node are refused
+    let err = AcyclicityProver::setup_with_width(300, PositionWidth::U8, &mut rng).err().unwrap();
+    assert!(matches!(err, ZycleError::InvalidGraph(_)));
+
+    // so is a graph of the wrong size
+    assert!(matches!(prover.prove(&[[false; 3]; 3], &mut rng), Err(ZycleError::InvalidGraph(_))));
+
+    // a cyclic graph is refused before any constraints are generated
+    let mut cyclic = adj_matrix;
+    cyclic[3][1] = true;
+    let err = prover.prove(&cyclic, &mut rng).unwrap_err();
+    match err {
+        ZycleError::CyclicGraph(e) => assert_eq!(e.cycle, vec![1, 2, 3]),
+        e => panic!("expected a cycle, got {}", e),
+    }
+}
+
+#[test]
+fn prove_and_verify_on_other_curves() {
+    use crate::bundle::ProofBundle;
+    use crate::keys::{read_key, write_key, KeyHeader};
+    use ark_bls12_377::Bls12_377;
+    use ark_bn254::Bn254;
+    use ark_std::{rand::{rngs::StdRng, SeedableRng}, test_rng};
+
+    fn roundtrip<E: ZycleCurve>() {
+        let adj_matrix = [[false, true, false], [false, false, true], [false, false, false]];
+        let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+        let (prover, verifier) = AcyclicityProverOn::<E>::setup(3, &mut rng).unwrap();
+        let (proof, public_inputs) = prover.prove(&adj_matrix, &mut rng).unwrap();
+        assert_eq!(public_inputs, vec![commitment(&adj_matrix).unwrap()]);
+        assert!(verifier.verify(&proof, &public_inputs).unwrap());
+        assert!(!verifier.verify(&proof, &[E::ScalarField::from(1u64)]).unwrap());
+
+        // keys and bundles record the curve, so they are refused on any other
+        let mut bytes = Vec::new();
+        write_key(verifier.verifying_key(), verifier.shape(), Compress::Yes, &mut bytes).unwrap();
+        assert_eq!(KeyHeader::read(bytes.as_slice()).unwrap().curve, E::CURVE_ID);
+        assert!(read_key::<VerifyingKey<Bls12_381>, _>(bytes.as_slice(), Validate::Yes).is_err());
+        let plain = CommitmentKind::Plain;
+        let bundle = ProofBundle::new(&proof, &public_inputs, 3, CircuitVariant::TopoSort, plain, None).unwrap();
+        assert_eq!(bundle.curve, E::CURVE_ID);
+        bundle.check_matches(&verifier).unwrap();
+        assert!(bundle.check_matches_circuit::<Bls12_381>(CircuitVariant::TopoSort, plain, 3).is_err());
+        assert!(verifier.verify(&bundle.proof().unwrap(), &bundle.public_inputs().unwrap()).unwrap());
+        assert!(bundle.proof::<Bls12_381>().is_err());
+    }
+
+    roundtrip::<Bn254>();
+    roundtrip::<Bls12_377>();
+}
@@ -0,0 +1,289 @@
+// Circuit proving that no cycle can be reached from one customer
+//
+// The subgraph variant of the dense circuit takes any set of nodes closed under outgoing edges,
+// which could just as well be empty. Here the start node is a public input and the set has to
+// contain it, and a set that contains the start node and that no edge leaves holds every node
+// reachable from it. A valid sort of that set then means "no cycle is reachable from the start node",
+// while cycles elsewhere in the graph are allowed
+
+use ark_bls12_381::Bls12_381;
+use ark_crypto_primitives::snark::{CircuitSpecificSetupSNARK, SNARK};
+use ark_ff::PrimeField;
+use ark_groth16::{prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey};
+use ark_r1cs_std::{alloc::AllocVar, boolean::Boolean, eq::EqGadget, fields::fp::FpVar};
+use ark_relations::r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError};
+use ark_serialize::{Compress, Validate};
+use ark_std::rand::{CryptoRng, RngCore};
+
+use crate::circuit::{check_subgraph_with_width, CircuitVariant, PositionWidth};
+use crate::curves::ZycleCurve;
+use crate::error::ZycleError;
+use crate::hashing::hasher_var_vec;
+use crate::keys::{read_key_from_file, read_verifying_key_from_file, write_key_to_file, CircuitShape};
+use crate::prover::{
+    check_matrix, check_width, commitment, matrix_rows, prove_checked, width_for_nodes, ProofWithInputs,
+};
+use crate::utils::{Boolean2DVec, BooleanVec};
+use crate::witness::{reachable_from, subgraph_toposort_witness};
+
+#[derive(Clone)]
+pub struct ReachabilityCircuit<ConstraintF: PrimeField> {
+    pub adj_matrix: Vec<Vec<bool>>,
+    // the nodes reachable from start, any larger set no edge leaves works too
+    pub subgraph_nodes: Vec<bool>,
+    pub toposort: Vec<u32>,
+    pub position_width: PositionWidth,
+    pub start: u32,
+    pub adj_hash: ConstraintF,
+}
+
+impl<ConstraintF: PrimeField> ReachabilityCircuit<ConstraintF> {
+    // placeholder inputs used when only the circuit shape matters (e.g. during setup)
+    pub fn blank(nodes: usize, position_width: PositionWidth) -> Self {
+        Self {
+            adj_matrix: vec![vec![false; nodes]; nodes],
+            subgraph_nodes: vec![true; nodes],
+            toposort: (0..nodes as u32).collect(),
+            position_width,
+            start: 0,
+            adj_hash: ConstraintF::zero(),
+        }
+    }
+}
+
+impl<ConstraintF: PrimeField> ConstraintSynthesizer<ConstraintF> for ReachabilityCircuit<ConstraintF> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<ConstraintF>) -> Result<(), SynthesisError> {
+        // public inputs are the hash of the graph, then the start node
+        let hash_claim_var = FpVar::new_input(cs.clone(), || Ok(self.adj_hash))?;
+        let start_var = FpVar::new_input(cs.clone(), || Ok(ConstraintF::from(self.start)))?;
+        let adj_matrix_var = Boolean2DVec::new_witness(cs.clone(), || Ok(self.adj_matrix))?;
+        let subgraph_var = BooleanVec::new_witness(cs.clone(), || Ok(self.subgraph_nodes))?;
+
+        hasher_var_vec(cs.clone(), &adj_matrix_var)?[0].enforce_equal(&hash_claim_var)?;
+
+        // the start node has to be one of the subgraph nodes, which also rules out a start past the last node
+        let mut contains_start = Boolean::FALSE;
+        for (i, in_subgraph) in subgraph_var.0.iter().enumerate() {
+            let is_start = start_var.is_eq(&FpVar::Constant(ConstraintF::from(i as u64)))?;
+            contains_start = contains_start.or(&is_start.and(in_subgraph)?)?;
+        }
+        contains_start.enforce_equal(&Boolean::TRUE)?;
+
+        // no edge leaves the subgraph and the edges inside it go forwards
+        check_subgraph_with_width(cs, &adj_matrix_var, &subgraph_var, &self.toposort, self.position_width)
+    }
+}
+
+// Holds the Groth16 proving key for reachability proofs on graphs with a fixed number of nodes, on the curve E
+pub struct ReachabilityProverOn<E: ZycleCurve> {
+    nodes: usize,
+    position_width: PositionWidth,
+    pk: ProvingKey<E>,
+}
+
+pub struct ReachabilityVerifierOn<E: ZycleCurve> {
+    nodes: usize,
+    position_width: PositionWidth,
+    vk: VerifyingKey<E>,
+    pvk: PreparedVerifyingKey<E>,
+}
+
+// the prover and verifier on the default curve
+pub type ReachabilityProver = ReachabilityProverOn<Bls12_381>;
+pub type ReachabilityVerifier = ReachabilityVerifierOn<Bls12_381>;
+
+impl<E: ZycleCurve> ReachabilityProverOn<E> {
+    // positions get the smallest unsigned width that fits, see setup_with_width to choose it
+    pub fn setup<R: RngCore + CryptoRng>(
+        nodes: usize,
+        rng: &mut R,
+    ) -> Result<(Self, ReachabilityVerifierOn<E>), ZycleError> {
+        Self::setup_with_width(nodes, width_for_nodes(nodes)?, rng)
+    }
+
+    pub fn setup_with_width<R: RngCore + CryptoRng>(
+        nodes: usize,
+        position_width: PositionWidth,
+        rng: &mut R,
+    ) -> Result<(Self, ReachabilityVerifierOn<E>), ZycleError> {
+        check_width(nodes, position_width)?;
+        let circuit = ReachabilityCircuit::<E::ScalarField>::blank(nodes, position_width);
+        let (pk, vk) = Groth16::<E>::setup(circuit, rng)?;
+        Ok((
+            Self { nodes, position_width, pk },
+            ReachabilityVerifierOn::new(vk, nodes, position_width),
+        ))
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn position_width(&self) -> PositionWidth {
+        self.position_width
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<E> {
+        &self.pk
+    }
+
+    fn shape(&self) -> CircuitShape {
+        CircuitShape::reachability(self.nodes, self.position_width)
+    }
+
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let (header, pk) = read_key_from_file(file_path, validate)?;
+        header.expect_variant(CircuitVariant::Reachability)?;
+        Ok(Self {
+            nodes: header.nodes,
+            position_width: header.position_width,
+            pk,
+        })
+    }
+
+    pub fn write_proving_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.pk, self.shape(), compress, file_path)
+    }
+
+    // proves that no cycle is reachable from start, working out the subgraph and its sort itself
+    // fails with ZycleError::CyclicGraph naming a cycle if one is reachable
+    pub fn prove<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        start: usize,
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        check_matrix(adj_matrix, self.nodes)?;
+        if start >= adj_matrix.len() {
+            return Err(bad_start(start, adj_matrix.len()));
+        }
+        let subgraph_nodes = reachable_from(adj_matrix, start)?;
+        let toposort = subgraph_toposort_witness(adj_matrix, &subgraph_nodes)?;
+        self.prove_with_witness(adj_matrix, start, &subgraph_nodes, &toposort, rng)
+    }
+
+    // returns the proof and its public inputs, the hash of adj_matrix and the start node
+    // fails with ZycleError::Synthesis(SynthesisError::Unsatisfiable) if the subgraph misses start, is left by an edge
+    // or is not sorted by toposort
+    pub fn prove_with_witness<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        start: usize,
+        subgraph_nodes: &[bool],
+        toposort: &[u32],
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        let adj_matrix = matrix_rows(adj_matrix, self.nodes)?;
+        if start >= self.nodes {
+            return Err(bad_start(start, self.nodes));
+        }
+        if subgraph_nodes.len() != self.nodes || toposort.len() != self.nodes {
+            return Err(ZycleError::InvalidGraph(format!(
+                "subgraph and topological sort need {} entries each",
+                self.nodes
+            )));
+        }
+        let adj_hash = commitment(&adj_matrix)?;
+        let circuit = ReachabilityCircuit {
+            adj_matrix,
+            subgraph_nodes: subgraph_nodes.to_vec(),
+            toposort: toposort.to_vec(),
+            position_width: self.position_width,
+            start: start as u32,
+            adj_hash,
+        };
+        let proof = prove_checked(&self.pk, circuit, rng)?;
+        Ok((proof, reachability_inputs(adj_hash, start)))
+    }
+}
+
+impl<E: ZycleCurve> ReachabilityVerifierOn<E> {
+    pub fn new(vk: VerifyingKey<E>, nodes: usize, position_width: PositionWidth) -> Self {
+        let pvk = prepare_verifying_key::<E>(&vk);
+        Self { nodes, position_width, vk, pvk }
+    }
+
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let (header, pvk) = read_verifying_key_from_file(file_path, CircuitVariant::Reachability, validate)?;
+        Ok(Self { nodes: header.nodes, position_width: header.position_width, vk: pvk.vk.clone(), pvk })
+    }
+
+    pub fn write_verifying_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.vk, self.shape(), compress, file_path)
+    }
+
+    pub fn write_prepared_verifying_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.pvk, self.shape(), compress, file_path)
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    fn shape(&self) -> CircuitShape {
+        CircuitShape::reachability(self.nodes, self.position_width)
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+
+    pub fn prepared_verifying_key(&self) -> &PreparedVerifyingKey<E> {
+        &self.pvk
+    }
+
+    // checks the proof against reachability_inputs of the graph commitment and start node
+    pub fn verify(&self, proof: &Proof<E>, public_inputs: &[E::ScalarField]) -> Result<bool, ZycleError> {
+        Ok(Groth16::<E>::verify_with_processed_vk(&self.pvk, public_inputs, proof)?)
+    }
+}
+
+// the public inputs a reachability proof is checked against
+pub fn reachability_inputs<F: PrimeField>(commitment: F, start: usize) -> Vec<F> {
+    vec![commitment, F::from(start as u64)]
+}
+
+fn bad_start(start: usize, nodes: usize) -> ZycleError {
+    ZycleError::InvalidGraph(format!("start node {} is not one of the {} nodes", start, nodes))
+}
+
+#[test]
+fn reachability_prove_and_verify() {
+    use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+
+    let adj_matrix = [
+        [false, true, false, false], //   [0] -> [1]    [2] <-> [3]
+        [false, false, false, false],
+        [false, false, false, true],
+        [false, false, true, false],
+    ];
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = ReachabilityProver::setup(4, &mut rng).unwrap();
+
+    // the cycle between 2 and 3 cannot be reached from 0
+    let (proof, public_inputs) = prover.prove(&adj_matrix, 0, &mut rng).unwrap();
+    let hash = commitment(&adj_matrix).unwrap();
+    assert_eq!(public_inputs, reachability_inputs(hash, 0));
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+    // the proof says nothing about other customers
+    assert!(!verifier.verify(&proof, &reachability_inputs(hash, 1)).unwrap());
+
+    // but it can from 2
+    let err = prover.prove(&adj_matrix, 2, &mut rng).unwrap_err();
+    assert!(matches!(err, ZycleError::CyclicGraph(e) if e.cycle == vec![2, 3]));
+    assert!(matches!(prover.prove(&adj_matrix, 4, &mut rng), Err(ZycleError::InvalidGraph(_))));
+
+    // a subgraph has to contain the start node and no edge may leave it
+    let toposort = [0, 1, 2, 3];
+    for subgraph in [[false, true, false, false], [true, false, false, false]] {
+        let err = prover.prove_with_witness(&adj_matrix, 0, &subgraph, &toposort, &mut rng).unwrap_err();
+        assert!(matches!(err, ZycleError::Synthesis(SynthesisError::Unsatisfiable)));
+    }
+
+    // and on another curve
+    let (prover, verifier) = ReachabilityProverOn::<ark_bls12_377::Bls12_377>::setup(4, &mut rng).unwrap();
+    let (proof, public_inputs) = prover.prove(&adj_matrix, 0, &mut rng).unwrap();
+    assert_eq!(public_inputs, reachability_inputs(commitment(&adj_matrix).unwrap(), 0));
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+}
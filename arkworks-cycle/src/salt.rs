@@ -0,0 +1,87 @@
+use ark_crypto_primitives::sponge::Absorb;
+use ark_ff::PrimeField;
+use ark_r1cs_std::alloc::AllocVar;
+use ark_relations::r1cs::{ConstraintSystem, SynthesisError};
+use ark_std::{
+    fs, io,
+    rand::{CryptoRng, RngCore},
+};
+
+use crate::bundle::field_to_string;
+use crate::hashing::salted_hasher_vec;
+use crate::utils::Boolean2DVec;
+
+// Salted commitments: Poseidon(salt || flattened matrix) with a secret salt. Publishing the
+// plain commitment lets anyone test guesses of the graph against it, the salt stops that as
+// long as it stays secret. Keep the salt to open the commitment later.
+
+// a fresh salt, uniform in the scalar field
+pub fn generate_salt<F: PrimeField, R: RngCore + CryptoRng>(rng: &mut R) -> F {
+    F::rand(rng)
+}
+
+// the public input of a salted proof, computed natively
+pub fn salted_commitment<F: PrimeField + Absorb, Row: AsRef<[bool]>>(
+    adj_matrix: &[Row],
+    salt: F,
+) -> Result<F, SynthesisError> {
+    let cs = ConstraintSystem::<F>::new_ref();
+    let adj_rows: Vec<Vec<bool>> = adj_matrix.iter().map(|row| row.as_ref().to_vec()).collect();
+    let adj_matrix_var = Boolean2DVec::new_witness(cs, || Ok(adj_rows))?;
+    Ok(salted_hasher_vec(&adj_matrix_var, salt)?[0])
+}
+
+// checks that a revealed graph and salt match a commitment published earlier
+pub fn open_commitment<F: PrimeField + Absorb, Row: AsRef<[bool]>>(
+    adj_matrix: &[Row],
+    salt: F,
+    commitment: F,
+) -> Result<bool, SynthesisError> {
+    Ok(salted_commitment(adj_matrix, salt)? == commitment)
+}
+
+// the salt is stored as a decimal field element on a single line
+pub fn write_salt_to_file<F: PrimeField>(salt: &F, file_path: &str) -> Result<(), io::Error> {
+    fs::write(file_path, format!("{}\n", field_to_string(salt)))
+}
+
+pub fn read_salt_from_file<F: PrimeField>(file_path: &str) -> Result<F, io::Error> {
+    let text = fs::read_to_string(file_path)?;
+    F::from_str(text.trim()).map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidData, format!("{} does not hold a field element", file_path))
+    })
+}
+
+#[test]
+fn salted_prove_and_open() {
+    use crate::circuit::PositionWidth;
+    use crate::prover::{commitment, AcyclicityProver};
+    use ark_bls12_381::fr::Fr;
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use ark_std::test_rng;
+
+    let adj_matrix = [[false, true, false], [false, false, true], [false, false, false]];
+    let other = [[false, false, false], [true, false, false], [false, true, false]];
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let salt: Fr = generate_salt(&mut rng);
+    let hidden = salted_commitment(&adj_matrix, salt).unwrap();
+    assert_ne!(hidden, commitment(&adj_matrix).unwrap());
+    assert_ne!(hidden, salted_commitment(&adj_matrix, generate_salt::<Fr, _>(&mut rng)).unwrap());
+    assert!(open_commitment(&adj_matrix, salt, hidden).unwrap());
+    assert!(!open_commitment(&other, salt, hidden).unwrap());
+
+    let (prover, verifier) = AcyclicityProver::setup_salted(3, PositionWidth::U8, &mut rng).unwrap();
+    assert!(verifier.is_salted());
+    let (proof, public_inputs) = prover.prove_salted(&adj_matrix, salt, &mut rng).unwrap();
+    assert_eq!(public_inputs, vec![hidden]);
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+    assert!(!verifier.verify(&proof, &[commitment(&adj_matrix).unwrap()]).unwrap());
+    // salted keys need a salt and plain keys refuse one
+    assert!(prover.prove(&adj_matrix, &mut rng).is_err());
+
+    let path = std::env::temp_dir().join(format!("zycle-salt-{}", rng.next_u64()));
+    let path = path.to_str().unwrap();
+    write_salt_to_file(&salt, path).unwrap();
+    assert_eq!(read_salt_from_file::<Fr>(path).unwrap(), salt);
+    fs::remove_file(path).unwrap();
+}
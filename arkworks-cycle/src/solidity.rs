@@ -0,0 +1,156 @@
+use ark_bn254::{Bn254, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::AffineRepr;
+use ark_ff::{BigInteger, PrimeField};
+use ark_groth16::{Proof, VerifyingKey};
+
+use crate::bundle::field_to_string;
+use crate::error::ZycleError;
+
+// Groth16 verifiers for BN254 keys running on Ethereum, through the ecAdd (0x06), ecMul (0x07) and
+// ecPairing (0x08) precompiles, called from inline assembly. The contract takes the proof and the
+// commitment as nine flat uint256 arguments, the same calldata layout as uint256[2]/uint256[2][2] arrays.
+
+// first 4 bytes of keccak256 of the signature below
+pub const VERIFY_PROOF_SELECTOR: [u8; 4] = [0x97, 0x30, 0x85, 0x66];
+pub const VERIFY_PROOF_SIGNATURE: &str =
+    "verifyProof(uint256,uint256,uint256,uint256,uint256,uint256,uint256,uint256,uint256)";
+
+pub const CONTRACT_NAME: &str = "ZycleVerifier";
+
+const CONTRACT_BODY: &str = r#"
+    function verifyProof(
+        uint256 aX,
+        uint256 aY,
+        uint256 bX1,
+        uint256 bX0,
+        uint256 bY1,
+        uint256 bY0,
+        uint256 cX,
+        uint256 cY,
+        uint256 commitment
+    ) public view returns (bool) {
+        if (commitment >= R || aY >= Q) {
+            return false;
+        }
+        bool ok;
+        uint256 result;
+        assembly {
+            let p := mload(0x40)
+            // vk_x = IC0 + commitment * IC1, written where the third pair starts
+            mstore(add(p, 384), IC1_X)
+            mstore(add(p, 416), IC1_Y)
+            mstore(add(p, 448), commitment)
+            ok := staticcall(gas(), 7, add(p, 384), 96, add(p, 384), 64)
+            mstore(add(p, 448), IC0_X)
+            mstore(add(p, 480), IC0_Y)
+            ok := and(ok, staticcall(gas(), 6, add(p, 384), 128, add(p, 384), 64))
+
+            // e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1
+            mstore(p, aX)
+            mstore(add(p, 32), mod(sub(Q, aY), Q))
+            mstore(add(p, 64), bX1)
+            mstore(add(p, 96), bX0)
+            mstore(add(p, 128), bY1)
+            mstore(add(p, 160), bY0)
+            mstore(add(p, 192), ALPHA_X)
+            mstore(add(p, 224), ALPHA_Y)
+            mstore(add(p, 256), BETA_X1)
+            mstore(add(p, 288), BETA_X0)
+            mstore(add(p, 320), BETA_Y1)
+            mstore(add(p, 352), BETA_Y0)
+            mstore(add(p, 448), GAMMA_X1)
+            mstore(add(p, 480), GAMMA_X0)
+            mstore(add(p, 512), GAMMA_Y1)
+            mstore(add(p, 544), GAMMA_Y0)
+            mstore(add(p, 576), cX)
+            mstore(add(p, 608), cY)
+            mstore(add(p, 640), DELTA_X1)
+            mstore(add(p, 672), DELTA_X0)
+            mstore(add(p, 704), DELTA_Y1)
+            mstore(add(p, 736), DELTA_Y0)
+            // points off the curve make the precompiles fail, which rejects the proof
+            ok := and(ok, staticcall(gas(), 8, p, 768, p, 32))
+            result := mload(p)
+        }
+        return ok && result == 1;
+    }
+}
+"#;
+
+// a self-contained Solidity contract verifying proofs against the key, which must have the
+// commitment as its only public input
+pub fn solidity_verifier(vk: &VerifyingKey<Bn254>) -> Result<String, ZycleError> {
+    if vk.gamma_abc_g1.len() != 2 {
+        return Err(ZycleError::KeyMismatch(format!(
+            "the Solidity verifier takes the commitment as its only public input, the key has {}",
+            vk.gamma_abc_g1.len().saturating_sub(1)
+        )));
+    }
+    let mut constants = vec![
+        ("Q".to_string(), Fq::MODULUS.to_string()),
+        ("R".to_string(), Fr::MODULUS.to_string()),
+    ];
+    constants.extend(g1_constants("ALPHA", &vk.alpha_g1));
+    constants.extend(g2_constants("BETA", &vk.beta_g2));
+    constants.extend(g2_constants("GAMMA", &vk.gamma_g2));
+    constants.extend(g2_constants("DELTA", &vk.delta_g2));
+    constants.extend(g1_constants("IC0", &vk.gamma_abc_g1[0]));
+    constants.extend(g1_constants("IC1", &vk.gamma_abc_g1[1]));
+
+    let mut contract = String::from("// SPDX-License-Identifier: MIT\n");
+    contract.push_str("// Generated by zycle from a Groth16 verifying key\n");
+    contract.push_str("pragma solidity ^0.8.4;\n\n");
+    contract.push_str(&format!("contract {} {{\n", CONTRACT_NAME));
+    for (name, value) in &constants {
+        contract.push_str(&format!("    uint256 constant {} = {};\n", name, value));
+    }
+    contract.push_str(CONTRACT_BODY);
+    Ok(contract)
+}
+
+// the calldata of a verifyProof call checking the proof against the commitment
+pub fn verifier_calldata(proof: &Proof<Bn254>, commitment: Fr) -> Vec<u8> {
+    let mut words = g1_words(&proof.a).to_vec();
+    words.extend(g2_words(&proof.b));
+    words.extend(g1_words(&proof.c));
+    let mut calldata = VERIFY_PROOF_SELECTOR.to_vec();
+    for word in words {
+        calldata.extend(word.into_bigint().to_bytes_be());
+    }
+    calldata.extend(commitment.into_bigint().to_bytes_be());
+    calldata
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    let digits: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("0x{}", digits)
+}
+
+// the point at infinity is (0, 0) on Ethereum
+fn g1_words(point: &G1Affine) -> [Fq; 2] {
+    match point.xy() {
+        Some((x, y)) => [*x, *y],
+        None => [Fq::from(0u64); 2],
+    }
+}
+
+// Ethereum puts the imaginary part of G2 coordinates first
+fn g2_words(point: &G2Affine) -> [Fq; 4] {
+    let zero = Fq2::from(0u64);
+    let (x, y) = point.xy().unwrap_or((&zero, &zero));
+    [x.c1, x.c0, y.c1, y.c0]
+}
+
+fn g1_constants(name: &str, point: &G1Affine) -> Vec<(String, String)> {
+    let [x, y] = g1_words(point);
+    vec![(format!("{}_X", name), field_to_string(&x)), (format!("{}_Y", name), field_to_string(&y))]
+}
+
+fn g2_constants(name: &str, point: &G2Affine) -> Vec<(String, String)> {
+    let [x1, x0, y1, y0] = g2_words(point);
+    ["X1", "X0", "Y1", "Y0"]
+        .iter()
+        .zip([x1, x0, y1, y0])
+        .map(|(coordinate, value)| (format!("{}_{}", name, coordinate), field_to_string(&value)))
+        .collect()
+}
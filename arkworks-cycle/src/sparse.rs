@@ -0,0 +1,547 @@
+// Circuit for large sparse graphs, proving acyclicity from a committed edge list
+//
+// The dense circuit compares the positions of every pair of nodes and hashes all n^2 matrix bits. Here the
+// prover instead gives every node a position and every edge the positions of its two ends, and
+// the circuit checks
+//   - each edge goes from a lower position to a higher one, so no cycle can exist
+//     (positions do not need to be distinct for that, so there is no permutation check)
+//   - the positions given per edge are the positions of its nodes, with a logUp lookup argument:
+//     sum over edge ends 1 / (alpha - (node + beta * position))
+//       == sum over nodes multiplicity / (alpha - (node + beta * position))
+//     where alpha and beta are squeezed from a Poseidon hash of everything the prover chose
+// so the constraints grow with nodes + edges instead of nodes^2
+
+use ark_bls12_381::Bls12_381;
+use ark_crypto_primitives::snark::{CircuitSpecificSetupSNARK, SNARK};
+use ark_crypto_primitives::sponge::{
+    poseidon::PoseidonSponge, Absorb, CryptographicSponge, FieldBasedCryptographicSponge,
+};
+use ark_ff::PrimeField;
+use ark_groth16::{
+    prepare_verifying_key, Groth16, PreparedVerifyingKey, Proof, ProvingKey, VerifyingKey,
+};
+use ark_r1cs_std::{
+    alloc::AllocVar,
+    boolean::Boolean,
+    eq::EqGadget,
+    fields::{fp::FpVar, FieldVar},
+};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystemRef, SynthesisError,
+};
+use ark_serialize::{Compress, Validate};
+use ark_std::rand::{CryptoRng, RngCore};
+
+use crate::circuit::{CircuitVariant, PositionWidth};
+use crate::curves::ZycleCurve;
+use crate::error::ZycleError;
+use crate::hashing::hashing_utils::{CryptographicSpongeVar, PoseidonSpongeVar};
+use crate::hashing::poseidon_parameters;
+use crate::keys::{
+    read_key_from_file, read_verifying_key_from_file, write_key_to_file, CircuitShape,
+};
+use crate::utils::{BoundedFpVar, CmpGadget};
+use crate::prover::{check_matrix, prove_checked, ProofWithInputs};
+use crate::witness::topological_positions_from_edges;
+
+// A graph as a list of (sender, receiver) edges between nodes 0..nodes
+// edges are kept sorted and without repeats, so the commitment only depends on the graph
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparseGraph {
+    pub nodes: usize,
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl SparseGraph {
+    pub fn new(
+        nodes: usize,
+        edges: impl IntoIterator<Item = (usize, usize)>,
+    ) -> Result<Self, ZycleError> {
+        let mut edges: Vec<(usize, usize)> = edges.into_iter().collect();
+        if let Some(&(sender, receiver)) = edges.iter().find(|&&(s, r)| s >= nodes || r >= nodes) {
+            return Err(ZycleError::InvalidGraph(format!(
+                "edge {} -> {} is outside a graph with {} nodes",
+                sender, receiver, nodes
+            )));
+        }
+        edges.sort_unstable();
+        edges.dedup();
+        Ok(Self { nodes, edges })
+    }
+
+    // fails with ZycleError::InvalidGraph if adj_matrix is not square
+    pub fn from_adjacency_matrix<Row: AsRef<[bool]>>(adj_matrix: &[Row]) -> Result<Self, ZycleError> {
+        check_matrix(adj_matrix, adj_matrix.len())?;
+        let edges = adj_matrix.iter().enumerate().flat_map(|(i, row)| {
+            row.as_ref()
+                .iter()
+                .enumerate()
+                .filter(|(_, &transacted)| transacted)
+                .map(move |(j, _)| (i, j))
+        });
+        Ok(Self {
+            nodes: adj_matrix.len(),
+            edges: edges.collect(),
+        })
+    }
+
+    // a position for every node that puts each edge's sender before its receiver
+    pub fn positions(&self) -> Result<Vec<u32>, ZycleError> {
+        Ok(
+            topological_positions_from_edges(self.nodes, self.edges.iter().copied())?
+                .into_iter()
+                .map(|pos| pos as u32)
+                .collect(),
+        )
+    }
+}
+
+// bits needed to give each of `nodes` nodes its own position
+pub fn position_bits(nodes: usize) -> usize {
+    (usize::BITS - nodes.saturating_sub(1).leading_zeros()).max(1) as usize
+}
+
+// Poseidon hash of the edge list padded to max_edges, as (sender, receiver, is_real_edge) triples
+pub fn edge_commitment<F: PrimeField + Absorb>(graph: &SparseGraph, max_edges: usize) -> Result<F, ZycleError> {
+    if graph.edges.len() > max_edges {
+        return Err(too_many_edges(graph.edges.len(), max_edges));
+    }
+    let mut elements = Vec::with_capacity(3 * max_edges);
+    for k in 0..max_edges {
+        let (sender, receiver, real) = match graph.edges.get(k) {
+            Some(&(sender, receiver)) => (sender as u64, receiver as u64, true),
+            None => (0, 0, false),
+        };
+        elements.extend([F::from(sender), F::from(receiver), F::from(real)]);
+    }
+    let mut sponge = PoseidonSponge::<F>::new(&poseidon_parameters());
+    sponge.absorb(&elements);
+    Ok(sponge.squeeze_native_field_elements(1)[0])
+}
+
+// inputs to the sparse circuit, the shape is fixed by nodes and max_edges
+#[derive(Clone)]
+pub struct SparseGraphCircuit<ConstraintF: PrimeField> {
+    pub nodes: usize,
+    pub max_edges: usize,
+    pub edges: Vec<(usize, usize)>,
+    pub positions: Vec<u32>,
+    pub edge_hash: ConstraintF,
+}
+
+impl<ConstraintF: PrimeField> SparseGraphCircuit<ConstraintF> {
+    // placeholder inputs used when only the circuit shape matters (e.g. during setup)
+    pub fn blank(nodes: usize, max_edges: usize) -> Self {
+        Self {
+            nodes,
+            max_edges,
+            edges: Vec::new(),
+            positions: vec![0; nodes],
+            edge_hash: ConstraintF::zero(),
+        }
+    }
+}
+
+impl<ConstraintF: PrimeField> ConstraintSynthesizer<ConstraintF>
+    for SparseGraphCircuit<ConstraintF>
+{
+    fn generate_constraints(
+        self,
+        cs: ConstraintSystemRef<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        if self.edges.len() > self.max_edges
+            || self.positions.len() != self.nodes
+            || self.nodes == 0
+            || self
+                .edges
+                .iter()
+                .any(|&(s, r)| s >= self.nodes || r >= self.nodes)
+        {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let bits = position_bits(self.nodes);
+        let field = |x: u64| ConstraintF::from(x);
+
+        // the committed edge list, padding edges run from node 0 to itself and are switched off
+        let mut senders = Vec::with_capacity(self.max_edges);
+        let mut receivers = Vec::with_capacity(self.max_edges);
+        let mut real_edges = Vec::with_capacity(self.max_edges);
+        let mut edge_elements = Vec::with_capacity(3 * self.max_edges);
+        for k in 0..self.max_edges {
+            let (sender, receiver, real) = match self.edges.get(k) {
+                Some(&(sender, receiver)) => (sender as u64, receiver as u64, true),
+                None => (0, 0, false),
+            };
+            let sender = FpVar::new_witness(cs.clone(), || Ok(field(sender)))?;
+            let receiver = FpVar::new_witness(cs.clone(), || Ok(field(receiver)))?;
+            let real = Boolean::new_witness(cs.clone(), || Ok(real))?;
+            edge_elements.extend([sender.clone(), receiver.clone(), FpVar::from(real.clone())]);
+            senders.push(sender);
+            receivers.push(receiver);
+            real_edges.push(real);
+        }
+        let edge_hash_var = FpVar::new_input(cs.clone(), || Ok(self.edge_hash))?;
+        let sponge_param = poseidon_parameters();
+        let mut sponge = PoseidonSpongeVar::<ConstraintF>::new(cs.clone(), &sponge_param);
+        sponge.absorb(&edge_elements)?;
+        sponge.squeeze_field_elements(1)?[0].enforce_equal(&edge_hash_var)?;
+
+        // the table of node positions, range checked so comparisons between them are sound
+        let positions = self
+            .positions
+            .iter()
+            .map(|&pos| {
+                BoundedFpVar::new_witness_with_bits(cs.clone(), || Ok(field(pos as u64)), bits)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the positions claimed for both ends of every edge, and how often each node is looked up
+        let position_of = |node: usize| self.positions.get(node).copied().unwrap_or(0) as u64;
+        let mut multiplicities = vec![0u64; self.nodes];
+        let mut sender_positions = Vec::with_capacity(self.max_edges);
+        let mut receiver_positions = Vec::with_capacity(self.max_edges);
+        for k in 0..self.max_edges {
+            let (sender, receiver) = self.edges.get(k).copied().unwrap_or((0, 0));
+            multiplicities[sender] += 1;
+            multiplicities[receiver] += 1;
+            // no range check needed, the lookup below makes these equal to range checked positions
+            let sender_position =
+                FpVar::new_witness(cs.clone(), || Ok(field(position_of(sender))))?;
+            let receiver_position =
+                FpVar::new_witness(cs.clone(), || Ok(field(position_of(receiver))))?;
+            sender_positions.push(BoundedFpVar {
+                value: sender_position,
+                bits,
+            });
+            receiver_positions.push(BoundedFpVar {
+                value: receiver_position,
+                bits,
+            });
+        }
+        let multiplicities = multiplicities
+            .into_iter()
+            .map(|m| FpVar::new_witness(cs.clone(), || Ok(field(m))))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // every edge has to go forwards, padding edges are exempt
+        for k in 0..self.max_edges {
+            let backwards = sender_positions[k].is_geq(&receiver_positions[k])?;
+            real_edges[k]
+                .and(&backwards)?
+                .enforce_equal(&Boolean::FALSE)?;
+        }
+
+        // challenges for the lookup, bound to the edge list and every value the prover picked
+        let mut transcript = vec![edge_hash_var];
+        transcript.extend(positions.iter().map(|pos| pos.value.clone()));
+        for k in 0..self.max_edges {
+            transcript.push(sender_positions[k].value.clone());
+            transcript.push(receiver_positions[k].value.clone());
+        }
+        transcript.extend(multiplicities.iter().cloned());
+        let mut sponge = PoseidonSpongeVar::<ConstraintF>::new(cs.clone(), &sponge_param);
+        sponge.absorb(&transcript)?;
+        let challenges = sponge.squeeze_field_elements(2)?;
+        let (alpha, beta) = (&challenges[0], &challenges[1]);
+
+        // logUp: every (node, position) looked up by an edge end is a row of the table
+        let mut looked_up = FpVar::zero();
+        for k in 0..self.max_edges {
+            looked_up += (alpha - (&senders[k] + beta * &sender_positions[k].value)).inverse()?;
+            looked_up +=
+                (alpha - (&receivers[k] + beta * &receiver_positions[k].value)).inverse()?;
+        }
+        let mut table = FpVar::zero();
+        for (node, (position, multiplicity)) in positions.iter().zip(&multiplicities).enumerate() {
+            let row = FpVar::constant(field(node as u64)) + beta * &position.value;
+            table += multiplicity * (alpha - row).inverse()?;
+        }
+        looked_up.enforce_equal(&table)?;
+
+        Ok(())
+    }
+}
+
+// Holds the Groth16 proving key of the sparse circuit for at most `nodes` nodes and `max_edges` edges,
+// on the curve E
+pub struct SparseProverOn<E: ZycleCurve> {
+    nodes: usize,
+    max_edges: usize,
+    pk: ProvingKey<E>,
+}
+
+// Holds the Groth16 verifying key (and its prepared form) of the sparse circuit
+pub struct SparseVerifierOn<E: ZycleCurve> {
+    nodes: usize,
+    max_edges: usize,
+    vk: VerifyingKey<E>,
+    pvk: PreparedVerifyingKey<E>,
+}
+
+// the prover and verifier on the default curve
+pub type SparseProver = SparseProverOn<Bls12_381>;
+pub type SparseVerifier = SparseVerifierOn<Bls12_381>;
+
+impl<E: ZycleCurve> SparseProverOn<E> {
+    pub fn setup<R: RngCore + CryptoRng>(
+        nodes: usize,
+        max_edges: usize,
+        rng: &mut R,
+    ) -> Result<(Self, SparseVerifierOn<E>), ZycleError> {
+        let circuit = SparseGraphCircuit::<E::ScalarField>::blank(nodes, max_edges);
+        let (pk, vk) = Groth16::<E>::setup(circuit, rng)?;
+        Ok((
+            Self {
+                nodes,
+                max_edges,
+                pk,
+            },
+            SparseVerifierOn::new(vk, nodes, max_edges),
+        ))
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn max_edges(&self) -> usize {
+        self.max_edges
+    }
+
+    pub fn proving_key(&self) -> &ProvingKey<E> {
+        &self.pk
+    }
+
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let (header, pk) = read_key_from_file(file_path, validate)?;
+        header.expect_variant(CircuitVariant::Sparse)?;
+        Ok(Self {
+            nodes: header.nodes,
+            max_edges: header.edges,
+            pk,
+        })
+    }
+
+    pub fn write_proving_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(
+            &self.pk,
+            sparse_shape(self.nodes, self.max_edges),
+            compress,
+            file_path,
+        )
+    }
+
+    // proves that the graph has no cycles, working out the positions itself
+    // the graph can have fewer nodes than the key, the missing ones are simply never used
+    pub fn prove<R: RngCore + CryptoRng>(
+        &self,
+        graph: &SparseGraph,
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        let positions = graph.positions()?;
+        self.prove_with_positions(graph, &positions, rng)
+    }
+
+    // fails with ZycleError::Synthesis(SynthesisError::Unsatisfiable) if some edge does not go
+    // from a lower to a higher position
+    pub fn prove_with_positions<R: RngCore + CryptoRng>(
+        &self,
+        graph: &SparseGraph,
+        positions: &[u32],
+        rng: &mut R,
+    ) -> Result<ProofWithInputs<E>, ZycleError> {
+        if graph.nodes > self.nodes {
+            return Err(ZycleError::InvalidGraph(format!(
+                "graph has {} nodes, but the key is for at most {} nodes",
+                graph.nodes, self.nodes
+            )));
+        }
+        if positions.len() != graph.nodes {
+            return Err(ZycleError::InvalidGraph(format!(
+                "got {} positions for {} nodes",
+                positions.len(),
+                graph.nodes
+            )));
+        }
+        let edge_hash = edge_commitment(graph, self.max_edges)?;
+        let mut padded_positions = positions.to_vec();
+        padded_positions.resize(self.nodes, 0);
+        let circuit = SparseGraphCircuit {
+            nodes: self.nodes,
+            max_edges: self.max_edges,
+            edges: graph.edges.clone(),
+            positions: padded_positions,
+            edge_hash,
+        };
+        let proof = prove_checked(&self.pk, circuit, rng)?;
+        Ok((proof, vec![edge_hash]))
+    }
+}
+
+impl<E: ZycleCurve> SparseVerifierOn<E> {
+    pub fn new(vk: VerifyingKey<E>, nodes: usize, max_edges: usize) -> Self {
+        let pvk = prepare_verifying_key::<E>(&vk);
+        Self {
+            nodes,
+            max_edges,
+            vk,
+            pvk,
+        }
+    }
+
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let (header, pvk) = read_verifying_key_from_file(file_path, CircuitVariant::Sparse, validate)?;
+        Ok(Self {
+            nodes: header.nodes,
+            max_edges: header.edges,
+            vk: pvk.vk.clone(),
+            pvk,
+        })
+    }
+
+    pub fn write_verifying_key(
+        &self,
+        file_path: &str,
+        compress: Compress,
+    ) -> Result<(), ZycleError> {
+        write_key_to_file(
+            &self.vk,
+            sparse_shape(self.nodes, self.max_edges),
+            compress,
+            file_path,
+        )
+    }
+
+    pub fn write_prepared_verifying_key(
+        &self,
+        file_path: &str,
+        compress: Compress,
+    ) -> Result<(), ZycleError> {
+        write_key_to_file(
+            &self.pvk,
+            sparse_shape(self.nodes, self.max_edges),
+            compress,
+            file_path,
+        )
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn max_edges(&self) -> usize {
+        self.max_edges
+    }
+
+    pub fn verifying_key(&self) -> &VerifyingKey<E> {
+        &self.vk
+    }
+
+    pub fn verify(
+        &self,
+        proof: &Proof<E>,
+        public_inputs: &[E::ScalarField],
+    ) -> Result<bool, ZycleError> {
+        Ok(Groth16::<E>::verify_with_processed_vk(
+            &self.pvk,
+            public_inputs,
+            proof,
+        )?)
+    }
+}
+
+fn sparse_shape(nodes: usize, max_edges: usize) -> CircuitShape {
+    CircuitShape::sparse(nodes, max_edges, PositionWidth::Field(position_bits(nodes)))
+}
+
+fn too_many_edges(edges: usize, max_edges: usize) -> ZycleError {
+    ZycleError::InvalidGraph(format!(
+        "graph has {} edges, but the circuit takes at most {}",
+        edges, max_edges
+    ))
+}
+
+#[test]
+fn sparse_circuit_checks_every_edge() {
+    use ark_bls12_381::Fr;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let graph = SparseGraph::new(5, [(0, 1), (1, 2), (0, 2), (3, 4), (0, 1)]).unwrap();
+    assert_eq!(graph.edges.len(), 4);
+    let positions = graph.positions().unwrap();
+
+    let satisfied = |positions: &[u32], max_edges: usize| {
+        let circuit = SparseGraphCircuit {
+            nodes: 6,
+            max_edges,
+            edges: graph.edges.clone(),
+            positions: positions.iter().copied().chain([0]).collect(),
+            edge_hash: edge_commitment(&graph, max_edges).unwrap(),
+        };
+        let cs = ConstraintSystem::<Fr>::new_ref();
+        circuit.generate_constraints(cs.clone()).unwrap();
+        cs.is_satisfied().unwrap()
+    };
+    assert!(satisfied(&positions, 4));
+    assert!(satisfied(&positions, 7));
+    // positions may repeat, as long as every edge goes forwards
+    assert!(satisfied(&[0, 1, 2, 0, 1], 6));
+    assert!(!satisfied(&[0, 2, 1, 0, 1], 6));
+    assert!(!satisfied(&[0, 1, 2, 1, 1], 6));
+
+    // the commitment covers the padding as well
+    let circuit = SparseGraphCircuit {
+        nodes: 6,
+        max_edges: 5,
+        edges: graph.edges.clone(),
+        positions: vec![0; 6],
+        edge_hash: edge_commitment(&graph, 4).unwrap(),
+    };
+    let cs = ConstraintSystem::<Fr>::new_ref();
+    circuit.generate_constraints(cs.clone()).unwrap();
+    assert!(!cs.is_satisfied().unwrap());
+
+    assert!(SparseGraph::new(2, [(0, 2)]).is_err());
+    assert!(edge_commitment::<Fr>(&graph, 3).is_err());
+    let cyclic = SparseGraph::new(3, [(0, 1), (1, 2), (2, 0)]).unwrap();
+    assert!(matches!(cyclic.positions(), Err(ZycleError::CyclicGraph(e)) if e.cycle == vec![0, 1, 2]));
+    let ragged = [vec![false, true], vec![false]];
+    assert!(matches!(SparseGraph::from_adjacency_matrix(&ragged), Err(ZycleError::InvalidGraph(_))));
+}
+
+#[test]
+fn sparse_prove_and_verify() {
+    use ark_std::rand::{rngs::StdRng, SeedableRng};
+    use ark_std::test_rng;
+
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = SparseProver::setup(4, 4, &mut rng).unwrap();
+
+    let graph = SparseGraph::new(3, [(0, 1), (1, 2), (0, 2)]).unwrap();
+    let (proof, public_inputs) = prover.prove(&graph, &mut rng).unwrap();
+    assert_eq!(public_inputs, vec![edge_commitment(&graph, 4).unwrap()]);
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+
+    let other = SparseGraph::new(3, [(0, 1), (1, 2)]).unwrap();
+    assert!(!verifier
+        .verify(&proof, &[edge_commitment(&other, 4).unwrap()])
+        .unwrap());
+
+    let err = prover
+        .prove_with_positions(&graph, &[2, 1, 0], &mut rng)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ZycleError::Synthesis(SynthesisError::Unsatisfiable)
+    ));
+    assert!(matches!(
+        prover.prove(&SparseGraph::new(5, []).unwrap(), &mut rng),
+        Err(ZycleError::InvalidGraph(_))
+    ));
+
+    // the same circuit on another curve, committing with that curve's Poseidon parameters
+    let (prover, verifier) = SparseProverOn::<ark_bn254::Bn254>::setup(3, 2, &mut rng).unwrap();
+    let (proof, public_inputs) = prover.prove(&other, &mut rng).unwrap();
+    assert_eq!(public_inputs, vec![edge_commitment(&other, 2).unwrap()]);
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+}
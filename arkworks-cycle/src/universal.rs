@@ -0,0 +1,318 @@
+use ark_bls12_381::{fr::Fr, Bls12_381};
+use ark_crypto_primitives::sponge::poseidon::PoseidonSponge;
+use ark_poly::univariate::DensePolynomial;
+use ark_poly_commit::{kzg10::UniversalParams, marlin_pc::MarlinKZG10};
+use ark_serialize::{Compress, Validate};
+use ark_std::rand::{CryptoRng, RngCore};
+use blake2::Blake2s256;
+use rand::rngs::OsRng;
+use zycle_marlin::{self as marlin, ahp::AHPForR1CS, IndexProverKey, IndexVerifierKey, Marlin};
+
+use crate::circuit::{CircuitVariant, CommitmentKind, MyGraphCircuitStruct, PositionWidth};
+use crate::error::ZycleError;
+use crate::keys::{read_key_from_file, write_key_to_file, CircuitShape};
+use crate::prover::{check_matrix, check_satisfied, check_width, dense_circuit, width_for_nodes};
+use crate::witness::toposort_witness_vec;
+
+// Marlin over KZG commitments on the same curve as the Groth16 keys, its transcript is hashed
+// with blake2s; the sponge type only fills a slot in the commitment scheme's interface
+pub type MarlinPC = MarlinKZG10<Bls12_381, DensePolynomial<Fr>, PoseidonSponge<Fr>>;
+pub type MarlinBls12 = Marlin<Fr, PoseidonSponge<Fr>, MarlinPC, Blake2s256>;
+// spelled out rather than as UniversalSRS<..> so it can get its own ZycleKey impl
+pub type MarlinSrs = UniversalParams<Bls12_381>;
+pub type MarlinProvingKey = IndexProverKey<Fr, PoseidonSponge<Fr>, MarlinPC>;
+pub type MarlinVerifyingKey = IndexVerifierKey<Fr, PoseidonSponge<Fr>, MarlinPC>;
+pub type MarlinProof = marlin::Proof<Fr, PoseidonSponge<Fr>, MarlinPC>;
+
+// which proof system keys and proofs belong to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    // a trusted setup per circuit and graph size, smallest proofs
+    Groth16,
+    // keys for any circuit up to a size are derived from one universal SRS
+    Marlin,
+}
+
+impl Backend {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Backend::Groth16 => "groth16",
+            Backend::Marlin => "marlin",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "groth16" => Some(Backend::Groth16),
+            "marlin" => Some(Backend::Marlin),
+            _ => None,
+        }
+    }
+}
+
+// The universal parameters Marlin keys are derived from, one SRS covers the dense circuit for
+// every graph of up to max_nodes nodes, with any kind of commitment
+pub struct UniversalSrs {
+    max_nodes: usize,
+    srs: MarlinSrs,
+}
+
+impl UniversalSrs {
+    // the only trusted setup the Marlin backend needs, sized for the default position width
+    pub fn setup<R: RngCore + CryptoRng>(max_nodes: usize, rng: &mut R) -> Result<Self, ZycleError> {
+        let position_width = width_for_nodes(max_nodes)?;
+        let mut bounds = (0, 0, 0);
+        for commitment in [CommitmentKind::Plain, CommitmentKind::Salted, CommitmentKind::RowTree] {
+            let circuit = MyGraphCircuitStruct::<Fr>::blank_with_commitment(max_nodes, position_width, commitment);
+            let (constraints, variables, non_zero) = AHPForR1CS::index_bounds(circuit).map_err(marlin_error)?;
+            bounds = (bounds.0.max(constraints), bounds.1.max(variables), bounds.2.max(non_zero));
+        }
+        let srs = MarlinBls12::universal_setup(bounds.0, bounds.1, bounds.2, rng).map_err(marlin_error)?;
+        Ok(Self { max_nodes, srs })
+    }
+
+    // most nodes the graphs keys derived from this SRS can have
+    pub fn max_nodes(&self) -> usize {
+        self.max_nodes
+    }
+
+    pub fn srs(&self) -> &MarlinSrs {
+        &self.srs
+    }
+
+    fn shape(&self) -> CircuitShape {
+        let position_width = PositionWidth::for_nodes(self.max_nodes).unwrap_or(PositionWidth::U32);
+        CircuitShape::dense(self.max_nodes, position_width)
+    }
+
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let (header, srs) = read_key_from_file(file_path, validate)?;
+        header.expect_variant(CircuitVariant::TopoSort)?;
+        Ok(Self { max_nodes: header.nodes, srs })
+    }
+
+    pub fn write(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.srs, self.shape(), compress, file_path)
+    }
+}
+
+// Holds the Marlin proving key for graphs with a fixed number of nodes
+pub struct MarlinProver {
+    nodes: usize,
+    position_width: PositionWidth,
+    commitment: CommitmentKind,
+    pk: MarlinProvingKey,
+}
+
+// Holds the Marlin verifying key, the number of nodes is fixed by the key
+pub struct MarlinVerifier {
+    nodes: usize,
+    position_width: PositionWidth,
+    commitment: CommitmentKind,
+    vk: MarlinVerifyingKey,
+}
+
+impl MarlinProver {
+    // derives the keys for graphs with `nodes` nodes from the SRS, anyone can rerun this and
+    // get the same keys, so there is no per circuit trusted setup
+    pub fn index(
+        srs: &UniversalSrs,
+        nodes: usize,
+        position_width: PositionWidth,
+        commitment: CommitmentKind,
+    ) -> Result<(Self, MarlinVerifier), ZycleError> {
+        check_width(nodes, position_width)?;
+        if nodes > srs.max_nodes {
+            return Err(ZycleError::KeyMismatch(format!(
+                "the SRS is for graphs with at most {} nodes, got {}",
+                srs.max_nodes, nodes
+            )));
+        }
+        let circuit = MyGraphCircuitStruct::<Fr>::blank_with_commitment(nodes, position_width, commitment);
+        let (pk, vk) = MarlinBls12::index(&srs.srs, circuit).map_err(marlin_error)?;
+        let verifier = MarlinVerifier { nodes, position_width, commitment, vk };
+        Ok((Self { nodes, position_width, commitment, pk }, verifier))
+    }
+
+    pub fn proving_key(&self) -> &MarlinProvingKey {
+        &self.pk
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn position_width(&self) -> PositionWidth {
+        self.position_width
+    }
+
+    pub fn is_salted(&self) -> bool {
+        self.commitment.is_salted()
+    }
+
+    pub fn commitment(&self) -> CommitmentKind {
+        self.commitment
+    }
+
+    fn shape(&self) -> CircuitShape {
+        CircuitShape { commitment: self.commitment, ..CircuitShape::dense(self.nodes, self.position_width) }
+    }
+
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let (header, pk) = read_key_from_file(file_path, validate)?;
+        header.expect_variant(CircuitVariant::TopoSort)?;
+        Ok(Self { nodes: header.nodes, position_width: header.position_width, commitment: header.commitment, pk })
+    }
+
+    pub fn write_proving_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.pk, self.shape(), compress, file_path)
+    }
+
+    // same as AcyclicityProver::prove, with a Marlin proof
+    pub fn prove<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        rng: &mut R,
+    ) -> Result<(MarlinProof, Vec<Fr>), ZycleError> {
+        check_matrix(adj_matrix, self.nodes)?;
+        let topological_sort = toposort_witness_vec(adj_matrix)?;
+        self.prove_with_toposort(adj_matrix, &topological_sort, rng)
+    }
+
+    pub fn prove_with_toposort<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        topological_sort: &[u32],
+        rng: &mut R,
+    ) -> Result<(MarlinProof, Vec<Fr>), ZycleError> {
+        self.prove_with_salt(adj_matrix, topological_sort, None, rng)
+    }
+
+    // for keys indexed with a salted commitment, the public input is salted_commitment(adj_matrix, salt),
+    // and row_tree_commitment(adj_matrix, salt) for a row tree
+    pub fn prove_salted_with_toposort<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        topological_sort: &[u32],
+        salt: Fr,
+        rng: &mut R,
+    ) -> Result<(MarlinProof, Vec<Fr>), ZycleError> {
+        self.prove_with_salt(adj_matrix, topological_sort, Some(salt), rng)
+    }
+
+    fn prove_with_salt<Row: AsRef<[bool]>, R: RngCore + CryptoRng>(
+        &self,
+        adj_matrix: &[Row],
+        topological_sort: &[u32],
+        salt: Option<Fr>,
+        rng: &mut R,
+    ) -> Result<(MarlinProof, Vec<Fr>), ZycleError> {
+        let circuit_inputs =
+            dense_circuit(adj_matrix, topological_sort, salt, self.nodes, self.position_width, self.commitment)?;
+        // Marlin does not check the witness either
+        check_satisfied(circuit_inputs.clone())?;
+        let adj_hash = circuit_inputs.adj_hash;
+        let proof = MarlinBls12::prove(&self.pk, circuit_inputs, rng).map_err(marlin_error)?;
+        Ok((proof, vec![adj_hash]))
+    }
+}
+
+impl MarlinVerifier {
+    pub fn load(file_path: &str, validate: Validate) -> Result<Self, ZycleError> {
+        let (header, vk) = read_key_from_file(file_path, validate)?;
+        header.expect_variant(CircuitVariant::TopoSort)?;
+        Ok(Self { nodes: header.nodes, position_width: header.position_width, commitment: header.commitment, vk })
+    }
+
+    pub fn write_verifying_key(&self, file_path: &str, compress: Compress) -> Result<(), ZycleError> {
+        write_key_to_file(&self.vk, self.shape(), compress, file_path)
+    }
+
+    fn shape(&self) -> CircuitShape {
+        CircuitShape { commitment: self.commitment, ..CircuitShape::dense(self.nodes, self.position_width) }
+    }
+
+    pub fn verifying_key(&self) -> &MarlinVerifyingKey {
+        &self.vk
+    }
+
+    pub fn nodes(&self) -> usize {
+        self.nodes
+    }
+
+    pub fn is_salted(&self) -> bool {
+        self.commitment.is_salted()
+    }
+
+    pub fn commitment(&self) -> CommitmentKind {
+        self.commitment
+    }
+
+    // checks the proof against the public inputs returned by MarlinProver::prove
+    // a proof that does not even have the shape of a Marlin proof is invalid (Ok(false)), while a
+    // failure of the commitment scheme or the verifier, e.g. with a corrupted key, is an error
+    pub fn verify(&self, proof: &MarlinProof, public_inputs: &[Fr]) -> Result<bool, ZycleError> {
+        // the verifier pads the inputs to the instance the key was indexed for, but more inputs than
+        // that (besides the constant one) are evaluated over a larger domain than the key's and panic
+        let max_inputs = self.vk.index_info.num_instance_variables - 1;
+        if public_inputs.len() > max_inputs {
+            return Err(ZycleError::InvalidGraph(format!(
+                "key takes at most {} public inputs, but {} were given",
+                max_inputs,
+                public_inputs.len()
+            )));
+        }
+        MarlinBls12::verify(&self.vk, public_inputs, proof, &mut OsRng).map_err(marlin_error)
+    }
+}
+
+// an index too large for the SRS is the caller's mistake, the rest are errors of the proof system
+fn marlin_error(e: impl Into<marlin::Error<ark_poly_commit::Error>>) -> ZycleError {
+    match e.into() {
+        e @ marlin::Error::IndexTooLarge => ZycleError::KeyMismatch(e.to_string()),
+        marlin::Error::AHPError(marlin::ahp::Error::ConstraintSystemError(e)) => ZycleError::Synthesis(e),
+        e => ZycleError::ProofSystem(e.to_string()),
+    }
+}
+
+#[test]
+fn marlin_prove_and_verify_roundtrip() {
+    use ark_relations::r1cs::SynthesisError;
+    use ark_std::{rand::SeedableRng, test_rng, Zero};
+
+    let adj_matrix = [[false, true], [false, false]];
+
+    let mut rng = ark_std::rand::rngs::StdRng::seed_from_u64(test_rng().next_u64());
+    let srs = UniversalSrs::setup(2, &mut rng).unwrap();
+    let (prover, verifier) = MarlinProver::index(&srs, 2, PositionWidth::U8, CommitmentKind::Plain).unwrap();
+    let (proof, public_inputs) = prover.prove(&adj_matrix, &mut rng).unwrap();
+
+    assert_eq!(public_inputs, vec![crate::prover::commitment(&adj_matrix).unwrap()]);
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+    assert!(!verifier.verify(&proof, &[Fr::zero()]).unwrap());
+    // too many public inputs is the caller's mistake
+    let err = verifier.verify(&proof, &[public_inputs[0], Fr::zero()]).err().unwrap();
+    assert!(matches!(err, ZycleError::InvalidGraph(_)) && err.is_bad_input());
+
+    // a key stripped of its degree bound powers fails the commitment check itself, an error rather than a rejection
+    let mut broken = verifier;
+    broken.vk.verifier_key.degree_bounds_and_shift_powers = None;
+    let err = broken.verify(&proof, &public_inputs).err().unwrap();
+    assert!(matches!(err, ZycleError::ProofSystem(_)) && !err.is_bad_input());
+
+    // an ordering that breaks the edge is refused rather than proven
+    let err = match prover.prove_with_toposort(&adj_matrix, &[1, 0], &mut rng) {
+        Err(err) => err,
+        Ok(_) => panic!("proved a bad ordering"),
+    };
+    assert!(matches!(err, ZycleError::Synthesis(SynthesisError::Unsatisfiable)));
+
+    // the same SRS serves the other commitments and smaller graphs without another setup
+    let (prover, verifier) = MarlinProver::index(&srs, 1, PositionWidth::U8, CommitmentKind::Salted).unwrap();
+    let (proof, public_inputs) = prover.prove_salted_with_toposort(&[[false]], &[0], Fr::from(7u64), &mut rng).unwrap();
+    assert!(verifier.verify(&proof, &public_inputs).unwrap());
+
+    // but not larger graphs
+    let err = MarlinProver::index(&srs, 3, PositionWidth::U8, CommitmentKind::Plain).err().unwrap();
+    assert!(matches!(err, ZycleError::KeyMismatch(_)));
+}
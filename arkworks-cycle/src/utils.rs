@@ -1,11 +1,14 @@
 use std::borrow::Borrow;
-use ark_ff::PrimeField;
+use ark_ff::{BigInteger, PrimeField};
 use ark_r1cs_std::{
+    fields::fp::FpVar,
     prelude::{AllocVar, AllocationMode, Boolean, EqGadget},
+    uint16::UInt16,
+    uint32::UInt32,
     uint8::UInt8,
     R1CSVar, ToBitsGadget,
 };
-use ark_relations::r1cs::{Namespace, SynthesisError};
+use ark_relations::r1cs::{ConstraintSystemRef, Namespace, SynthesisError};
 
 
 pub struct Uint8Array<const N: usize, ConstraintF: PrimeField>(pub [UInt8<ConstraintF>; N]);
@@ -15,6 +18,11 @@ pub struct Boolean3DArray<const N: usize, const M: usize, ConstraintF: PrimeFiel
     pub [[[Boolean<ConstraintF>; N]; N]; M],
 );
 
+// Vec backed versions of the arrays above, for graphs whose size is only known at runtime
+pub struct Uint8Vec<ConstraintF: PrimeField>(pub Vec<UInt8<ConstraintF>>);
+pub struct BooleanVec<ConstraintF: PrimeField>(pub Vec<Boolean<ConstraintF>>);
+pub struct Boolean2DVec<ConstraintF: PrimeField>(pub Vec<Vec<Boolean<ConstraintF>>>);
+
 // Allocates memory for Uint8Array in our constrains system
 impl<const N: usize, F: PrimeField> AllocVar<[u8; N], F> for Uint8Array<N, F> {
     fn new_variable<T: Borrow<[u8; N]>>(
@@ -94,6 +102,80 @@ impl<const N: usize, const M: usize, F: PrimeField> AllocVar<[[[bool; N]; N]; M]
     }
 }
 
+// Unlike the fixed size arrays, the length comes from the value itself, so it must be available
+impl<F: PrimeField> AllocVar<Vec<u8>, F> for Uint8Vec<F> {
+    fn new_variable<T: Borrow<Vec<u8>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let cs = cs.into();
+        let value = f()?;
+        let array = value
+            .borrow()
+            .iter()
+            .map(|v| UInt8::new_variable(cs.clone(), || Ok(*v), mode))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Uint8Vec(array))
+    }
+}
+
+impl<F: PrimeField> AllocVar<Vec<bool>, F> for BooleanVec<F> {
+    fn new_variable<T: Borrow<Vec<bool>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let cs = cs.into();
+        let value = f()?;
+        let array = value
+            .borrow()
+            .iter()
+            .map(|v| Boolean::new_variable(cs.clone(), || Ok(*v), mode))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(BooleanVec(array))
+    }
+}
+
+impl<F: PrimeField> AllocVar<Vec<Vec<bool>>, F> for Boolean2DVec<F> {
+    fn new_variable<T: Borrow<Vec<Vec<bool>>>>(
+        cs: impl Into<Namespace<F>>,
+        f: impl FnOnce() -> Result<T, SynthesisError>,
+        mode: AllocationMode,
+    ) -> Result<Self, SynthesisError> {
+        let cs = cs.into();
+        let value = f()?;
+        let mut contraint_array = Vec::with_capacity(value.borrow().len());
+        for row in value.borrow() {
+            let row = row
+                .iter()
+                .map(|cell| Boolean::new_variable(cs.clone(), || Ok(*cell), mode))
+                .collect::<Result<Vec<_>, _>>()?;
+            contraint_array.push(row);
+        }
+        Ok(Boolean2DVec(contraint_array))
+    }
+}
+
+// the fixed size arrays convert into the Vec backed ones, which is how they share the graph checks
+impl<const N: usize, F: PrimeField> From<&Uint8Array<N, F>> for Uint8Vec<F> {
+    fn from(array: &Uint8Array<N, F>) -> Self {
+        Uint8Vec(array.0.to_vec())
+    }
+}
+
+impl<const N: usize, F: PrimeField> From<&BooleanArray<N, F>> for BooleanVec<F> {
+    fn from(array: &BooleanArray<N, F>) -> Self {
+        BooleanVec(array.0.to_vec())
+    }
+}
+
+impl<const N: usize, F: PrimeField> From<&Boolean2DArray<N, F>> for Boolean2DVec<F> {
+    fn from(array: &Boolean2DArray<N, F>) -> Self {
+        Boolean2DVec(array.0.iter().map(|row| row.to_vec()).collect())
+    }
+}
+
 // allows comparision ops for ConstraintF
 pub trait CmpGadget<ConstraintF: PrimeField>: R1CSVar<ConstraintF> + EqGadget<ConstraintF> {
     #[inline]
@@ -119,14 +201,17 @@ pub trait CmpGadget<ConstraintF: PrimeField>: R1CSVar<ConstraintF> + EqGadget<Co
     }
 
     fn is_lt(&self, other: &Self) -> Result<Boolean<ConstraintF>, SynthesisError>;
+
+    // the value as one field element, which is what the permutation check multiplies together
+    fn to_fp_var(&self) -> Result<FpVar<ConstraintF>, SynthesisError>;
 }
 
 impl<ConstraintF: PrimeField> CmpGadget<ConstraintF> for UInt8<ConstraintF> {
     fn is_lt(&self, other: &Self) -> Result<Boolean<ConstraintF>, SynthesisError> {
         // Determine the variable mode.
         if self.is_constant() && other.is_constant() {
-            let self_value = self.value().unwrap();
-            let other_value = other.value().unwrap();
+            let self_value = self.value()?;
+            let other_value = other.value()?;
             let result = Boolean::constant(self_value < other_value);
             Ok(result)
         } else {
@@ -144,4 +229,218 @@ impl<ConstraintF: PrimeField> CmpGadget<ConstraintF> for UInt8<ConstraintF> {
             Ok(result)
         }
     }
+
+    fn to_fp_var(&self) -> Result<FpVar<ConstraintF>, SynthesisError> {
+        Boolean::le_bits_to_fp_var(&self.to_bits_le()?)
+    }
+}
+
+// UInt16 and UInt32 compare the same way as UInt8, one bit at a time from the most significant
+impl<ConstraintF: PrimeField> CmpGadget<ConstraintF> for UInt16<ConstraintF> {
+    fn is_lt(&self, other: &Self) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        if self.is_constant() && other.is_constant() {
+            Ok(Boolean::constant(self.value()? < other.value()?))
+        } else {
+            is_lt_bits_le(&self.to_bits_le(), &other.to_bits_le())
+        }
+    }
+
+    fn to_fp_var(&self) -> Result<FpVar<ConstraintF>, SynthesisError> {
+        Boolean::le_bits_to_fp_var(&self.to_bits_le())
+    }
+}
+
+impl<ConstraintF: PrimeField> CmpGadget<ConstraintF> for UInt32<ConstraintF> {
+    fn is_lt(&self, other: &Self) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        if self.is_constant() && other.is_constant() {
+            Ok(Boolean::constant(self.value()? < other.value()?))
+        } else {
+            is_lt_bits_le(&self.to_bits_le(), &other.to_bits_le())
+        }
+    }
+
+    fn to_fp_var(&self) -> Result<FpVar<ConstraintF>, SynthesisError> {
+        Boolean::le_bits_to_fp_var(&self.to_bits_le())
+    }
+}
+
+// a < b for two little endian bit strings of the same length
+fn is_lt_bits_le<ConstraintF: PrimeField>(
+    a_bits: &[Boolean<ConstraintF>],
+    b_bits: &[Boolean<ConstraintF>],
+) -> Result<Boolean<ConstraintF>, SynthesisError> {
+    let mut result = Boolean::FALSE;
+    let mut a_and_b_equal_so_far = Boolean::TRUE;
+    for (a, b) in a_bits.iter().rev().zip(b_bits.iter().rev()) {
+        let a_is_lt_b = a.not().and(b)?;
+        result = result.or(&a_is_lt_b.and(&a_and_b_equal_so_far)?)?;
+        a_and_b_equal_so_far = a_and_b_equal_so_far.and(&a.xor(b)?.not())?;
+    }
+    Ok(result)
+}
+
+// A field element constrained to be below 2^bits
+// comparing two of these takes one bit decomposition of their difference (about bits + 2 constraints),
+// where the UInt gadgets need several constraints for every bit of both sides
+#[derive(Clone, Debug)]
+pub struct BoundedFpVar<ConstraintF: PrimeField> {
+    pub value: FpVar<ConstraintF>,
+    pub bits: usize,
+}
+
+impl<ConstraintF: PrimeField> BoundedFpVar<ConstraintF> {
+    // allocates the value and enforces that it fits in `bits` bits
+    // bits has to leave room for the sign bit of a difference, so it must be below the field size
+    pub fn new_variable_with_bits(
+        cs: impl Into<Namespace<ConstraintF>>,
+        f: impl FnOnce() -> Result<ConstraintF, SynthesisError>,
+        mode: AllocationMode,
+        bits: usize,
+    ) -> Result<Self, SynthesisError> {
+        if bits + 1 >= ConstraintF::MODULUS_BIT_SIZE as usize {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        let value = FpVar::new_variable(cs, f, mode)?;
+        enforce_bit_length(&value, bits)?;
+        Ok(Self { value, bits })
+    }
+
+    pub fn new_witness_with_bits(
+        cs: impl Into<Namespace<ConstraintF>>,
+        f: impl FnOnce() -> Result<ConstraintF, SynthesisError>,
+        bits: usize,
+    ) -> Result<Self, SynthesisError> {
+        Self::new_variable_with_bits(cs, f, AllocationMode::Witness, bits)
+    }
+
+    pub fn constant(value: u64, bits: usize) -> Self {
+        Self {
+            value: FpVar::Constant(ConstraintF::from(value)),
+            bits,
+        }
+    }
+}
+
+// decomposes value into `bits` little endian bits and checks they add back up to it,
+// which is only possible if value < 2^bits
+fn enforce_bit_length<ConstraintF: PrimeField>(
+    value: &FpVar<ConstraintF>,
+    bits: usize,
+) -> Result<Vec<Boolean<ConstraintF>>, SynthesisError> {
+    let native_bits = value.value().ok().map(|v| v.into_bigint().to_bits_le());
+    let bit = |i: usize| native_bits.as_ref().is_some_and(|b| b.get(i).copied().unwrap_or(false));
+    if value.is_constant() {
+        let bit_vars: Vec<_> = (0..bits).map(|i| Boolean::constant(bit(i))).collect();
+        if native_bits.is_some_and(|b| b[bits..].iter().any(|&high| high)) {
+            return Err(SynthesisError::Unsatisfiable);
+        }
+        return Ok(bit_vars);
+    }
+    let cs: ConstraintSystemRef<ConstraintF> = value.cs();
+    let bit_vars = (0..bits)
+        .map(|i| {
+            Boolean::new_witness(cs.clone(), || {
+                native_bits.as_ref().map(|_| bit(i)).ok_or(SynthesisError::AssignmentMissing)
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Boolean::le_bits_to_fp_var(&bit_vars)?.enforce_equal(value)?;
+    Ok(bit_vars)
+}
+
+impl<ConstraintF: PrimeField> R1CSVar<ConstraintF> for BoundedFpVar<ConstraintF> {
+    type Value = ConstraintF;
+
+    fn cs(&self) -> ConstraintSystemRef<ConstraintF> {
+        self.value.cs()
+    }
+
+    fn value(&self) -> Result<Self::Value, SynthesisError> {
+        self.value.value()
+    }
+}
+
+impl<ConstraintF: PrimeField> EqGadget<ConstraintF> for BoundedFpVar<ConstraintF> {
+    fn is_eq(&self, other: &Self) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        self.value.is_eq(&other.value)
+    }
+
+    fn conditional_enforce_equal(
+        &self,
+        other: &Self,
+        condition: &Boolean<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        self.value.conditional_enforce_equal(&other.value, condition)
+    }
+
+    fn conditional_enforce_not_equal(
+        &self,
+        other: &Self,
+        condition: &Boolean<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        self.value.conditional_enforce_not_equal(&other.value, condition)
+    }
+}
+
+impl<ConstraintF: PrimeField> CmpGadget<ConstraintF> for BoundedFpVar<ConstraintF> {
+    fn is_lt(&self, other: &Self) -> Result<Boolean<ConstraintF>, SynthesisError> {
+        // both sides are below 2^bits, so 2^bits + self - other lies in [1, 2^(bits+1))
+        // and its top bit is set exactly when self >= other
+        let bits = self.bits.max(other.bits);
+        let offset = FpVar::Constant(ConstraintF::from(2u64).pow([bits as u64]));
+        let shifted = offset + &self.value - &other.value;
+        let shifted_bits = enforce_bit_length(&shifted, bits + 1)?;
+        Ok(shifted_bits[bits].not())
+    }
+
+    fn to_fp_var(&self) -> Result<FpVar<ConstraintF>, SynthesisError> {
+        Ok(self.value.clone())
+    }
+}
+
+// Allocates topological positions as witnesses of type P, where T is the native type P is allocated from
+// a position too big for T can never satisfy the circuit
+pub fn position_witnesses<ConstraintF, P, T>(
+    cs: ConstraintSystemRef<ConstraintF>,
+    positions: &[u32],
+) -> Result<Vec<P>, SynthesisError>
+where
+    ConstraintF: PrimeField,
+    P: AllocVar<T, ConstraintF>,
+    T: TryFrom<u32>,
+{
+    positions
+        .iter()
+        .map(|&pos| P::new_witness(cs.clone(), || T::try_from(pos).map_err(|_| SynthesisError::Unsatisfiable)))
+        .collect()
+}
+
+#[test]
+fn cmp_gadgets_agree_with_native() {
+    use ark_bls12_381::Fr as F;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let pairs: [(u32, u32); 6] = [(0, 0), (0, 1), (1, 0), (255, 256), (300, 299), (65535, 65535)];
+    for (a, b) in pairs {
+        let cs = ConstraintSystem::<F>::new_ref();
+        let a16 = UInt16::new_witness(cs.clone(), || Ok(a as u16)).unwrap();
+        let b16 = UInt16::new_witness(cs.clone(), || Ok(b as u16)).unwrap();
+        let a32 = UInt32::new_witness(cs.clone(), || Ok(a)).unwrap();
+        let b32 = UInt32::new_witness(cs.clone(), || Ok(b)).unwrap();
+        let a_fp = BoundedFpVar::new_witness_with_bits(cs.clone(), || Ok(F::from(a)), 16).unwrap();
+        let b_fp = BoundedFpVar::new_witness_with_bits(cs.clone(), || Ok(F::from(b)), 16).unwrap();
+        let b_const = BoundedFpVar::constant(b as u64, 16);
+
+        assert_eq!(a16.is_lt(&b16).unwrap().value().unwrap(), a < b);
+        assert_eq!(a32.is_gt(&b32).unwrap().value().unwrap(), a > b);
+        assert_eq!(a_fp.is_lt(&b_fp).unwrap().value().unwrap(), a < b);
+        assert_eq!(a_fp.is_geq(&b_const).unwrap().value().unwrap(), a >= b);
+        assert_eq!(a_fp.is_leq(&b_fp).unwrap().value().unwrap(), a <= b);
+        assert!(cs.is_satisfied().unwrap());
+    }
+
+    // a value that does not fit in the width leaves the constraints unsatisfied
+    let cs = ConstraintSystem::<F>::new_ref();
+    BoundedFpVar::new_witness_with_bits(cs.clone(), || Ok(F::from(256u64)), 8).unwrap();
+    assert!(!cs.is_satisfied().unwrap());
 }
@@ -0,0 +1,286 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::error::ZycleError;
+use crate::prover::check_matrix;
+
+// returned when the graph has no topological sort, holds one cycle as a list of node indices
+// where each node has an edge to the next and the last node has an edge back to the first
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CycleError {
+    pub cycle: Vec<usize>,
+}
+
+impl fmt::Display for CycleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "graph has a cycle")?;
+        // the field is public, so a CycleError may be made without any nodes
+        if let Some(first) = self.cycle.first() {
+            write!(f, ": ")?;
+            for node in &self.cycle {
+                write!(f, "{} -> ", node)?;
+            }
+            write!(f, "{}", first)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for CycleError {}
+
+// Computes the position of every node in a topological sort of the graph (Kahn's algorithm)
+// positions[i] is where node i appears, which is the form check_topo_sort expects
+// fails with ZycleError::InvalidGraph if adj_matrix is not square
+pub fn topological_positions<Row: AsRef<[bool]>>(adj_matrix: &[Row]) -> Result<Vec<usize>, ZycleError> {
+    check_matrix(adj_matrix, adj_matrix.len())?;
+    let edges = adj_matrix.iter().enumerate().flat_map(|(i, row)| {
+        row.as_ref()
+            .iter()
+            .enumerate()
+            .filter(|(_, &transacted)| transacted)
+            .map(move |(j, _)| (i, j))
+    });
+    topological_positions_from_edges(adj_matrix.len(), edges)
+}
+
+// Same as topological_positions for a graph given as (sender, receiver) pairs, which avoids
+// building an n x n matrix for large sparse graphs. An index of `nodes` or more is a ZycleError::InvalidGraph
+pub fn topological_positions_from_edges(
+    nodes: usize,
+    edges: impl IntoIterator<Item = (usize, usize)>,
+) -> Result<Vec<usize>, ZycleError> {
+    let mut successors = vec![Vec::new(); nodes];
+    for (sender, receiver) in edges {
+        if sender >= nodes || receiver >= nodes {
+            return Err(ZycleError::InvalidGraph(format!(
+                "edge {} -> {} is outside a graph with {} nodes",
+                sender, receiver, nodes
+            )));
+        }
+        successors[sender].push(receiver);
+    }
+    let mut in_degree = vec![0usize; nodes];
+    for targets in successors.iter_mut() {
+        targets.sort_unstable();
+        targets.dedup();
+        for &j in targets.iter() {
+            in_degree[j] += 1;
+        }
+    }
+
+    let mut ready: VecDeque<usize> = (0..nodes).filter(|&i| in_degree[i] == 0).collect();
+    let mut positions = vec![usize::MAX; nodes];
+    let mut next_position = 0;
+    while let Some(i) = ready.pop_front() {
+        positions[i] = next_position;
+        next_position += 1;
+        for &j in &successors[i] {
+            in_degree[j] -= 1;
+            if in_degree[j] == 0 {
+                ready.push_back(j);
+            }
+        }
+    }
+
+    if next_position == nodes {
+        Ok(positions)
+    } else {
+        let unsorted: Vec<bool> = positions.iter().map(|&p| p == usize::MAX).collect();
+        Err(CycleError { cycle: find_cycle(&successors, &unsorted) }.into())
+    }
+}
+
+// Same as topological_positions, in the fixed size form Uint8Array takes, so only for graphs of up to 256 nodes
+pub fn toposort_witness<const N: usize>(adj_matrix: &[[bool; N]; N]) -> Result<[u8; N], ZycleError> {
+    let positions = topological_positions(adj_matrix)?;
+    let mut toposort = [0u8; N];
+    for (i, pos) in positions.into_iter().enumerate() {
+        toposort[i] = u8::try_from(pos).map_err(|_| {
+            ZycleError::InvalidGraph(format!("position {} of node {} does not fit in a u8", pos, i))
+        })?;
+    }
+    Ok(toposort)
+}
+
+// Same as topological_positions, in the runtime sized form MyGraphCircuitStruct takes
+pub fn toposort_witness_vec<Row: AsRef<[bool]>>(adj_matrix: &[Row]) -> Result<Vec<u32>, ZycleError> {
+    Ok(topological_positions(adj_matrix)?
+        .into_iter()
+        .map(|pos| pos as u32)
+        .collect())
+}
+
+// Marks every node reachable from start along edges, start included
+pub fn reachable_from<Row: AsRef<[bool]>>(adj_matrix: &[Row], start: usize) -> Result<Vec<bool>, ZycleError> {
+    check_matrix(adj_matrix, adj_matrix.len())?;
+    if start >= adj_matrix.len() {
+        return Err(ZycleError::InvalidGraph(format!("start node {} is not in the graph", start)));
+    }
+    let mut reachable = vec![false; adj_matrix.len()];
+    let mut stack = vec![start];
+    reachable[start] = true;
+    while let Some(i) = stack.pop() {
+        for (j, &transacted) in adj_matrix[i].as_ref().iter().enumerate() {
+            if transacted && !reachable[j] {
+                reachable[j] = true;
+                stack.push(j);
+            }
+        }
+    }
+    Ok(reachable)
+}
+
+// Positions for every node where only the edges inside the subgraph have to go forwards,
+// so a cycle elsewhere in the graph does not get in the way
+pub fn subgraph_toposort_witness<Row: AsRef<[bool]>>(
+    adj_matrix: &[Row],
+    subgraph_nodes: &[bool],
+) -> Result<Vec<u32>, ZycleError> {
+    check_matrix(adj_matrix, adj_matrix.len())?;
+    if subgraph_nodes.len() != adj_matrix.len() {
+        return Err(ZycleError::InvalidGraph(format!("expected {} subgraph nodes", adj_matrix.len())));
+    }
+    let edges = adj_matrix.iter().enumerate().flat_map(|(i, row)| {
+        row.as_ref()
+            .iter()
+            .enumerate()
+            .filter(move |&(j, &transacted)| transacted && subgraph_nodes[i] && subgraph_nodes[j])
+            .map(move |(j, _)| (i, j))
+    });
+    Ok(topological_positions_from_edges(adj_matrix.len(), edges)?
+        .into_iter()
+        .map(|pos| pos as u32)
+        .collect())
+}
+
+// Every node Kahn's algorithm could not sort still has an incoming edge from another unsorted node,
+// so walking backwards along those edges has to revisit a node, which closes a cycle
+fn find_cycle(successors: &[Vec<usize>], unsorted: &[bool]) -> Vec<usize> {
+    let mut predecessors = vec![Vec::new(); successors.len()];
+    for (i, targets) in successors.iter().enumerate() {
+        for &j in targets {
+            predecessors[j].push(i);
+        }
+    }
+    let start = unsorted.iter().position(|&u| u).unwrap_or(0);
+    let mut visited_at = vec![usize::MAX; successors.len()];
+    let mut walk = Vec::new();
+    let mut node = start;
+    while visited_at[node] == usize::MAX {
+        visited_at[node] = walk.len();
+        walk.push(node);
+        node = predecessors[node]
+            .iter()
+            .copied()
+            .find(|&pred| unsorted[pred])
+            .unwrap_or(node);
+    }
+    // the walk followed edges backwards, so reverse it to get the cycle in edge order
+    // and start it from its smallest node so the reported cycle does not depend on the walk
+    let mut cycle = walk.split_off(visited_at[node]);
+    cycle.reverse();
+    let smallest = (0..cycle.len()).min_by_key(|&k| cycle[k]).unwrap_or(0);
+    cycle.rotate_left(smallest);
+    cycle
+}
+
+#[cfg(test)]
+fn cycle_of(err: ZycleError) -> Vec<usize> {
+    match err {
+        ZycleError::CyclicGraph(e) => e.cycle,
+        e => panic!("expected a cycle, got {:?}", e),
+    }
+}
+
+#[cfg(test)]
+fn is_cycle<Row: AsRef<[bool]>>(adj_matrix: &[Row], cycle: &[usize]) -> bool {
+    !cycle.is_empty()
+        && (0..cycle.len()).all(|k| adj_matrix[cycle[k]].as_ref()[cycle[(k + 1) % cycle.len()]])
+}
+
+#[test]
+fn witness_satisfies_topo_sort_check() {
+    use crate::graph_checks::check_topo_sort;
+    use crate::utils::{Boolean2DArray, Uint8Array};
+    use ark_bls12_381::Fq as F;
+    use ark_r1cs_std::alloc::AllocVar;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    let adj_matrix = [
+        [false, false, false, false, false, false], //
+        [false, false, true, false, false, false],  //      [5] -> [3] -> [1] -> [2]
+        [false, false, false, false, false, false], //              |
+        [false, true, false, false, true, false],   //              v
+        [true, false, false, false, false, false],  //             [4] -> [0]
+        [false, false, false, true, false, false],  //
+    ];
+    let topo = toposort_witness(&adj_matrix).unwrap();
+
+    let cs = ConstraintSystem::<F>::new_ref();
+    let adj_matrix_var = Boolean2DArray::new_witness(cs.clone(), || Ok(adj_matrix)).unwrap();
+    let topo_var = Uint8Array::new_witness(cs.clone(), || Ok(topo)).unwrap();
+    check_topo_sort(&adj_matrix_var, &topo_var).unwrap();
+    assert!(cs.is_satisfied().unwrap());
+}
+
+#[test]
+fn cyclic_graph_names_a_cycle() {
+    let adj_matrix = [
+        [false, true, true, false, false, false],   //               [0]<-----\
+        [false, false, true, false, false, false],  //               / \       \
+        [false, false, false, true, false, true],   //             [1]->[2]      \
+        [false, false, false, false, false, false], //                /  \     /
+        [true, false, false, false, false, false],  //               [3]  [5]->[4]
+        [false, false, false, false, true, false],  //
+    ];
+    let cycle = cycle_of(toposort_witness(&adj_matrix).unwrap_err());
+    assert!(is_cycle(&adj_matrix, &cycle));
+
+    let mut self_loop = [[false; 3]; 3];
+    self_loop[0][1] = true;
+    self_loop[2][2] = true;
+    assert_eq!(cycle_of(topological_positions(&self_loop).unwrap_err()), vec![2]);
+
+    let edges = [(0, 1), (2, 2), (0, 1)];
+    assert_eq!(cycle_of(topological_positions_from_edges(3, edges).unwrap_err()), vec![2]);
+    assert_eq!(topological_positions_from_edges(3, [(2, 0), (0, 1)]).unwrap(), vec![1, 2, 0]);
+
+    assert_eq!(CycleError { cycle: vec![2, 0] }.to_string(), "graph has a cycle: 2 -> 0 -> 2");
+    assert_eq!(CycleError { cycle: Vec::new() }.to_string(), "graph has a cycle");
+}
+
+#[test]
+fn reachable_subgraph_ignores_other_cycles() {
+    let adj_matrix = [
+        [false, true, false, false], //   [0] -> [1]    [2] <-> [3]
+        [false, false, false, false],
+        [false, false, false, true],
+        [false, false, true, false],
+    ];
+    let reachable = reachable_from(&adj_matrix, 0).unwrap();
+    assert_eq!(reachable, vec![true, true, false, false]);
+    let positions = subgraph_toposort_witness(&adj_matrix, &reachable).unwrap();
+    assert!(positions[0] < positions[1]);
+    assert!(subgraph_toposort_witness(&adj_matrix, &reachable_from(&adj_matrix, 3).unwrap()).is_err());
+}
+
+#[test]
+fn malformed_graphs_are_errors() {
+    let ragged = [vec![false, true], vec![false]];
+    let not_square = [vec![false, true, false], vec![false, false, false]];
+    for adj_matrix in [&ragged[..], &not_square[..]] {
+        assert!(matches!(topological_positions(adj_matrix), Err(ZycleError::InvalidGraph(_))));
+        assert!(matches!(reachable_from(adj_matrix, 0), Err(ZycleError::InvalidGraph(_))));
+    }
+    let adj_matrix = [[false, true], [false, false]];
+    assert!(matches!(reachable_from(&adj_matrix, 2), Err(ZycleError::InvalidGraph(_))));
+    assert!(matches!(subgraph_toposort_witness(&adj_matrix, &[true]), Err(ZycleError::InvalidGraph(_))));
+    let err = topological_positions_from_edges(2, [(0, 1), (1, 2)]).unwrap_err();
+    assert!(matches!(err, ZycleError::InvalidGraph(_)));
+
+    // u8 positions cover 256 nodes, a path one longer is refused instead of wrapping around
+    let path: [[bool; 257]; 257] = std::array::from_fn(|i| std::array::from_fn(|j| j == i + 1));
+    assert!(matches!(toposort_witness(&path), Err(ZycleError::InvalidGraph(_))));
+    let fits: [[bool; 256]; 256] = std::array::from_fn(|i| std::array::from_fn(|j| j == i + 1));
+    assert_eq!(toposort_witness(&fits).unwrap()[255], 255);
+}
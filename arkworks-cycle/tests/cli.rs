@@ -0,0 +1,95 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+// runs the built binary and returns its exit code and what it printed
+fn zycle(args: &[&str]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_zycle")).args(args).output().unwrap();
+    (output.status.code().unwrap(), String::from_utf8(output.stdout).unwrap().trim().to_string())
+}
+
+fn path(dir: &Path, name: &str) -> String {
+    dir.join(name).to_string_lossy().into_owned()
+}
+
+#[test]
+fn exit_codes() {
+    let dir = std::env::temp_dir().join(format!("zycle-cli-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (pk, vk, proof) = (path(&dir, "pk.bin"), path(&dir, "vk.bin"), path(&dir, "proof.json"));
+    let (graph, cyclic) = (path(&dir, "graph.txt"), path(&dir, "cyclic.txt"));
+    fs::write(&graph, "0 1 1\n0 0 1\n0 0 0\n").unwrap();
+    fs::write(&cyclic, "0 1 0\n0 0 1\n1 0 0\n").unwrap();
+
+    // 0 when everything goes through, prove and hash print the same commitment
+    assert_eq!(zycle(&["setup", "--nodes", "3", "--pk", &pk, "--vk", &vk]).0, 0);
+    let (code, commitment) = zycle(&["prove", "--pk", &pk, "--graph", &graph, "--proof", &proof]);
+    assert_eq!(code, 0);
+    assert_eq!(zycle(&["hash", "--graph", &graph]), (0, commitment.clone()));
+    assert_eq!(zycle(&["verify", "--vk", &vk, "--proof", &proof]).0, 0);
+    assert_eq!(zycle(&["verify", "--vk", &vk, "--proof", &proof, "--commitment", &commitment]).0, 0);
+
+    // 1 for a proof checked against another commitment
+    assert_eq!(zycle(&["verify", "--vk", &vk, "--proof", &proof, "--commitment", "1"]).0, 1);
+
+    // 2 for a graph with a cycle, a commitment that is not a number or a missing key
+    assert_eq!(zycle(&["prove", "--pk", &pk, "--graph", &cyclic, "--proof", &proof]).0, 2);
+    assert_eq!(zycle(&["verify", "--vk", &vk, "--proof", &proof, "--commitment", "not a number"]).0, 2);
+    assert_eq!(zycle(&["verify", "--vk", &path(&dir, "missing.bin"), "--proof", &proof]).0, 2);
+
+    // 3 when the keys cannot be written
+    let unwritable = path(&dir, "missing/pk.bin");
+    assert_eq!(zycle(&["setup", "--nodes", "3", "--pk", &unwritable, "--vk", &vk]).0, 3);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn sparse_keys_take_edge_lists() {
+    let dir = std::env::temp_dir().join(format!("zycle-cli-sparse-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (pk, vk, proof) = (path(&dir, "pk.bin"), path(&dir, "vk.bin"), path(&dir, "proof.json"));
+    let (edges, matrix) = (path(&dir, "graph.csv"), path(&dir, "graph.txt"));
+    fs::write(&edges, "sender,receiver\nalice,bob\nbob,carol\nalice,carol\nalice,bob\n").unwrap();
+    fs::write(&matrix, "0 1 1\n0 0 1\n0 0 0\n").unwrap();
+
+    assert_eq!(zycle(&["setup", "--nodes", "4", "--edges", "4", "--pk", &pk, "--vk", &vk]).0, 0);
+    let (code, commitment) = zycle(&["prove", "--pk", &pk, "--graph", &edges, "--proof", &proof]);
+    assert_eq!(code, 0);
+    // the repeated edge counts once, as in the matrix of the same graph
+    assert_eq!(zycle(&["hash", "--graph", &edges, "--edges", "4"]), (0, commitment.clone()));
+    assert_eq!(zycle(&["hash", "--graph", &matrix, "--edges", "4"]), (0, commitment));
+    assert_eq!(zycle(&["verify", "--vk", &vk, "--proof", &proof]).0, 0);
+
+    fs::write(&edges, "alice,bob\nbob,alice\n").unwrap();
+    assert_eq!(zycle(&["prove", "--pk", &pk, "--graph", &edges, "--proof", &proof]).0, 2);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn row_tree_reveals_one_row() {
+    let dir = std::env::temp_dir().join(format!("zycle-cli-row-tree-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (pk, vk, proof) = (path(&dir, "pk.bin"), path(&dir, "vk.bin"), path(&dir, "proof.json"));
+    let (graph, salt, opening) = (path(&dir, "graph.txt"), path(&dir, "salt.txt"), path(&dir, "opening.json"));
+    fs::write(&graph, "0 1 1\n0 0 1\n0 0 0\n").unwrap();
+
+    assert_eq!(zycle(&["setup", "--nodes", "3", "--row-tree", "--pk", &pk, "--vk", &vk]).0, 0);
+    let (code, root) = zycle(&["commit", "--graph", &graph, "--salt", &salt, "--row-tree"]);
+    assert_eq!(code, 0);
+    // row tree keys take the salt the root was made with
+    assert_eq!(zycle(&["prove", "--pk", &pk, "--graph", &graph, "--proof", &proof]).0, 2);
+    let proved = zycle(&["prove", "--pk", &pk, "--graph", &graph, "--salt", &salt, "--proof", &proof]);
+    assert_eq!(proved, (0, root.clone()));
+    assert_eq!(zycle(&["verify", "--vk", &vk, "--proof", &proof, "--commitment", &root]).0, 0);
+    assert_eq!(zycle(&["open", "--graph", &graph, "--salt", &salt, "--commitment", &root, "--row-tree"]).0, 0);
+
+    let revealed = zycle(&["reveal", "--graph", &graph, "--row", "1", "--salt", &salt, "--opening", &opening]);
+    assert_eq!(revealed, (0, root.clone()));
+    assert!(!fs::read_to_string(&opening).unwrap().contains(fs::read_to_string(&salt).unwrap().trim()));
+    assert_eq!(zycle(&["check-row", "--opening", &opening, "--commitment", &root]), (0, "row 1: 0,0,1".to_string()));
+    assert_eq!(zycle(&["check-row", "--opening", &opening, "--commitment", "1"]).0, 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,95 @@
+use ark_bls12_381::{fr::Fr, Bls12_381};
+use ark_groth16::Proof;
+use ark_serialize::{CanonicalDeserialize, Validate};
+use std::io;
+use wasm_bindgen::prelude::*;
+
+use zycle::bundle::ProofBundle;
+use zycle::{AcyclicityVerifier, ZycleError};
+
+// Verification of dense Groth16 proofs on BLS12-381 for browsers and edge runtimes. There is no
+// file system there, so keys and proofs are passed in as bytes. Keys are always validated, the
+// subgroup checks are what stops a malformed key from accepting forged proofs.
+
+// checks a JSON proof bundle written by `zycle prove` against a verifying key or prepared
+// verifying key written by `zycle setup`, against the given commitment or else the one in the bundle
+// returns false if the proof is rejected and throws on a malformed key or bundle
+#[wasm_bindgen(js_name = verifyBundle)]
+pub fn verify_bundle(vk: &[u8], bundle_json: &str, commitment: Option<String>) -> Result<bool, JsError> {
+    check_bundle(vk, bundle_json, commitment.as_deref()).map_err(|e| JsError::new(&e.to_string()))
+}
+
+// same as verifyBundle for a compressed proof written by `zycle prove --proof proof.bin`, which
+// does not hold its commitment
+#[wasm_bindgen(js_name = verifyProof)]
+pub fn verify_proof(vk: &[u8], proof: &[u8], commitment: &str) -> Result<bool, JsError> {
+    check_proof(vk, proof, commitment).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn check_bundle(vk: &[u8], bundle_json: &str, commitment: Option<&str>) -> Result<bool, ZycleError> {
+    let verifier = AcyclicityVerifier::from_bytes(vk, Validate::Yes)?;
+    let bundle = ProofBundle::from_json(bundle_json)?;
+    bundle.check_matches(&verifier)?;
+    let public_inputs = match commitment {
+        Some(commitment) => vec![parse_commitment(commitment)?],
+        None => bundle.public_inputs()?,
+    };
+    verifier.verify(&bundle.proof()?, &public_inputs)
+}
+
+fn check_proof(vk: &[u8], proof: &[u8], commitment: &str) -> Result<bool, ZycleError> {
+    let verifier = AcyclicityVerifier::from_bytes(vk, Validate::Yes)?;
+    let proof = Proof::<Bls12_381>::deserialize_compressed(proof)?;
+    verifier.verify(&proof, &[parse_commitment(commitment)?])
+}
+
+fn parse_commitment(commitment: &str) -> Result<Fr, ZycleError> {
+    commitment.parse().map_err(|_| {
+        io::Error::new(io::ErrorKind::InvalidInput, format!("invalid commitment {:?}", commitment)).into()
+    })
+}
+
+// runs natively with cargo test too, where nothing on the success path calls into JS
+#[cfg(test)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen_test::wasm_bindgen_test)]
+#[cfg_attr(not(target_arch = "wasm32"), test)]
+fn verify_in_wasm() {
+    use ark_serialize::{CanonicalSerialize, Compress};
+    use ark_std::rand::{rngs::StdRng, RngCore, SeedableRng};
+    use ark_std::test_rng;
+    use zycle::circuit::{CircuitVariant, CommitmentKind, PositionWidth};
+    use zycle::keys::{write_key, CircuitShape};
+    use zycle::AcyclicityProver;
+
+    let adj_matrix = [[false, true, false], [false, false, true], [false, false, false]];
+    let mut rng = StdRng::seed_from_u64(test_rng().next_u64());
+    let (prover, verifier) = AcyclicityProver::setup(3, &mut rng).unwrap();
+    let (proof, public_inputs) = prover.prove(&adj_matrix, &mut rng).unwrap();
+    let shape = CircuitShape::dense(3, PositionWidth::U8);
+    let mut vk = Vec::new();
+    write_key(verifier.verifying_key(), shape, Compress::Yes, &mut vk).unwrap();
+    let mut pvk = Vec::new();
+    write_key(verifier.prepared_verifying_key(), shape, Compress::No, &mut pvk).unwrap();
+    let bundle =
+        ProofBundle::new(&proof, &public_inputs, 3, CircuitVariant::TopoSort, CommitmentKind::Plain, None).unwrap();
+    let bundle_json = bundle.to_json().unwrap();
+    let mut proof_bytes = Vec::new();
+    proof.serialize_compressed(&mut proof_bytes).unwrap();
+    let commitment = public_inputs[0].to_string();
+
+    assert!(check_bundle(&vk, &bundle_json, None).unwrap());
+    assert!(check_bundle(&pvk, &bundle_json, Some(&commitment)).unwrap());
+    assert!(!check_bundle(&vk, &bundle_json, Some("1")).unwrap());
+    assert!(check_proof(&vk, &proof_bytes, &commitment).unwrap());
+    assert!(!check_proof(&pvk, &proof_bytes, "1").unwrap());
+    // a bundle for another graph size, a truncated key or proof and a bad commitment are refused
+    let other = ProofBundle { n: 4, ..bundle }.to_json().unwrap();
+    assert!(check_bundle(&vk, &other, None).is_err());
+    assert!(check_bundle(&vk[..vk.len() - 1], &bundle_json, None).is_err());
+    assert!(check_proof(&vk, &proof_bytes[1..], &commitment).is_err());
+    assert!(check_proof(&vk, &proof_bytes, "not a number").is_err());
+
+    // through the exported functions
+    assert!(verify_bundle(&vk, &bundle_json, None).unwrap());
+    assert!(verify_proof(&vk, &proof_bytes, &commitment).unwrap());
+}